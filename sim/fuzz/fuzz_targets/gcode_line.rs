@@ -0,0 +1,26 @@
+//! Fuzz target feeding arbitrary bytes through the G-code line buffer and parser
+//!
+//! Every line must either parse or be rejected with an error, without panicking.
+
+#![no_main]
+
+use esp_sandbox::gcode::parser::{Command, LineBuffer, parse_line};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut buffer = LineBuffer::<96>::new();
+    for &byte in data.iter().chain(b"\n") {
+        if let Some(Ok(line)) = buffer.push(byte)
+            && let Ok(Some(command)) = parse_line(line)
+        {
+            // feedrates which would stall the machine are rejected
+            if let Command::Move {
+                feedrate: Some(feedrate),
+                ..
+            } = command
+            {
+                assert!(feedrate > 0.0);
+            }
+        }
+    }
+});
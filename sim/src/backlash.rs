@@ -0,0 +1,134 @@
+//! Scenario moving a stepper with backlash compensation through a sequence of reversals
+//!
+//! Checks that each move emits its logical steps plus the backlash after every reversal,
+//! that DIR only changes on reversals, and that the reported position only counts the
+//! logical steps.
+
+use embassy_time::Instant;
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        Direction,
+        controller::Stepper,
+        driver::{DriverChip, Drv8825, StepDir},
+        generator::SoftwareStepGenerator,
+    },
+};
+
+use crate::{check_pulse_widths, gpio::Trace, time};
+
+// Inputs
+const BACKLASH_STEPS: u32 = 6;
+const MAX_VELOCITY: u32 = 1_000; // steps/s
+const ACCELERATION: u32 = 4_000; // steps/s²
+const STEP_PERIOD_US: u32 = 2_000;
+
+/// Relative moves (steps), and whether each is executed one step at a time (as when homing)
+/// rather than as a profile.
+const MOVES: [(i32, bool); 10] = [
+    (400, false),
+    (-200, false),
+    (-200, false),
+    (150, false),
+    (150, false),
+    (-300, false),
+    (1, false),
+    (-1, false),
+    (20, true),
+    (-20, true),
+];
+
+// Calculated values
+const MIN_PULSE_WIDTH_US: u64 = Drv8825::TIMING.min_pulse_width_ns.div_ceil(1_000) as u64;
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    // Initialize virtual motor control GPIO
+    let dir = trace.pin("dir", true);
+    let step = trace.pin("step", false);
+    let (dir_signal, step_signal) = (dir.signal(), step.signal());
+    let driver = StepDir::<Drv8825, _>::new(dir, step);
+    let mut stepper =
+        Stepper::new(driver, SoftwareStepGenerator::new()).with_backlash(BACKLASH_STEPS);
+
+    // (start, end) of each move (µs)
+    let spans = time::run(async {
+        let mut spans = Vec::new();
+        for (delta, single_steps) in MOVES {
+            stepper.move_by(delta).unwrap();
+            let start_us = Instant::now().as_micros();
+            if single_steps {
+                while stepper.step(STEP_PERIOD_US).await.unwrap() {}
+            } else {
+                let steps = stepper.remaining_steps();
+                let profile = TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, steps);
+                stepper.run(profile).await.unwrap();
+            }
+            spans.push((start_us, Instant::now().as_micros()));
+        }
+        spans
+    });
+
+    let mut failures = Vec::new();
+    let step_changes = trace.changes_of(step_signal);
+    let dir_changes = trace.changes_of(dir_signal);
+    let mut direction = Direction::Forward;
+    let mut position = 0;
+    for (index, ((delta, _), (start_us, end_us))) in MOVES.into_iter().zip(spans).enumerate() {
+        let move_direction = if delta > 0 {
+            Direction::Forward
+        } else {
+            Direction::Reverse
+        };
+        let reversed = move_direction != direction;
+        direction = move_direction;
+        position += delta;
+
+        let in_move = |time_us: u64| (start_us..end_us).contains(&time_us);
+        let pulses = step_changes
+            .iter()
+            .filter(|change| change.high() && in_move(change.time_us))
+            .count() as u32;
+        let expected = delta.unsigned_abs() + if reversed { BACKLASH_STEPS } else { 0 };
+        if pulses != expected {
+            failures.push(format!(
+                "move {index} ({delta:+}): {pulses} STEP pulses traced, {expected} expected"
+            ));
+        }
+
+        // DIR is set when the move is planned, just before it starts
+        let dir_set = dir_changes
+            .iter()
+            .rfind(|change| change.time_us <= start_us)
+            .map(|change| change.high())
+            .unwrap_or(true);
+        if dir_set != (direction == Direction::Forward) {
+            failures.push(format!("move {index} ({delta:+}): wrong DIR level"));
+        }
+    }
+    println!(
+        "{} moves, {} STEP pulses, {} DIR changes, final position {}",
+        MOVES.len(),
+        step_changes.iter().filter(|change| change.high()).count(),
+        dir_changes.len(),
+        stepper.position(),
+    );
+    if stepper.position() != position {
+        failures.push(format!(
+            "final position {}, {position} expected",
+            stepper.position()
+        ));
+    }
+    let reversals = MOVES
+        .windows(2)
+        .filter(|pair| (pair[0].0 > 0) != (pair[1].0 > 0))
+        .count();
+    if dir_changes.len() != reversals {
+        failures.push(format!(
+            "{} DIR changes traced, {reversals} expected",
+            dir_changes.len()
+        ));
+    }
+    check_pulse_widths(&step_changes, MIN_PULSE_WIDTH_US, &mut failures);
+    failures
+}
@@ -0,0 +1,149 @@
+//! Scenario microstepping a bipolar stepper from two H-bridges driven by virtual PWM channels
+//!
+//! Checks the const generated sine tables against the host's floating point sine, then moves
+//! forward and back by an electrical cycle at each resolution from 1/32 to 1/4 steps. The coil
+//! duties must never drive both inputs of an H-bridge at once, must keep a constant current
+//! vector magnitude, and must end each move at the phase of the final position.
+
+use std::f64::consts::TAU;
+
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        bipolar::{DualHBridge, FULL_SCALE, MAX_MICROSTEPS, PHASES, SINE, sine_table},
+        controller::Stepper,
+        driver::StepperDriver,
+        generator::SoftwareStepGenerator,
+    },
+};
+
+use crate::{gpio::Trace, time};
+
+// Inputs (matching src/bin/stepper_bipolar.rs)
+const MAX_DUTY: u16 = (1 << 10) - 1; // 10 bit LEDC duty
+const AMPLITUDE_PCT: u8 = 80;
+const MAX_VELOCITY: u32 = 800; // steps/s
+const ACCELERATION: u32 = 4_000; // steps/s²
+
+// Simulation inputs
+const RESOLUTIONS: [u16; 4] = [32, 16, 8, 4];
+const MAGNITUDE_TOLERANCE: f64 = 2.0; // duty counts
+
+// Calculated values
+const FULL_DUTY: f64 = (MAX_DUTY as u32 * AMPLITUDE_PCT as u32 / 100) as f64;
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    let mut failures = Vec::new();
+    check_table(&SINE, &mut failures);
+    check_table(&sine_table::<4>(), &mut failures);
+    check_table(&sine_table::<1024>(), &mut failures);
+
+    // Initialize virtual H-bridge inputs (coil A IN1, IN2, coil B IN1, IN2)
+    let inputs = [
+        trace.pwm("a_in1", MAX_DUTY),
+        trace.pwm("a_in2", MAX_DUTY),
+        trace.pwm("b_in1", MAX_DUTY),
+        trace.pwm("b_in2", MAX_DUTY),
+    ];
+    let signals = inputs.each_ref().map(|input| input.signal());
+    let [a_in1, a_in2, b_in1, b_in2] = inputs;
+    let driver = DualHBridge::new([a_in1, a_in2], [b_in1, b_in2]).with_amplitude(AMPLITUDE_PCT);
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+
+    // (expected, actual) phase after each move
+    let phases = time::run(async {
+        let mut phases = Vec::new();
+        let mut expected = 0;
+        for microsteps in RESOLUTIONS {
+            stepper.driver_mut().set_microsteps(microsteps).unwrap();
+            let stride = (MAX_MICROSTEPS / microsteps) as usize;
+            expected -= expected % stride;
+
+            // one electrical cycle plus a few steps, so the next resolution has to snap back
+            let cycle = 4 * microsteps as i32;
+            for delta in [cycle + 3, -cycle] {
+                stepper.move_by(delta).unwrap();
+                let steps = stepper.remaining_steps();
+                let profile = TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, steps);
+                stepper.run(profile).await.unwrap();
+                let offset = (delta * stride as i32).rem_euclid(PHASES as i32);
+                expected = (expected + offset as usize) % PHASES;
+                phases.push((expected, stepper.driver().phase()));
+            }
+        }
+        phases
+    });
+
+    for (index, (expected, actual)) in phases.iter().enumerate() {
+        if expected != actual {
+            failures.push(format!(
+                "move {index}: ended at phase {actual}, {expected} expected"
+            ));
+        }
+    }
+
+    // replay the duty changes, checking the state of both coils at each point in time
+    let changes = trace.changes();
+    let mut duties = [0; 4];
+    for group in changes.chunk_by(|a, b| a.time_us == b.time_us) {
+        let time_us = group[0].time_us;
+        for change in group {
+            let input = signals.iter().position(|&signal| signal == change.signal);
+            duties[input.unwrap()] = change.value;
+            if duties[0] != 0 && duties[1] != 0 || duties[2] != 0 && duties[3] != 0 {
+                failures.push(format!("both inputs of a coil driven at {time_us} us"));
+            }
+        }
+        let [a, b] = coil_duties(duties);
+        let magnitude = a.hypot(b);
+        if magnitude != 0.0 && (magnitude - FULL_DUTY).abs() > MAGNITUDE_TOLERANCE {
+            failures.push(format!(
+                "current vector magnitude {magnitude:.1} at {time_us} us, {FULL_DUTY} expected"
+            ));
+        }
+    }
+
+    // the final duties follow the cosine and sine of the final phase
+    let phase = stepper.driver().phase();
+    let angle = TAU * phase as f64 / PHASES as f64;
+    let expected = [angle.cos(), angle.sin()].map(|current| current * FULL_DUTY);
+    for (coil, (duty, expected)) in ["A", "B"]
+        .iter()
+        .zip(coil_duties(duties).iter().zip(expected))
+    {
+        if (duty - expected).abs() > 1.0 {
+            failures.push(format!(
+                "coil {coil} duty {duty} at phase {phase}, {expected:.1} expected"
+            ));
+        }
+    }
+
+    println!(
+        "{} moves, {} duty changes, final phase {phase}",
+        phases.len(),
+        changes.len(),
+    );
+    failures
+}
+
+/// Check a sine table against the host's sine, rounded to the nearest integer.
+fn check_table(table: &[i16], failures: &mut Vec<String>) {
+    let len = table.len();
+    for (index, &value) in table.iter().enumerate() {
+        let expected = (TAU * index as f64 / len as f64).sin() * FULL_SCALE as f64;
+        if (value as f64 - expected).abs() > 0.5 + 1e-9 {
+            failures.push(format!(
+                "sine table of {len} entries: entry {index} is {value}, {expected:.2} expected"
+            ));
+        }
+    }
+}
+
+/// Signed duties of coils A and B, from the duties of their IN1 and IN2 inputs.
+fn coil_duties(duties: [u16; 4]) -> [f64; 2] {
+    [
+        duties[0] as f64 - duties[1] as f64,
+        duties[2] as f64 - duties[3] as f64,
+    ]
+}
@@ -0,0 +1,284 @@
+//! Scenario running the supervised moves of the `stepper_closed_loop` demo with missed steps
+//!
+//! The motor shaft follows the STEP pulses, except for injected runs of missed steps, and
+//! drives a simulated quadrature encoder decoded by the library's decoder. Checks that missed
+//! steps within the fault threshold are corrected at the end of the move, that a stall aborts
+//! the move with a following error, and that the commanded position matches the shaft after
+//! every corrected move.
+
+use core::ops::Range;
+
+use embassy_time::Instant;
+use embedded_hal::digital::OutputPin;
+use esp_sandbox::{
+    encoder::{EncoderCount, QuadratureDecoder},
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        Direction, Error,
+        controller::Stepper,
+        driver::{DriverChip, Drv8825, StepDir, StepperDriver, Timing},
+        generator::SoftwareStepGenerator,
+        supervisor::{Supervisor, SupervisorConfig, SupervisorError},
+        units::{Microsteps, Steps},
+    },
+};
+
+use crate::{
+    check_pulse_widths,
+    gpio::{Trace, VirtualPin},
+    time,
+};
+
+// Inputs (matching src/bin/stepper_closed_loop.rs)
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const ENCODER_LINES_PER_REV: u32 = 600;
+const MAX_VELOCITY: u32 = 800; // steps/s
+const ACCELERATION: u32 = 2_000; // steps/s²
+const CORRECTION_PERIOD_US: u32 = 5_000;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const STEPS_PER_FULL_STEP: Steps = MICRO_STEP_MODE.steps_per_rev(1);
+const SUPERVISOR_CONFIG: SupervisorConfig = SupervisorConfig {
+    steps_per_rev: STEPS_PER_REV.get(),
+    counts_per_rev: 4 * ENCODER_LINES_PER_REV,
+    correction_threshold: STEPS_PER_FULL_STEP.times(2).get(),
+    fault_threshold: STEPS_PER_FULL_STEP.times(8).get(),
+};
+const MIN_PULSE_WIDTH_US: u64 = Drv8825::TIMING.min_pulse_width_ns.div_ceil(1_000) as u64;
+
+// Simulation inputs
+/// Relative moves (steps), each with the STEP pulses missed by the shaft (counted from the
+/// start of the move) and the expected result of the supervised move.
+const MOVES: [(i32, Range<u32>, Result<u32, SupervisorError>); 5] = [
+    (800, 200..210, Ok(10)),
+    (-800, 500..512, Ok(12)),
+    (800, 0..0, Ok(0)),
+    // stall: the shaft stops turning part way through the move
+    (
+        800,
+        300..u32::MAX,
+        Err(SupervisorError::FollowingError(
+            SUPERVISOR_CONFIG.fault_threshold as i32 + 1,
+        )),
+    ),
+    (-400, 0..0, Ok(0)),
+];
+
+/// Encoder channel levels (A, B) for each count modulo four, with A leading B forwards.
+const QUADRATURE: [(bool, bool); 4] = [(false, false), (true, false), (true, true), (false, true)];
+
+/// Driver whose motor shaft turns on each STEP pulse, except for missed pulses, and drives a
+/// quadrature encoder decoded into an [`EncoderCount`].
+struct EncodedShaft<'a, D> {
+    driver: D,
+    encoder_a: VirtualPin<'a>,
+    encoder_b: VirtualPin<'a>,
+    decoder: QuadratureDecoder,
+    count: &'a EncoderCount,
+    /// Shaft position (steps).
+    position: i32,
+    /// Encoder position of the shaft (counts).
+    counts: i32,
+    step_high: bool,
+    pulses: u32,
+    missed: Range<u32>,
+}
+
+impl<'a, D> EncodedShaft<'a, D> {
+    fn new(driver: D, trace: &'a Trace, count: &'a EncoderCount) -> Self {
+        Self {
+            driver,
+            encoder_a: trace.pin("encoder_a", false),
+            encoder_b: trace.pin("encoder_b", false),
+            decoder: QuadratureDecoder::new(false, false),
+            count,
+            position: 0,
+            counts: 0,
+            step_high: false,
+            pulses: 0,
+            missed: 0..0,
+        }
+    }
+
+    /// Miss the STEP pulses in `range`, counted from the next pulse.
+    fn miss(&mut self, range: Range<u32>) {
+        self.missed =
+            self.pulses.saturating_add(range.start)..self.pulses.saturating_add(range.end);
+    }
+
+    /// Turn the shaft one step, emitting every encoder edge passed.
+    fn turn(&mut self, direction: Direction) {
+        self.position += match direction {
+            Direction::Forward => 1,
+            Direction::Reverse => -1,
+        };
+        let counts_per_rev = i64::from(SUPERVISOR_CONFIG.counts_per_rev);
+        let scaled = i64::from(self.position) * counts_per_rev;
+        let target = scaled.div_euclid(i64::from(STEPS_PER_REV.get())) as i32;
+        while self.counts != target {
+            self.counts += (target - self.counts).signum();
+            let (a, b) = QUADRATURE[self.counts.rem_euclid(4) as usize];
+            self.encoder_a.set_state(a.into()).unwrap();
+            self.encoder_b.set_state(b.into()).unwrap();
+            match self.decoder.update(a, b) {
+                Ok(_) => self.count.set(self.decoder.count()),
+                Err(_) => self.count.record_error(),
+            }
+        }
+    }
+}
+
+impl<D: StepperDriver> StepperDriver for EncodedShaft<'_, D> {
+    fn timing(&self) -> Timing {
+        self.driver.timing()
+    }
+
+    fn direction(&self) -> Direction {
+        self.driver.direction()
+    }
+
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        self.driver.set_direction(direction)
+    }
+
+    fn set_step(&mut self, high: bool) -> Result<(), Error> {
+        self.driver.set_step(high)?;
+        if high && !self.step_high {
+            if !self.missed.contains(&self.pulses) {
+                self.turn(self.driver.direction());
+            }
+            self.pulses += 1;
+        }
+        self.step_high = high;
+        Ok(())
+    }
+
+    fn microsteps(&self) -> u16 {
+        self.driver.microsteps()
+    }
+
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error> {
+        self.driver.set_microsteps(microsteps)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.driver.set_enabled(enabled)
+    }
+
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error> {
+        self.driver.set_sleep(asleep)
+    }
+
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error> {
+        self.driver.set_reset(reset)
+    }
+
+    fn is_faulted(&mut self) -> Result<bool, Error> {
+        self.driver.is_faulted()
+    }
+}
+
+/// Outcome of one supervised move.
+struct Move {
+    start_us: u64,
+    end_us: u64,
+    result: Result<u32, SupervisorError>,
+    /// Commanded position minus shaft position once the move ended.
+    offset: i32,
+}
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    // Initialize virtual motor control GPIO and encoder
+    let dir = trace.pin("dir", true);
+    let step = trace.pin("step", false);
+    let step_signal = step.signal();
+    let count = EncoderCount::new();
+    let driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+    let shaft = EncodedShaft::new(driver, trace, &count);
+    let mut stepper = Stepper::new(shaft, SoftwareStepGenerator::new());
+    let mut supervisor = Supervisor::new(SUPERVISOR_CONFIG);
+    supervisor.align(stepper.position(), count.get());
+
+    // same sequence as the step_manager task, with missed steps injected
+    let moves = time::run(async {
+        let mut moves = Vec::new();
+        for (delta, missed, _) in MOVES {
+            stepper.driver_mut().miss(missed);
+            stepper.move_by(delta).unwrap();
+            let start_us = Instant::now().as_micros();
+            let profile =
+                TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, stepper.remaining_steps());
+            let result = supervisor
+                .run(&mut stepper, &count, profile, CORRECTION_PERIOD_US)
+                .await;
+            let end_us = Instant::now().as_micros();
+            let offset = stepper.position() - stepper.driver().position;
+            println!(
+                "move {delta:+}: {result:?}, position {}, following error {}",
+                stepper.position(),
+                supervisor.following_error(stepper.position(), count.get()),
+            );
+            if let Err(SupervisorError::FollowingError(_)) = result {
+                stepper.set_position(supervisor.measured_steps(count.get()));
+            }
+            moves.push(Move {
+                start_us,
+                end_us,
+                result,
+                offset,
+            });
+        }
+        moves
+    });
+
+    // Check the results and trace against the injected missed steps
+    let mut failures = Vec::new();
+    let step_changes = trace.changes_of(step_signal);
+    for (index, ((delta, missed, expected), outcome)) in MOVES.into_iter().zip(moves).enumerate() {
+        if outcome.result != expected {
+            failures.push(format!(
+                "move {index} ({delta:+}): {:?}, {expected:?} expected",
+                outcome.result
+            ));
+            continue;
+        }
+        let expected_pulses = match expected {
+            Ok(corrected) => delta.unsigned_abs() + corrected,
+            Err(SupervisorError::FollowingError(error)) => missed.start + error.unsigned_abs(),
+            Err(SupervisorError::Stepper(_)) => unreachable!(),
+        };
+        let pulses = step_changes
+            .iter()
+            .filter(|change| change.high())
+            .filter(|change| (outcome.start_us..outcome.end_us).contains(&change.time_us))
+            .count() as u32;
+        if pulses != expected_pulses {
+            failures.push(format!(
+                "move {index} ({delta:+}): {pulses} STEP pulses traced, {expected_pulses} expected"
+            ));
+        }
+        if expected.is_ok() && outcome.offset != 0 {
+            failures.push(format!(
+                "move {index} ({delta:+}): ended {} steps from the shaft position",
+                outcome.offset
+            ));
+        }
+    }
+    let shaft = stepper.driver().position;
+    if stepper.position() != shaft {
+        failures.push(format!(
+            "final position {}, shaft at {shaft}",
+            stepper.position()
+        ));
+    }
+    if count.errors() != 0 {
+        failures.push(format!("{} encoder errors", count.errors()));
+    }
+    check_pulse_widths(&step_changes, MIN_PULSE_WIDTH_US, &mut failures);
+    failures
+}
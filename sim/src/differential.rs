@@ -0,0 +1,297 @@
+//! Scenario driving a differential drive rover from two H-bridges on virtual PWM channels
+//!
+//! Checks the mixing of commands at the duty limit under each saturation policy, then drives
+//! a square (straight lines and pivot turns), a figure of eight and further arcs and pivots
+//! (including an arc too fast for the duty limit). The rover's pose is integrated from the
+//! recorded duties, taking wheel speeds to be proportional to duty as the drive assumes, and
+//! the change in pose over each move must match its geometry. The duties must never exceed
+//! the limit.
+
+use embassy_time::Instant;
+use esp_sandbox::motor::{
+    differential::{DifferentialDrive, DriveConfig, Saturation},
+    hbridge::{Dbh12, HBridge},
+};
+
+use crate::{
+    gpio::{Change, Trace},
+    time,
+};
+
+// Inputs (matching src/bin/dbh12_rover.rs)
+const MAX_DUTY: u16 = (1 << 8) - 1; // 8 bit LEDC duty
+const CONFIG: DriveConfig = DriveConfig {
+    track_width_mm: 150.0,
+    max_speed_mm_s: 500.0,
+    max_duty: 95,
+    saturation: Saturation::Scale,
+};
+
+// Simulation inputs
+const POSITION_TOLERANCE_PCT: f64 = 3.0; // of the path length
+const HEADING_TOLERANCE_PCT: f64 = 3.0; // of the angle turned
+const MIN_TOLERANCE: f64 = 1.0; // mm or deg
+
+/// A move of the rover.
+#[derive(Clone, Copy, Debug)]
+enum Move {
+    /// Distance (mm) and speed (mm/s).
+    Straight(f32, f32),
+    /// Angle (deg) and rate (deg/s).
+    Pivot(f32, f32),
+    /// Angle (deg), radius (mm) and speed (mm/s).
+    Arc(f32, f32, f32),
+}
+
+/// Moves driven, in order: the square and figure of eight of the demo, then an arc driven
+/// backwards, a pivot clockwise, and an arc which is too fast for the duty limit and must be
+/// slowed down rather than widened.
+const MOVES: [Move; 13] = [
+    Move::Straight(300.0, 200.0),
+    Move::Pivot(90.0, 90.0),
+    Move::Straight(300.0, 200.0),
+    Move::Pivot(90.0, 90.0),
+    Move::Straight(300.0, 200.0),
+    Move::Pivot(90.0, 90.0),
+    Move::Straight(300.0, 200.0),
+    Move::Pivot(90.0, 90.0),
+    Move::Arc(360.0, 200.0, 200.0),
+    Move::Arc(360.0, -200.0, 200.0),
+    Move::Arc(90.0, 100.0, -150.0),
+    Move::Pivot(-270.0, 120.0),
+    Move::Arc(360.0, 60.0, 1_000.0),
+];
+
+/// Number of moves in the square, which ends where it started.
+const SQUARE_MOVES: usize = 8;
+
+/// Position (mm) and heading (rad) of the rover, starting from the origin facing along x.
+#[derive(Clone, Copy, Debug, Default)]
+struct Pose {
+    x: f64,
+    y: f64,
+    heading: f64,
+}
+
+impl Pose {
+    /// Pose after driving for `dt_s` with the left and right wheels at constant speeds (mm/s).
+    fn advance(self, [left, right]: [f64; 2], dt_s: f64) -> Self {
+        let linear = (left + right) / 2.0;
+        let angular = (right - left) / CONFIG.track_width_mm as f64;
+        let heading = self.heading + angular * dt_s;
+        let (dx, dy) = if angular.abs() < 1e-12 {
+            (
+                linear * dt_s * self.heading.cos(),
+                linear * dt_s * self.heading.sin(),
+            )
+        } else {
+            let radius = linear / angular;
+            (
+                radius * (heading.sin() - self.heading.sin()),
+                radius * (self.heading.cos() - heading.cos()),
+            )
+        };
+        Self {
+            x: self.x + dx,
+            y: self.y + dy,
+            heading,
+        }
+    }
+
+    /// Change from `start` to this pose, in the frame of `start`.
+    fn relative_to(self, start: Self) -> Self {
+        let (dx, dy) = (self.x - start.x, self.y - start.y);
+        let (sin, cos) = start.heading.sin_cos();
+        Self {
+            x: dx * cos + dy * sin,
+            y: dy * cos - dx * sin,
+            heading: self.heading - start.heading,
+        }
+    }
+}
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    let mut failures = Vec::new();
+    check_saturation(&mut failures);
+
+    // Initialize virtual H-bridge inputs (left IN1, IN2, right IN1, IN2)
+    let inputs = [
+        trace.pwm("left_in1", MAX_DUTY),
+        trace.pwm("left_in2", MAX_DUTY),
+        trace.pwm("right_in1", MAX_DUTY),
+        trace.pwm("right_in2", MAX_DUTY),
+    ];
+    let signals = inputs.each_ref().map(|input| input.signal());
+    let [left_in1, left_in2, right_in1, right_in2] = inputs;
+    let left = HBridge::<Dbh12, _>::new(left_in1, left_in2);
+    let right = HBridge::<Dbh12, _>::new(right_in1, right_in2);
+    let mut drive = DifferentialDrive::new(left, right, CONFIG);
+
+    // (start, end) of each move (µs)
+    let spans = time::run(async {
+        let mut spans = Vec::new();
+        for step in MOVES {
+            let start_us = Instant::now().as_micros();
+            let result = match step {
+                Move::Straight(distance, speed) => drive.straight_by(distance, speed).await,
+                Move::Pivot(angle, rate) => drive.pivot_by(angle, rate).await,
+                Move::Arc(angle, radius, speed) => drive.arc_by(angle, radius, speed).await,
+            };
+            result.unwrap();
+            spans.push((start_us, Instant::now().as_micros()));
+        }
+        spans
+    });
+
+    // replay the duty changes into wheel speeds, checking each against the duty limit
+    let changes: Vec<Change> = trace.changes().to_vec();
+    let limit = (MAX_DUTY as u32 * CONFIG.max_duty as u32 / 100) as u16;
+    for change in changes.iter().filter(|change| change.value > limit) {
+        failures.push(format!(
+            "duty {} at {} us exceeds {limit} ({}%)",
+            change.value, change.time_us, CONFIG.max_duty
+        ));
+    }
+    let pose_at = |time_us: u64| {
+        let mut duties = [0; 4];
+        let mut pose = Pose::default();
+        let mut previous_us = 0;
+        for change in changes
+            .iter()
+            .take_while(|change| change.time_us <= time_us)
+        {
+            pose = pose.advance(wheel_speeds(duties), elapsed_s(previous_us, change.time_us));
+            previous_us = change.time_us;
+            let input = signals.iter().position(|&signal| signal == change.signal);
+            duties[input.unwrap()] = change.value;
+        }
+        pose.advance(wheel_speeds(duties), elapsed_s(previous_us, time_us))
+    };
+
+    for (index, (step, &(start_us, end_us))) in MOVES.iter().zip(&spans).enumerate() {
+        let actual = pose_at(end_us).relative_to(pose_at(start_us));
+        let (expected, path_mm, angle_deg) = expected_change(*step);
+        let position_error = (actual.x - expected.x).hypot(actual.y - expected.y);
+        let position_limit = (path_mm * POSITION_TOLERANCE_PCT / 100.0).max(MIN_TOLERANCE);
+        let heading_error = (actual.heading - expected.heading).to_degrees().abs();
+        let heading_limit = (angle_deg.abs() * HEADING_TOLERANCE_PCT / 100.0).max(MIN_TOLERANCE);
+        if position_error > position_limit || heading_error > heading_limit {
+            failures.push(format!(
+                "move {index} ({step:?}): moved ({:.1}, {:.1}) mm and turned {:.1} deg, \
+                 ({:.1}, {:.1}) mm and {:.1} deg expected",
+                actual.x,
+                actual.y,
+                actual.heading.to_degrees(),
+                expected.x,
+                expected.y,
+                expected.heading.to_degrees(),
+            ));
+        }
+    }
+
+    // the square brings the rover back to where it started
+    let pose = pose_at(spans[SQUARE_MOVES - 1].1);
+    if pose.x.hypot(pose.y) > 4.0 * 300.0 * POSITION_TOLERANCE_PCT / 100.0 {
+        failures.push(format!(
+            "square ended at ({:.1}, {:.1}) mm, origin expected",
+            pose.x, pose.y
+        ));
+    }
+
+    let pose = pose_at(spans[spans.len() - 1].1);
+    println!(
+        "{} moves, {} duty changes, final pose ({:.1}, {:.1}) mm at {:.1} deg",
+        MOVES.len(),
+        changes.len(),
+        pose.x,
+        pose.y,
+        pose.heading.to_degrees(),
+    );
+    failures
+}
+
+/// Check commands at and beyond the duty limit against each saturation policy.
+fn check_saturation(failures: &mut Vec<String>) {
+    let limit = CONFIG.speed_limit_mm_s();
+    let preserve = DriveConfig {
+        saturation: Saturation::PreserveTurn,
+        ..CONFIG
+    };
+    // (linear mm/s, angular deg/s)
+    let commands = [
+        (limit, 0.0),
+        (limit, 90.0),
+        (-limit, -90.0),
+        (100.0, 1_000.0),
+    ];
+    for (config, name) in [(CONFIG, "scale"), (preserve, "preserve turn")] {
+        for (linear, angular) in commands {
+            let mix = config.mix(linear, angular);
+            let label = format!("{name} mix of ({linear:.1} mm/s, {angular:.1} deg/s)");
+            if mix.left.abs() > CONFIG.max_duty || mix.right.abs() > CONFIG.max_duty {
+                failures.push(format!("{label}: duties {} and {}", mix.left, mix.right));
+            }
+            // the policy decides which of the velocities is kept where both cannot be
+            let kept = match config.saturation {
+                Saturation::Scale => mix.linear_mm_s * angular - mix.angular_deg_s * linear,
+                Saturation::PreserveTurn if angular.abs() < 500.0 => mix.angular_deg_s - angular,
+                Saturation::PreserveTurn => mix.linear_mm_s,
+            };
+            if kept.abs() > 1e-3 * limit {
+                failures.push(format!(
+                    "{label}: gave ({:.1} mm/s, {:.1} deg/s)",
+                    mix.linear_mm_s, mix.angular_deg_s
+                ));
+            }
+        }
+    }
+}
+
+/// Change in pose expected for a move, with the length of its path (mm) and the angle it
+/// turns through (deg).
+fn expected_change(step: Move) -> (Pose, f64, f64) {
+    match step {
+        Move::Straight(distance, _) => {
+            let distance = distance as f64;
+            let pose = Pose {
+                x: distance,
+                ..Pose::default()
+            };
+            (pose, distance.abs(), 0.0)
+        }
+        Move::Pivot(angle, _) => {
+            let pose = Pose {
+                heading: (angle as f64).to_radians(),
+                ..Pose::default()
+            };
+            let path = (angle as f64).to_radians().abs() * CONFIG.track_width_mm as f64 / 2.0;
+            (pose, path, angle as f64)
+        }
+        Move::Arc(angle, radius, speed) => {
+            // turning left for positive radii when driving forward, along a circle of the
+            // signed radius either way
+            let radius = radius as f64;
+            let turned = (angle as f64).abs().to_radians() * (radius * speed as f64).signum();
+            let pose = Pose {
+                x: radius * turned.sin(),
+                y: radius * (1.0 - turned.cos()),
+                heading: turned,
+            };
+            (pose, radius.abs() * turned.abs(), turned.to_degrees())
+        }
+    }
+}
+
+/// Wheel speeds (mm/s) from the duties of the left and right IN1 and IN2 inputs.
+fn wheel_speeds(duties: [u16; 4]) -> [f64; 2] {
+    let speed = |forward: u16, reverse: u16| {
+        (forward as f64 - reverse as f64) / MAX_DUTY as f64 * CONFIG.max_speed_mm_s as f64
+    };
+    [speed(duties[0], duties[1]), speed(duties[2], duties[3])]
+}
+
+/// Time between two trace times (s).
+fn elapsed_s(from_us: u64, to_us: u64) -> f64 {
+    (to_us - from_us) as f64 / 1e6
+}
@@ -0,0 +1,190 @@
+//! Virtual GPIO and PWM channels recording changes against simulated time
+
+use core::{
+    cell::{Ref, RefCell},
+    convert::Infallible,
+};
+
+use embassy_time::Instant;
+use embedded_hal::{
+    digital::{self, OutputPin},
+    pwm::{self, SetDutyCycle},
+};
+
+/// A recorded signal.
+pub struct Signal {
+    /// Name shown in waveform viewers.
+    pub name: &'static str,
+    /// Width in bits (1 for a pin).
+    pub width: u8,
+    /// Value before the first change.
+    pub initial: u16,
+}
+
+/// A change of value on one signal.
+#[derive(Clone, Copy, Debug)]
+pub struct Change {
+    /// Simulated time of the change (µs).
+    pub time_us: u64,
+    /// Index of the signal in [`Trace::signals`].
+    pub signal: usize,
+    /// Value after the change (0 or 1 for a pin).
+    pub value: u16,
+}
+
+impl Change {
+    /// Whether a pin is high after the change.
+    pub fn high(&self) -> bool {
+        self.value != 0
+    }
+}
+
+/// Record of the changes on a set of virtual pins and PWM channels, in time order.
+#[derive(Default)]
+pub struct Trace {
+    signals: RefCell<Vec<Signal>>,
+    changes: RefCell<Vec<Change>>,
+}
+
+impl Trace {
+    /// Create an empty trace.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a signal, returning an output pin driving it.
+    pub fn pin(&self, name: &'static str, initial: bool) -> VirtualPin<'_> {
+        VirtualPin {
+            trace: self,
+            signal: self.add(name, 1, initial.into()),
+            high: initial,
+        }
+    }
+
+    /// Add a signal, returning a PWM channel driving its duty cycle (initially zero).
+    pub fn pwm(&self, name: &'static str, max_duty: u16) -> VirtualPwm<'_> {
+        VirtualPwm {
+            trace: self,
+            signal: self.add(name, 16, 0),
+            max_duty,
+            duty: 0,
+        }
+    }
+
+    /// Add a signal, returning its index.
+    fn add(&self, name: &'static str, width: u8, initial: u16) -> usize {
+        let mut signals = self.signals.borrow_mut();
+        signals.push(Signal {
+            name,
+            width,
+            initial,
+        });
+        signals.len() - 1
+    }
+
+    /// Record a change of value.
+    fn record(&self, signal: usize, value: u16) {
+        self.changes.borrow_mut().push(Change {
+            time_us: Instant::now().as_micros(),
+            signal,
+            value,
+        });
+    }
+
+    /// Signals in the trace.
+    pub fn signals(&self) -> Ref<'_, [Signal]> {
+        Ref::map(self.signals.borrow(), Vec::as_slice)
+    }
+
+    /// Changes on all signals, in time order.
+    pub fn changes(&self) -> Ref<'_, [Change]> {
+        Ref::map(self.changes.borrow(), Vec::as_slice)
+    }
+
+    /// Latest value of a signal.
+    pub fn value(&self, signal: usize) -> u16 {
+        let changes = self.changes();
+        let latest = changes.iter().rev().find(|change| change.signal == signal);
+        latest.map_or(self.signals()[signal].initial, |change| change.value)
+    }
+
+    /// Changes on one signal, in time order.
+    pub fn changes_of(&self, signal: usize) -> Vec<Change> {
+        self.changes()
+            .iter()
+            .filter(|change| change.signal == signal)
+            .copied()
+            .collect()
+    }
+}
+
+/// Output pin recording its level changes into a [`Trace`].
+pub struct VirtualPin<'a> {
+    trace: &'a Trace,
+    signal: usize,
+    high: bool,
+}
+
+impl VirtualPin<'_> {
+    /// Index of the pin's signal in [`Trace::signals`].
+    pub fn signal(&self) -> usize {
+        self.signal
+    }
+
+    fn set_level(&mut self, high: bool) {
+        if high != self.high {
+            self.high = high;
+            self.trace.record(self.signal, high.into());
+        }
+    }
+}
+
+impl digital::ErrorType for VirtualPin<'_> {
+    type Error = Infallible;
+}
+
+impl OutputPin for VirtualPin<'_> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_level(false);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_level(true);
+        Ok(())
+    }
+}
+
+/// PWM channel recording its duty cycle changes into a [`Trace`].
+pub struct VirtualPwm<'a> {
+    trace: &'a Trace,
+    signal: usize,
+    max_duty: u16,
+    duty: u16,
+}
+
+impl VirtualPwm<'_> {
+    /// Index of the channel's signal in [`Trace::signals`].
+    pub fn signal(&self) -> usize {
+        self.signal
+    }
+}
+
+impl pwm::ErrorType for VirtualPwm<'_> {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for VirtualPwm<'_> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.max_duty
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        assert!(duty <= self.max_duty, "duty cycle {duty} out of range");
+        if duty != self.duty {
+            self.duty = duty;
+            self.trace.record(self.signal, duty);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,24 @@
+//! defmt logger discarding all output
+//!
+//! The library logs with defmt, whose frames can only be decoded by a probe-rs host. The
+//! simulator reports on stdout instead, so defmt output is dropped.
+
+#[defmt::global_logger]
+struct Logger;
+
+unsafe impl defmt::Logger for Logger {
+    fn acquire() {}
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {}
+
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+defmt::timestamp!("{=u64:us}", embassy_time::Instant::now().as_micros());
+
+#[defmt::panic_handler]
+fn panic() -> ! {
+    core::panic!("defmt panic")
+}
@@ -0,0 +1,293 @@
+//! Scenario controlling the speed of a simulated DC motor through an H-bridge
+//!
+//! The motor is modelled as a first order system: its speed approaches a steady state
+//! proportional to the duty (less a deadband for friction), with a fixed time constant. The
+//! model's gain is deliberately lower than the controller's feed-forward assumes, so the
+//! integral term has to make up the difference. The model turns the simulated shaft, whose
+//! position is counted as the quadrature encoder or hall sensor of the demo would count it.
+//!
+//! The target steps through a sequence including an unreachable speed and a reversal. The
+//! motor must settle within tolerance of each reachable target in time and without excessive
+//! overshoot (in particular straight after saturating, which the integral would otherwise
+//! wind up during), the duty must stay within the controller limits, and the tachometer must
+//! agree with the model to within one count per period.
+
+use core::cell::Cell;
+
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Instant, Timer};
+use esp_sandbox::{
+    encoder::EncoderCount,
+    motor::{
+        Error,
+        hbridge::{Dbh12, HBridge},
+        pid::{Pid, PidGains},
+        speed::{SharedSpeed, SpeedController, SpeedSensor, Tachometer},
+    },
+};
+
+use crate::{gpio::Trace, time};
+
+// Inputs (matching src/bin/dbh12_speed.rs)
+const MAX_DUTY: u16 = (1 << 8) - 1; // 8 bit LEDC duty
+const PWM_MAX: i8 = 95;
+const LOOP_PERIOD_MS: u64 = 10;
+const ENCODER_LINES_PER_REV: u32 = 11;
+const GEAR_RATIO: u32 = 30;
+const NO_LOAD_RPM: f32 = 300.0; // at full duty
+const GAINS: PidGains = PidGains {
+    kp: 0.15,
+    ki: 1.5,
+    kd: 0.0,
+    kff: 100.0 / NO_LOAD_RPM,
+};
+
+// Simulation inputs
+const MODEL_RPM_PER_PCT: f64 = 2.7; // loaded, so below the no load speed
+const MODEL_DEADBAND_PCT: f64 = 5.0;
+const MODEL_TIME_CONSTANT_S: f64 = 0.08;
+const SAMPLE_PERIOD_MS: u64 = 5;
+const SETTLE_TIME_MS: u64 = 700;
+const SETTLE_TOLERANCE_RPM: f64 = 6.0;
+const MAX_OVERSHOOT_PCT: f64 = 15.0;
+
+/// Target speeds (rpm) and how long each is held (ms). Targets beyond the speed reachable at
+/// PWM_MAX are only checked for saturating the duty.
+const TARGETS: [(f32, u64); 7] = [
+    (150.0, 1_500),
+    (220.0, 1_500),
+    (400.0, 1_500),
+    (100.0, 1_500),
+    (-150.0, 2_000),
+    (-60.0, 1_500),
+    (0.0, 1_000),
+];
+
+// Calculated values
+const COUNTS_PER_REV: u32 = 4 * ENCODER_LINES_PER_REV * GEAR_RATIO;
+const PULSES_PER_REV: u32 = ENCODER_LINES_PER_REV * GEAR_RATIO;
+const MAX_RPM: f64 = MODEL_RPM_PER_PCT * (PWM_MAX as f64 - MODEL_DEADBAND_PCT);
+const MAX_DUTY_AT_LIMIT: u16 = (MAX_DUTY as u32 * PWM_MAX as u32 / 100) as u16;
+
+/// State of the simulated motor.
+#[derive(Default)]
+struct Model {
+    /// Output shaft speed (rpm).
+    rpm: Cell<f64>,
+    /// Output shaft position (revolutions).
+    revs: Cell<f64>,
+    /// Distance turned in either direction (revolutions), as counted by a hall sensor.
+    distance: Cell<f64>,
+    /// Largest difference between a tachometer reading and the model's average speed over
+    /// the same period, as a multiple of the tachometer resolution.
+    tachometer_error: Cell<f64>,
+}
+
+impl Model {
+    /// Advance the model by `dt_s` at a constant duty (%), returning the average speed (rpm).
+    fn advance(&self, duty_pct: f64, dt_s: f64) -> f64 {
+        let drive = (duty_pct.abs() - MODEL_DEADBAND_PCT).max(0.0);
+        let steady = MODEL_RPM_PER_PCT * drive.copysign(duty_pct);
+        let start = self.rpm.get();
+        let decay = (-dt_s / MODEL_TIME_CONSTANT_S).exp();
+        self.rpm.set(steady + (start - steady) * decay);
+
+        // integral of the exponential approach over the interval
+        let revs =
+            (steady * dt_s + (start - steady) * MODEL_TIME_CONSTANT_S * (1.0 - decay)) / 60.0;
+        self.revs.set(self.revs.get() + revs);
+        self.distance.set(self.distance.get() + revs.abs());
+        revs * 60.0 / dt_s
+    }
+}
+
+/// Tachometer on the simulated motor, advancing the model to the time of each read.
+struct ModelSensor<'a> {
+    model: &'a Model,
+    trace: &'a Trace,
+    /// IN1 and IN2 signals of the H-bridge, giving the duty applied to the motor.
+    inputs: [usize; 2],
+    count: &'a EncoderCount,
+    tachometer: Tachometer<'a>,
+    counts_per_rev: u32,
+}
+
+impl SpeedSensor for ModelSensor<'_> {
+    fn is_directional(&self) -> bool {
+        self.tachometer.is_directional()
+    }
+
+    fn reset(&mut self) {
+        self.tachometer.reset();
+    }
+
+    fn read_rpm(&mut self, elapsed: Duration) -> Result<f32, Error> {
+        let [in1, in2] = self.inputs.map(|signal| self.trace.value(signal) as f64);
+        let duty_pct = (in1 - in2) * 100.0 / MAX_DUTY as f64;
+        let dt_s = elapsed.as_micros() as f64 / 1e6;
+        let average = self.model.advance(duty_pct, dt_s);
+
+        let (revs, expected) = if self.tachometer.is_directional() {
+            (self.model.revs.get(), average)
+        } else {
+            (self.model.distance.get(), average.abs())
+        };
+        self.count
+            .set((revs * self.counts_per_rev as f64).floor() as i32);
+        let rpm = self.tachometer.read_rpm(elapsed)?;
+
+        let resolution = 60.0 / (self.counts_per_rev as f64 * dt_s);
+        let error = (rpm as f64 - expected).abs() / resolution;
+        let worst = &self.model.tachometer_error;
+        worst.set(worst.get().max(error));
+        Ok(rpm)
+    }
+}
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    let mut failures = Vec::new();
+    for (name, directional) in [("quadrature", true), ("hall", false)] {
+        run_sensor(trace, name, directional, &mut failures);
+    }
+    failures
+}
+
+/// Run the target sequence with a quadrature encoder or hall sensor tachometer.
+fn run_sensor(trace: &Trace, name: &'static str, directional: bool, failures: &mut Vec<String>) {
+    // Initialize virtual H-bridge inputs
+    let (in1, in2) = if directional {
+        (
+            trace.pwm("quadrature_in1", MAX_DUTY),
+            trace.pwm("quadrature_in2", MAX_DUTY),
+        )
+    } else {
+        (
+            trace.pwm("hall_in1", MAX_DUTY),
+            trace.pwm("hall_in2", MAX_DUTY),
+        )
+    };
+    let inputs = [in1.signal(), in2.signal()];
+    let mut motor = HBridge::<Dbh12, _>::new(in1, in2);
+
+    let model = Model::default();
+    let count = EncoderCount::new();
+    let (tachometer, counts_per_rev) = if directional {
+        (
+            Tachometer::quadrature(&count, COUNTS_PER_REV),
+            COUNTS_PER_REV,
+        )
+    } else {
+        (Tachometer::pulses(&count, PULSES_PER_REV), PULSES_PER_REV)
+    };
+    let sensor = ModelSensor {
+        model: &model,
+        trace,
+        inputs,
+        count: &count,
+        tachometer,
+        counts_per_rev,
+    };
+    let pid = Pid::new(GAINS, -PWM_MAX as f32, PWM_MAX as f32);
+    let period = Duration::from_millis(LOOP_PERIOD_MS);
+    let mut controller = SpeedController::new(pid, sensor, period);
+    let shared = SharedSpeed::new();
+
+    // model speed (rpm) as of the latest loop period, sampled through each target
+    let script = async {
+        let mut samples = Vec::new();
+        for (target, hold_ms) in TARGETS {
+            shared.set_target(target);
+            let start = Instant::now();
+            let mut segment = Vec::new();
+            while start.elapsed() < Duration::from_millis(hold_ms) {
+                Timer::after_millis(SAMPLE_PERIOD_MS).await;
+                segment.push((start.elapsed().as_millis(), model.rpm.get()));
+            }
+            samples.push((segment, shared.duty()));
+        }
+        samples
+    };
+    let samples = time::run(async {
+        match select(controller.run(&mut motor, &shared), script).await {
+            Either::First(e) => panic!("speed loop failed: {e:?}"),
+            Either::Second(samples) => samples,
+        }
+    });
+
+    let mut previous = 0.0;
+    for ((target, _), (segment, duty)) in TARGETS.iter().zip(&samples) {
+        let target = *target as f64;
+        let label = format!("{name} target {target} rpm");
+        if target.abs() > MAX_RPM {
+            // unreachable, so the duty should be pinned at the limit
+            if duty.unsigned_abs() != PWM_MAX.unsigned_abs() {
+                failures.push(format!("{label}: duty {duty}%, {PWM_MAX}% expected"));
+            }
+        } else {
+            check_segment(&label, previous, target, segment, failures);
+        }
+        previous = segment.last().map_or(previous, |&(_, rpm)| rpm);
+    }
+
+    // both inputs stay within the controller limits
+    for signal in inputs {
+        let peak = trace
+            .changes_of(signal)
+            .iter()
+            .map(|change| change.value)
+            .max();
+        if let Some(peak) = peak.filter(|&peak| peak > MAX_DUTY_AT_LIMIT) {
+            failures.push(format!(
+                "{name}: duty {peak} exceeds {MAX_DUTY_AT_LIMIT} ({PWM_MAX}%)"
+            ));
+        }
+    }
+
+    let error = model.tachometer_error.get();
+    if error > 1.0 + 1e-6 {
+        failures.push(format!(
+            "{name}: tachometer off by {error:.2} counts from the model"
+        ));
+    }
+    println!(
+        "{name}: {} targets, final speed {:.1} rpm, tachometer error {error:.2} counts",
+        TARGETS.len(),
+        model.rpm.get(),
+    );
+}
+
+/// Check the model speed (rpm) sampled after a step from `start` to `target` rpm settles in
+/// time and within the overshoot limit.
+fn check_segment(
+    label: &str,
+    start: f64,
+    target: f64,
+    samples: &[(u64, f64)],
+    failures: &mut Vec<String>,
+) {
+    let settled = samples
+        .iter()
+        .rposition(|&(_, rpm)| (rpm - target).abs() > SETTLE_TOLERANCE_RPM)
+        .map_or(Some(0), |index| samples.get(index + 1).map(|&(ms, _)| ms));
+    match settled {
+        Some(ms) if ms <= SETTLE_TIME_MS => {}
+        Some(ms) => failures.push(format!(
+            "{label}: settled after {ms} ms, {SETTLE_TIME_MS} ms allowed"
+        )),
+        None => failures.push(format!("{label}: never settled")),
+    }
+
+    // overshoot beyond the target, in the direction of the step
+    let step = target - start;
+    let overshoot = samples
+        .iter()
+        .map(|&(_, rpm)| (rpm - target) * step.signum())
+        .fold(0.0, f64::max);
+    let limit = step.abs() * MAX_OVERSHOOT_PCT / 100.0;
+    if overshoot > limit.max(SETTLE_TOLERANCE_RPM) {
+        failures.push(format!(
+            "{label}: overshoot {overshoot:.1} rpm, {limit:.1} rpm allowed"
+        ));
+    }
+}
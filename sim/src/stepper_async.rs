@@ -0,0 +1,90 @@
+//! Scenario running the rotation cycle of the `stepper_async` demo ([`cycle::run`])
+//!
+//! Checks the step count and duration of each rotation against the motion profile, and the
+//! STEP pulse widths against the driver's minimum.
+
+use core::ops::ControlFlow;
+
+use embassy_time::Timer;
+use esp_sandbox::stepper::{
+    cycle::{self, MICRO_STEP_MODE, NUM_STEPS, RUN_TIME_US, Rotation},
+    driver::{DriverChip, Drv8825, StepDir},
+};
+
+use crate::{check_pulse_widths, gpio::Trace, time};
+
+// Simulation inputs
+const NUM_ROTATIONS: u32 = 2;
+
+// Calculated values
+const MIN_PULSE_WIDTH_US: u64 = Drv8825::TIMING.min_pulse_width_ns.div_ceil(1_000) as u64;
+
+/// Run the scenario, returning a description of each failed check.
+pub fn run(trace: &Trace) -> Vec<String> {
+    // Initialize virtual motor control GPIO
+    let dir = trace.pin("dir", true);
+    let step = trace.pin("step", false);
+    let step_signal = step.signal();
+    let mut driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+
+    // same sequence as the pwm_manager task, for a fixed number of rotations
+    let rotations = time::run(async {
+        Timer::after_millis(100).await;
+        let mut rotations = Vec::new();
+        cycle::run(&mut driver, |rotation, stats| {
+            println!(
+                "rotation {}: {} steps in {} us, period deviation {}..={} us, {} overruns",
+                rotation.index,
+                rotation.steps,
+                (rotation.end - rotation.start).as_micros(),
+                stats.min().unwrap(),
+                stats.max().unwrap(),
+                stats.overruns(),
+            );
+            rotations.push(*rotation);
+            if rotations.len() < NUM_ROTATIONS as usize {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })
+        .await
+        .unwrap();
+        rotations
+    });
+
+    // Check the trace against the profile
+    let mut failures = Vec::new();
+    let step_changes = trace.changes_of(step_signal);
+    for Rotation {
+        index,
+        steps,
+        start,
+        end,
+    } in rotations
+    {
+        let (start_us, end_us) = (start.as_micros(), end.as_micros());
+        let rising_edges = step_changes
+            .iter()
+            .filter(|change| change.high())
+            .filter(|change| (start_us..end_us).contains(&change.time_us))
+            .count();
+        if steps != NUM_STEPS.get() || rising_edges != NUM_STEPS.get() as usize {
+            failures.push(format!(
+                "rotation {index}: {steps} steps reported, {rising_edges} STEP pulses traced, \
+                 {} expected",
+                NUM_STEPS.get(),
+            ));
+        }
+        let duration_us = end_us - start_us;
+        if duration_us != RUN_TIME_US {
+            failures.push(format!(
+                "rotation {index}: took {duration_us} us, {RUN_TIME_US} us expected"
+            ));
+        }
+    }
+    check_pulse_widths(&step_changes, MIN_PULSE_WIDTH_US, &mut failures);
+    failures
+}
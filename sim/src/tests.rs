@@ -0,0 +1,18 @@
+//! Unit tests of the library's host-independent logic
+
+mod axis;
+mod controller;
+mod encoder;
+mod gcode;
+mod hbridge;
+mod homing;
+mod interpolation;
+mod jitter;
+mod jog;
+mod multi_axis;
+mod queue;
+mod rmt_encoder;
+mod scurve;
+mod tmc2209;
+mod trapezoidal;
+mod uln2003;
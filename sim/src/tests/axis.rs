@@ -0,0 +1,79 @@
+//! Tests of axes positioned in mechanical units, on virtual pins
+
+use esp_sandbox::stepper::{
+    axis::{Axis, AxisConfig, GearRatio, Transmission},
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    units::Steps,
+};
+
+use crate::{
+    gpio::{Trace, VirtualPin},
+    time,
+};
+
+/// Rotary axis geared 3:1, at 80/3 steps per degree, so that moves of 0.1 degree are a
+/// fraction of a step which never rounds to a half.
+const CONFIG: AxisConfig = AxisConfig::new(
+    Steps::new(3_200),
+    GearRatio::new(60, 20),
+    Transmission::Rotary,
+);
+const STEPS_PER_DEG: f64 = 80.0 / 3.0;
+const VELOCITY: f32 = 90.0; // deg/s
+const ACCELERATION: f32 = 900.0; // deg/s²
+
+type TestAxis<'a> = Axis<StepDir<Drv8825, VirtualPin<'a>>, SoftwareStepGenerator>;
+
+fn axis(trace: &Trace) -> TestAxis<'_> {
+    let driver = StepDir::<Drv8825, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    Axis::new(Stepper::new(driver, SoftwareStepGenerator::new()), CONFIG)
+}
+
+/// Commanded position less the stepper target (steps).
+fn residual(axis: &TestAxis) -> f64 {
+    f64::from(axis.target()) * STEPS_PER_DEG - f64::from(axis.stepper().target())
+}
+
+#[test]
+fn residual_stays_bounded_over_many_moves() {
+    let trace = Trace::new();
+    let mut axis = axis(&trace);
+    let moves = [0.1, 0.1, -0.35, 0.7, -0.05, 0.25];
+    let mut commanded = 0.0;
+    for index in 0..100_000 {
+        let units = moves[index % moves.len()];
+        axis.move_by(units).unwrap();
+        commanded += f64::from(units);
+        assert!(residual(&axis).abs() <= 0.5 + 1e-3, "move {index}");
+    }
+
+    // the step target is where the moves add up to, rounding errors having not accumulated
+    let expected = (commanded * STEPS_PER_DEG).round() as i32;
+    assert_eq!(axis.stepper().target(), expected);
+}
+
+#[test]
+fn fractional_moves_step_the_sum() {
+    let trace = Trace::new();
+    let mut axis = axis(&trace);
+    time::run(async {
+        // each move is 2 2/3 steps, taken as 3, 2 then 3 steps
+        let mut taken = Vec::new();
+        for _ in 0..3_000 {
+            axis.move_by(0.1).unwrap();
+            taken.push(axis.run(VELOCITY, ACCELERATION).await.unwrap());
+        }
+        assert!(taken.iter().all(|steps| (2..=3).contains(steps)));
+        assert_eq!(taken.iter().sum::<u32>(), 8_000);
+        assert_eq!(axis.stepper().position(), 8_000);
+        assert!((axis.position() - 300.0).abs() < 1e-3);
+
+        // absolute moves replace the residual carried over by relative moves
+        axis.move_to(0.05).unwrap();
+        axis.run(VELOCITY, ACCELERATION).await.unwrap();
+        assert_eq!(axis.stepper().position(), 1);
+        assert!(residual(&axis).abs() <= 0.5);
+    });
+}
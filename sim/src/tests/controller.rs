@@ -0,0 +1,141 @@
+//! Tests of the stepper controller's position tracking, on virtual pins
+
+use core::iter;
+
+use esp_sandbox::stepper::{
+    Direction, Error,
+    controller::Stepper,
+    driver::{Drv8825, StepDir, StepperDriver},
+    generator::{Interrupted, SoftwareStepGenerator, StepGenerator},
+};
+
+use crate::{gpio::Trace, time};
+
+const STEP_PERIOD_US: u32 = 1_000;
+
+/// Generator taking at most `limit` steps, then failing with a peripheral error.
+struct FailingGenerator {
+    limit: u32,
+    /// Steps reported as taken when failing, which may differ from those actually taken.
+    reported: u32,
+}
+
+impl StepGenerator for FailingGenerator {
+    async fn step<D: StepperDriver>(
+        &mut self,
+        driver: &mut D,
+        period_us: u32,
+    ) -> Result<(), Error> {
+        SoftwareStepGenerator::new().step(driver, period_us).await
+    }
+
+    async fn steps<D: StepperDriver>(
+        &mut self,
+        driver: &mut D,
+        periods: impl IntoIterator<Item = u32>,
+    ) -> Result<u32, Interrupted> {
+        for period_us in periods.into_iter().take(self.limit as usize) {
+            self.step(driver, period_us)
+                .await
+                .map_err(|error| Interrupted { steps: 0, error })?;
+        }
+        Err(Interrupted {
+            steps: self.reported,
+            error: Error::Peripheral,
+        })
+    }
+}
+
+#[test]
+fn counts_steps_in_each_direction() {
+    let trace = Trace::new();
+    let dir = trace.pin("dir", true);
+    let step = trace.pin("step", false);
+    let (dir_signal, step_signal) = (dir.signal(), step.signal());
+    let driver = StepDir::<Drv8825, _, _>::new(dir, step);
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+
+    // (target, steps taken by the move, DIR level)
+    let moves = [
+        (10, 10, true),
+        (-5, 15, false),
+        (-5, 0, false),
+        (3, 8, true),
+    ];
+    time::run(async {
+        for (target, steps, forward) in moves {
+            let edges = trace.changes_of(step_signal).len();
+            stepper.move_to(target).unwrap();
+            let taken = stepper.run(iter::repeat(STEP_PERIOD_US)).await.unwrap();
+            assert_eq!(taken, steps, "move to {target}");
+            assert_eq!(stepper.position(), target);
+            assert!(!stepper.is_moving());
+            assert_eq!(
+                trace.changes_of(step_signal).len() - edges,
+                2 * steps as usize
+            );
+            assert_eq!(trace.value(dir_signal) != 0, forward, "move to {target}");
+        }
+
+        // single steps, and steps beyond the target are refused
+        stepper.move_by(-2).unwrap();
+        assert_eq!(stepper.direction(), Direction::Reverse);
+        assert!(stepper.step(STEP_PERIOD_US).await.unwrap());
+        assert!(stepper.step(STEP_PERIOD_US).await.unwrap());
+        assert!(!stepper.step(STEP_PERIOD_US).await.unwrap());
+        assert_eq!(stepper.position(), 1);
+    });
+}
+
+#[test]
+fn run_stops_at_end_of_periods() {
+    let trace = Trace::new();
+    let driver = StepDir::<Drv8825, _, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+    time::run(async {
+        stepper.move_to(-100).unwrap();
+        let taken = stepper.run(iter::repeat_n(STEP_PERIOD_US, 30)).await;
+        assert_eq!(taken, Ok(30));
+        assert_eq!(stepper.position(), -30);
+        assert_eq!(stepper.remaining_steps(), 70);
+    });
+}
+
+#[test]
+fn generator_error_keeps_steps_taken() {
+    let trace = Trace::new();
+    let driver = StepDir::<Drv8825, _, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    let generator = FailingGenerator {
+        limit: 7,
+        reported: 7,
+    };
+    let mut stepper = Stepper::new(driver, generator);
+    time::run(async {
+        stepper.move_to(20).unwrap();
+        let result = stepper.run(iter::repeat(STEP_PERIOD_US)).await;
+        assert_eq!(result, Err(Error::Peripheral));
+        assert_eq!(stepper.position(), 7);
+        // the rest of the move is abandoned
+        assert!(!stepper.is_moving());
+    });
+}
+
+#[test]
+fn position_overflow_is_an_error() {
+    let trace = Trace::new();
+    let driver = StepDir::<Drv8825, _, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    // a generator reporting more steps than it was given can push the position out of range
+    let generator = FailingGenerator {
+        limit: 1,
+        reported: 3,
+    };
+    let mut stepper = Stepper::new(driver, generator);
+    time::run(async {
+        stepper.set_position(i32::MAX - 1);
+        stepper.move_to(i32::MAX).unwrap();
+        let result = stepper.run(iter::repeat(STEP_PERIOD_US)).await;
+        assert_eq!(result, Err(Error::PositionOverflow));
+        assert_eq!(stepper.position(), i32::MAX - 1);
+        assert!(!stepper.is_moving());
+    });
+}
@@ -0,0 +1,53 @@
+//! Tests of the quadrature decoder
+
+use esp_sandbox::encoder::{EncoderError, QuadratureDecoder};
+
+/// Channel levels (A, B) through one cycle with A leading B.
+const A_LEADING: [(bool, bool); 4] = [(true, false), (true, true), (false, true), (false, false)];
+
+#[test]
+fn counts_up_while_a_leads() {
+    let mut decoder = QuadratureDecoder::new(false, false);
+    for _ in 0..3 {
+        for (a, b) in A_LEADING {
+            assert_eq!(decoder.update(a, b), Ok(1));
+        }
+    }
+    assert_eq!(decoder.count(), 12);
+}
+
+#[test]
+fn counts_down_while_b_leads() {
+    let mut decoder = QuadratureDecoder::new(false, false);
+    for (a, b) in A_LEADING.iter().rev().cycle().skip(1).take(8) {
+        assert_eq!(decoder.update(*a, *b), Ok(-1));
+    }
+    assert_eq!(decoder.count(), -8);
+
+    // swapping the channels reverses the count
+    let mut swapped = QuadratureDecoder::new(false, false);
+    for (a, b) in A_LEADING {
+        swapped.update(b, a).unwrap();
+    }
+    assert_eq!(swapped.count(), -4);
+}
+
+#[test]
+fn repeated_samples_do_not_count() {
+    let mut decoder = QuadratureDecoder::new(true, false);
+    assert_eq!(decoder.update(true, false), Ok(0));
+    assert_eq!(decoder.count(), 0);
+}
+
+#[test]
+fn double_transition_is_an_error() {
+    let mut decoder = QuadratureDecoder::new(false, false);
+    assert_eq!(
+        decoder.update(true, true),
+        Err(EncoderError::InvalidTransition)
+    );
+    assert_eq!(decoder.count(), 0);
+    // decoding continues from the new state
+    assert_eq!(decoder.update(false, true), Ok(1));
+    assert_eq!(decoder.count(), 1);
+}
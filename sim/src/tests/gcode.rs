@@ -0,0 +1,286 @@
+//! Tests of the G-code parser and interpreter
+
+use esp_sandbox::gcode::{
+    MAX_AXES,
+    interpreter::{ExecuteError, Interpreter, InterpreterConfig, Machine, Response},
+    parser::{Command, LineBuffer, LineError, ParseError, parse_line},
+};
+
+use crate::time;
+
+const CONFIG: InterpreterConfig<2> = InterpreterConfig {
+    steps_per_unit: [80.0, 40.0],
+    max_velocity: 4_000,
+    acceleration: 20_000,
+    default_feedrate: 600.0,
+};
+
+/// Two-axis machine which completes planned moves instantly, recording the velocity of each.
+#[derive(Default)]
+struct MockMachine {
+    positions: [i32; 2],
+    targets: [i32; 2],
+    enabled: bool,
+    /// Peak velocity of each executed move (steps/s).
+    velocities: Vec<u32>,
+}
+
+impl Machine<2> for MockMachine {
+    type Error = ();
+
+    fn positions(&self) -> [i32; 2] {
+        self.positions
+    }
+
+    fn move_to(&mut self, targets: [i32; 2]) -> Result<u32, ()> {
+        self.targets = targets;
+        let steps = targets
+            .iter()
+            .zip(self.positions)
+            .map(|(target, position)| target.abs_diff(position));
+        Ok(steps.max().unwrap())
+    }
+
+    async fn run(&mut self, periods: impl IntoIterator<Item = u32>) -> Result<u32, ()> {
+        let periods: Vec<u32> = periods.into_iter().collect();
+        if let Some(&fastest) = periods.iter().min() {
+            self.velocities.push(1_000_000 / fastest);
+        }
+        self.positions = self.targets;
+        Ok(periods.len() as u32)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), ()> {
+        self.enabled = enabled;
+        Ok(())
+    }
+}
+
+/// Move to the given axis words, as parsed from `G0`/`G1`.
+fn move_to(rapid: bool, target: [Option<f32>; MAX_AXES], feedrate: Option<f32>) -> Command {
+    Command::Move {
+        rapid,
+        target,
+        feedrate,
+    }
+}
+
+/// Parse and execute each line in turn.
+fn execute(
+    interpreter: &mut Interpreter<2>,
+    machine: &mut MockMachine,
+    lines: &[&str],
+) -> Vec<Result<Response<2>, ExecuteError<()>>> {
+    time::run(async {
+        let mut responses = Vec::new();
+        for line in lines {
+            let command = parse_line(line).unwrap().unwrap();
+            responses.push(interpreter.execute(machine, command).await);
+        }
+        responses
+    })
+}
+
+#[test]
+fn parses_supported_commands() {
+    let cases = [
+        (
+            "G0 X10 Y-2.5",
+            move_to(true, [Some(10.0), Some(-2.5), None, None], None),
+        ),
+        (
+            "g1 x1 z+3 a.5 f1200",
+            move_to(false, [Some(1.0), None, Some(3.0), Some(0.5)], Some(1200.0)),
+        ),
+        (
+            "G1X1Y2",
+            move_to(false, [Some(1.0), Some(2.0), None, None], None),
+        ),
+        ("G1 F300", move_to(false, [None; MAX_AXES], Some(300.0))),
+        (
+            "G28",
+            Command::Home {
+                axes: [false; MAX_AXES],
+            },
+        ),
+        (
+            "G28 X Z",
+            Command::Home {
+                axes: [true, false, true, false],
+            },
+        ),
+        ("G90", Command::AbsolutePositioning),
+        ("G91", Command::RelativePositioning),
+        ("M17", Command::EnableMotors),
+        ("M18", Command::DisableMotors),
+        ("M84", Command::DisableMotors),
+        ("M114", Command::ReportPosition),
+    ];
+    for (line, command) in cases {
+        assert_eq!(parse_line(line), Ok(Some(command)), "{line}");
+    }
+}
+
+#[test]
+fn ignores_line_numbers_checksums_and_comments() {
+    let expected = Ok(Some(move_to(true, [Some(1.0), None, None, None], None)));
+    for line in [
+        "N10 G0 X1*57",
+        "G0 X1 ; rapid",
+        "G0 (rapid) X1",
+        "(move)G0 X1(done)",
+        "  G0\tX1  ",
+    ] {
+        assert_eq!(parse_line(line), expected, "{line}");
+    }
+    for line in ["", "   ", "; comment only", "(comment only)", "N5", "*12"] {
+        assert_eq!(parse_line(line), Ok(None), "{line}");
+    }
+}
+
+#[test]
+fn rejects_invalid_lines() {
+    let cases = [
+        ("G0 X1 #", ParseError::UnexpectedCharacter('#')),
+        ("G0 X1..2", ParseError::InvalidNumber('X')),
+        ("G0 X", ParseError::InvalidNumber('X')),
+        ("G", ParseError::InvalidNumber('G')),
+        ("G1.5", ParseError::InvalidNumber('G')),
+        ("G-1", ParseError::InvalidNumber('G')),
+        ("G2 X1", ParseError::UnsupportedCommand('G', 2)),
+        ("M3", ParseError::UnsupportedCommand('M', 3)),
+        ("X1 Y2", ParseError::MissingCommand),
+        ("F100", ParseError::MissingCommand),
+        ("G0 G1", ParseError::MultipleCommands),
+        ("G0 B1", ParseError::UnexpectedWord('B')),
+        ("G90 X1", ParseError::UnexpectedWord('X')),
+        ("G28 F100", ParseError::UnexpectedWord('F')),
+        ("M114 F100", ParseError::UnexpectedWord('F')),
+        ("G0 (X1", ParseError::UnterminatedComment),
+        ("G0 X1 é", ParseError::UnexpectedCharacter('é')),
+        ("G1 X1 F0", ParseError::InvalidFeedrate),
+        ("G1 F-100", ParseError::InvalidFeedrate),
+    ];
+    for (line, error) in cases {
+        assert_eq!(parse_line(line), Err(error), "{line}");
+    }
+}
+
+#[test]
+fn line_buffer_splits_lines() {
+    let mut buffer = LineBuffer::<8>::new();
+    let mut lines = Vec::new();
+    for &byte in b"G0 X1\r\n\nG90\ntoo long line\nM17\n\xff\n" {
+        if let Some(line) = buffer.push(byte) {
+            lines.push(line.map(str::to_owned));
+        }
+    }
+    assert_eq!(
+        lines,
+        [
+            Ok("G0 X1".to_owned()),
+            Ok("G90".to_owned()),
+            Err(LineError::TooLong),
+            Ok("M17".to_owned()),
+            Err(LineError::InvalidUtf8),
+        ]
+    );
+}
+
+#[test]
+fn moves_in_absolute_and_relative_modes() {
+    let mut interpreter = Interpreter::new(CONFIG);
+    let mut machine = MockMachine::default();
+    let responses = execute(
+        &mut interpreter,
+        &mut machine,
+        &[
+            "M17",
+            "G0 X10 Y-2.5",
+            "G91",
+            "G1 X-0.0125 Y1",
+            "G90",
+            "G0 Y0",
+            "M114",
+        ],
+    );
+    assert!(machine.enabled);
+    assert_eq!(machine.positions, [799, 0]);
+    assert!(
+        responses[..6]
+            .iter()
+            .all(|response| *response == Ok(Response::Ok))
+    );
+    assert_eq!(responses[6], Ok(Response::Position([799.0 / 80.0, 0.0])));
+
+    // homing without sensors returns the axes to zero
+    execute(&mut interpreter, &mut machine, &["G28 X"]);
+    assert_eq!(machine.positions, [0, 0]);
+}
+
+#[test]
+fn linear_moves_follow_feedrate() {
+    let mut interpreter = Interpreter::new(CONFIG);
+    let mut machine = MockMachine::default();
+    // 10 mm along X at 600 mm/min: 800 steps in one second, reaching close to 800 steps/s
+    execute(&mut interpreter, &mut machine, &["G1 X10 F600", "G0 X0"]);
+    assert!(
+        machine.velocities[0].abs_diff(800) <= 8,
+        "{:?}",
+        machine.velocities
+    );
+    // rapid moves run at max velocity
+    assert!(machine.velocities[1] > 3_900, "{:?}", machine.velocities);
+}
+
+#[test]
+fn rejects_axes_beyond_machine() {
+    let mut interpreter = Interpreter::new(CONFIG);
+    let mut machine = MockMachine::default();
+    let responses = execute(
+        &mut interpreter,
+        &mut machine,
+        &["G0 X1 Z2", "G1 A1", "G0 X1"],
+    );
+    assert_eq!(responses[0], Err(ExecuteError::UnsupportedAxis('Z')));
+    assert_eq!(responses[1], Err(ExecuteError::UnsupportedAxis('A')));
+    // rejected moves leave the machine where it was
+    assert_eq!(responses[2], Ok(Response::Ok));
+    assert_eq!(machine.velocities.len(), 1);
+    assert_eq!(machine.positions, [80, 0]);
+}
+
+#[test]
+fn relative_moves_do_not_drift() {
+    let mut interpreter = Interpreter::new(CONFIG);
+    let mut machine = MockMachine::default();
+    // each move is 0.8 steps on X and 0.4 steps on Y
+    let mut lines = vec!["G91"];
+    lines.extend(["G1 X0.01 Y0.01"; 100]);
+    execute(&mut interpreter, &mut machine, &lines);
+    assert_eq!(machine.positions, [80, 40]);
+
+    // absolute moves restart the fraction from the new target
+    execute(
+        &mut interpreter,
+        &mut machine,
+        &["G90", "G0 X0.005", "G91", "G0 X0.005"],
+    );
+    assert_eq!(machine.positions, [1, 40]);
+}
+
+#[test]
+fn rejects_homing_axes_beyond_machine() {
+    let mut interpreter = Interpreter::new(CONFIG);
+    let mut machine = MockMachine::default();
+    let responses = execute(
+        &mut interpreter,
+        &mut machine,
+        &["G0 X1 Y1", "G28 Z", "G28 X A", "G28"],
+    );
+    assert_eq!(responses[1], Err(ExecuteError::UnsupportedAxis('Z')));
+    assert_eq!(responses[2], Err(ExecuteError::UnsupportedAxis('A')));
+    // a bare G28 homes every axis of the machine
+    assert_eq!(responses[3], Ok(Response::Ok));
+    assert_eq!(machine.positions, [0, 0]);
+}
@@ -0,0 +1,84 @@
+//! Tests of H-bridge ramps through hardware fades
+
+use core::convert::Infallible;
+
+use embassy_futures::select::select;
+use embassy_time::Timer;
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+use esp_sandbox::motor::hbridge::{Drv8871, FadeDutyCycle, HBridge, State};
+
+use crate::time;
+
+/// PWM channel whose fades, like the LEDC's, run to the end once started even if the future
+/// awaiting them is dropped, and cannot be of zero length.
+#[derive(Default)]
+struct MockFade {
+    duty: u16,
+}
+
+impl ErrorType for MockFade {
+    type Error = Infallible;
+}
+
+impl SetDutyCycle for MockFade {
+    fn max_duty_cycle(&self) -> u16 {
+        100
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Infallible> {
+        self.duty = duty;
+        Ok(())
+    }
+}
+
+impl FadeDutyCycle for MockFade {
+    async fn fade_duty_cycle_percent(
+        &mut self,
+        _from_pct: u8,
+        to_pct: u8,
+        duration_ms: u16,
+    ) -> Result<(), Infallible> {
+        assert!(duration_ms > 0, "zero-length fade");
+        self.duty = to_pct.into();
+        Timer::after_millis(duration_ms.into()).await;
+        Ok(())
+    }
+}
+
+#[test]
+fn cancelled_ramp_keeps_state() {
+    let mut bridge = HBridge::<Drv8871, _>::new(MockFade::default(), MockFade::default());
+    time::run(async {
+        bridge.ramp(40, 100).await.unwrap();
+        assert_eq!(bridge.state(), State::Drive(40));
+
+        // cancelled part way through its fade
+        select(bridge.ramp(80, 100), Timer::after_millis(30)).await;
+        assert_eq!(bridge.state(), State::Drive(80));
+
+        // cancelled during the ramp down of a ramp through zero, so the motor comes to rest
+        select(bridge.ramp(-80, 160), Timer::after_millis(30)).await;
+        assert_eq!(bridge.state(), State::Drive(0));
+
+        bridge.ramp(-20, 50).await.unwrap();
+        assert_eq!(bridge.state(), State::Drive(-20));
+    });
+}
+
+#[test]
+fn zero_length_fades_set_speed_directly() {
+    let mut bridge = HBridge::<Drv8871, _>::new(MockFade::default(), MockFade::default());
+    time::run(async {
+        bridge.ramp(60, 0).await.unwrap();
+        assert_eq!(bridge.state(), State::Drive(60));
+
+        // the ramp down rounds to 0 ms, and the ramp up takes all of the duration
+        bridge.ramp(1, 10).await.unwrap();
+        bridge.ramp(-100, 50).await.unwrap();
+        assert_eq!(bridge.state(), State::Drive(-100));
+
+        // a ramp through zero taking no time
+        bridge.ramp(50, 0).await.unwrap();
+        assert_eq!(bridge.state(), State::Drive(50));
+    });
+}
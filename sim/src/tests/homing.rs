@@ -0,0 +1,175 @@
+//! Tests of homing against a virtual limit switch
+
+use core::convert::Infallible;
+
+use embassy_time::{Duration, Instant};
+use embedded_hal::digital::{ErrorType, InputPin};
+use esp_sandbox::stepper::{
+    Direction,
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    homing::{HomingConfig, HomingError, home},
+};
+
+use crate::{
+    gpio::{Trace, VirtualPin},
+    time,
+};
+
+const CONFIG: HomingConfig = HomingConfig {
+    direction: Direction::Reverse,
+    fast_period_us: 500,
+    slow_period_us: 2_000,
+    max_travel: 2_000,
+    backoff_steps: 20,
+    active_low: true,
+    timeout: Duration::from_secs(10),
+};
+
+/// Active low switch triggered while the carriage is at or beyond `trigger_at` (steps from
+/// its starting point, in the reverse direction), tracking the carriage from the STEP and DIR
+/// signals in the trace.
+struct VirtualSwitch<'a> {
+    trace: &'a Trace,
+    dir_signal: usize,
+    step_signal: usize,
+    trigger_at: i32,
+    /// Whether the switch reads triggered wherever the carriage is.
+    stuck: bool,
+}
+
+impl VirtualSwitch<'_> {
+    /// Carriage position, counted from the STEP rising edges in the direction set on DIR.
+    fn position(&self) -> i32 {
+        let mut forward = self.trace.signals()[self.dir_signal].initial != 0;
+        let mut position = 0;
+        for change in self.trace.changes().iter() {
+            if change.signal == self.dir_signal {
+                forward = change.high();
+            } else if change.signal == self.step_signal && change.high() {
+                position += if forward { 1 } else { -1 };
+            }
+        }
+        position
+    }
+}
+
+impl ErrorType for VirtualSwitch<'_> {
+    type Error = Infallible;
+}
+
+impl InputPin for VirtualSwitch<'_> {
+    fn is_high(&mut self) -> Result<bool, Infallible> {
+        Ok(!self.stuck && self.position() > self.trigger_at)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Infallible> {
+        self.is_high().map(|high| !high)
+    }
+}
+
+type TestStepper<'a> = Stepper<StepDir<Drv8825, VirtualPin<'a>>, SoftwareStepGenerator>;
+
+/// Stepper on virtual pins, with a switch `trigger_at` steps from the start.
+fn setup(trace: &Trace, trigger_at: i32) -> (TestStepper<'_>, VirtualSwitch<'_>) {
+    let dir = trace.pin("dir", true);
+    let step = trace.pin("step", false);
+    let switch = VirtualSwitch {
+        trace,
+        dir_signal: dir.signal(),
+        step_signal: step.signal(),
+        trigger_at,
+        stuck: false,
+    };
+    let driver = StepDir::<Drv8825, _>::new(dir, step);
+    (Stepper::new(driver, SoftwareStepGenerator::new()), switch)
+}
+
+#[test]
+fn homes_to_switch() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, -300);
+    time::run(async {
+        assert_eq!(home(&mut stepper, &mut switch, &CONFIG).await, Ok(()));
+    });
+    // zero is where the switch triggers on the slow approach
+    assert_eq!(stepper.position(), 0);
+    assert_eq!(switch.position(), -300);
+    assert!(!stepper.is_moving());
+}
+
+#[test]
+fn homes_starting_on_switch() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, 5);
+    time::run(async {
+        assert_eq!(home(&mut stepper, &mut switch, &CONFIG).await, Ok(()));
+    });
+    // only backs off and re-approaches
+    assert_eq!(switch.position(), 5);
+    let pulses = trace.changes_of(switch.step_signal).len() / 2;
+    assert!(
+        pulses <= 2 * CONFIG.backoff_steps as usize,
+        "{pulses} pulses"
+    );
+}
+
+#[test]
+fn stuck_sensor_is_an_error() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, -300);
+    switch.stuck = true;
+    time::run(async {
+        let result = home(&mut stepper, &mut switch, &CONFIG).await;
+        assert_eq!(result, Err(HomingError::SensorStuck));
+    });
+    assert_eq!(switch.position(), CONFIG.backoff_steps as i32);
+}
+
+#[test]
+fn missing_sensor_is_an_error() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, -5_000);
+    time::run(async {
+        let result = home(&mut stepper, &mut switch, &CONFIG).await;
+        assert_eq!(result, Err(HomingError::SensorNeverTriggered));
+    });
+    assert_eq!(switch.position(), -(CONFIG.max_travel as i32));
+}
+
+#[test]
+fn zero_backoff_is_rejected() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, 5);
+    let config = HomingConfig {
+        backoff_steps: 0,
+        ..CONFIG
+    };
+    time::run(async {
+        let result = home(&mut stepper, &mut switch, &config).await;
+        assert_eq!(result, Err(HomingError::InvalidConfig));
+    });
+    assert!(trace.changes().is_empty());
+}
+
+#[test]
+fn timeout_stops_with_step_low() {
+    let trace = Trace::new();
+    let (mut stepper, mut switch) = setup(&trace, -300);
+    // part way through the high phase of a fast approach pulse
+    let config = HomingConfig {
+        timeout: Duration::from_micros(10_300),
+        ..CONFIG
+    };
+    time::run(async {
+        let result = home(&mut stepper, &mut switch, &config).await;
+        assert_eq!(result, Err(HomingError::Timeout));
+        assert_eq!(Instant::now().as_micros(), 10_300);
+    });
+    let steps = trace.changes_of(switch.step_signal);
+    let last = steps.last().unwrap();
+    assert!(!last.high());
+    assert_eq!(last.time_us, 10_300, "STEP left high at the timeout");
+    assert!(!stepper.is_moving());
+}
@@ -0,0 +1,73 @@
+//! Tests of the Bresenham linear interpolator
+
+use esp_sandbox::motion::interpolation::LinearInterpolator;
+
+/// Per-axis deltas covering equal, coprime, zero and negative deltas.
+const DELTAS: [[i32; 4]; 6] = [
+    [10, 4, -3, 0],
+    [-7, 7, 7, -7],
+    [1_000, 999, 1, 500],
+    [3, 5, 2, -1],
+    [0, 0, 0, 0],
+    [1, 0, -1, 0],
+];
+
+/// Ticks (1-based) on which each axis steps.
+fn step_ticks<const N: usize>(deltas: [i32; N]) -> [Vec<u32>; N] {
+    let mut ticks: [Vec<u32>; N] = core::array::from_fn(|_| Vec::new());
+    for (stepping, tick) in LinearInterpolator::new(deltas).zip(1..) {
+        for (axis, _) in stepping.iter().enumerate().filter(|&(_, &stepped)| stepped) {
+            ticks[axis].push(tick);
+        }
+    }
+    ticks
+}
+
+#[test]
+fn step_counts_are_exact() {
+    for deltas in DELTAS {
+        let interpolator = LinearInterpolator::new(deltas);
+        let major_steps = deltas
+            .iter()
+            .map(|delta| delta.unsigned_abs())
+            .max()
+            .unwrap();
+        assert_eq!(interpolator.major_steps(), major_steps);
+        assert_eq!(interpolator.len(), major_steps as usize);
+        assert_eq!(interpolator.count(), major_steps as usize, "{deltas:?}");
+
+        for (ticks, delta) in step_ticks(deltas).iter().zip(deltas) {
+            assert_eq!(ticks.len(), delta.unsigned_abs() as usize, "{deltas:?}");
+        }
+    }
+}
+
+#[test]
+fn major_axis_steps_on_every_tick() {
+    let ticks = step_ticks([10, 4, -3, 0]);
+    assert_eq!(ticks[0], (1..=10).collect::<Vec<u32>>());
+    assert!(ticks[3].is_empty());
+}
+
+#[test]
+fn minor_steps_are_centred_in_their_intervals() {
+    // step k of an axis taking s of the major axis' m steps falls on the first tick t for
+    // which m/2 + t·s >= k·m, spreading the steps evenly
+    for deltas in DELTAS {
+        let major = deltas
+            .iter()
+            .map(|delta| delta.unsigned_abs())
+            .max()
+            .unwrap();
+        for (ticks, delta) in step_ticks(deltas).iter().zip(deltas) {
+            let steps = delta.unsigned_abs();
+            let expected: Vec<u32> = (1..=steps)
+                .map(|k| (k * major - major / 2).div_ceil(steps))
+                .collect();
+            assert_eq!(*ticks, expected, "{deltas:?}");
+        }
+    }
+
+    // e.g. 3 steps over 10 ticks
+    assert_eq!(step_ticks([10, 3])[1], [2, 5, 9]);
+}
@@ -0,0 +1,103 @@
+//! Tests of the step timing jitter statistics
+
+use esp_sandbox::timing::JitterStats;
+
+#[test]
+fn empty_stats() {
+    let stats = JitterStats::<5>::new(10, 50);
+    assert_eq!(stats.count(), 0);
+    assert_eq!((stats.min(), stats.max()), (None, None));
+    assert_eq!((stats.mean(), stats.std_dev(), stats.overruns()), (0, 0, 0));
+    assert_eq!(stats.histogram(), &[0; 5]);
+}
+
+#[test]
+fn summary_of_deviations() {
+    let mut stats = JitterStats::<5>::new(10, 50);
+    // deviations of 2, 4, 4, 4, 5, 5, 7, 9 µs: mean 5, population standard deviation 2
+    for deviation in [2, 4, 4, 4, 5, 5, 7, 9] {
+        stats.record(1_000, 1_000 + deviation);
+    }
+    assert_eq!(stats.count(), 8);
+    assert_eq!((stats.min(), stats.max()), (Some(2), Some(9)));
+    assert_eq!(stats.mean(), 5);
+    assert_eq!(stats.std_dev(), 2);
+
+    // the same spread about a negative mean
+    stats.reset();
+    for deviation in [2, 4, 4, 4, 5, 5, 7, 9] {
+        stats.record_deviation(-deviation);
+    }
+    assert_eq!((stats.min(), stats.max()), (Some(-9), Some(-2)));
+    assert_eq!(stats.mean(), -5);
+    assert_eq!(stats.std_dev(), 2);
+}
+
+#[test]
+fn std_dev_rounds_down() {
+    let mut stats = JitterStats::<1>::new(1, 0);
+    // variance 5/3, standard deviation 1.29
+    for deviation in [-1, 2, 0, 1, -2, 0] {
+        stats.record_deviation(deviation + 100);
+    }
+    assert_eq!(stats.mean(), 100);
+    assert_eq!(stats.std_dev(), 1);
+
+    // constant intervals have no spread, however large the deviation
+    stats.reset();
+    for _ in 0..1_000 {
+        stats.record(10_000, 2_000_000);
+    }
+    assert_eq!(stats.std_dev(), 0);
+}
+
+#[test]
+fn histogram_bins_are_centred_on_zero() {
+    let mut stats = JitterStats::<5>::new(10, 1_000);
+    // bins: ..-10 (and below), -10..0, 0..10, 10..20, 20.. (and above)
+    let cases = [
+        (-1_000, 0),
+        (-11, 0),
+        (-10, 1),
+        (-1, 1),
+        (0, 2),
+        (9, 2),
+        (10, 3),
+        (19, 3),
+        (20, 4),
+        (1_000_000, 4),
+    ];
+    for (deviation, bin) in cases {
+        let before = *stats.histogram();
+        stats.record_deviation(deviation);
+        let mut expected = before;
+        expected[bin] += 1;
+        assert_eq!(stats.histogram(), &expected, "deviation {deviation}");
+    }
+    assert_eq!(stats.histogram(), &[2, 2, 2, 2, 2]);
+}
+
+#[test]
+fn histogram_with_even_bin_count() {
+    let mut stats = JitterStats::<4>::new(5, 1_000);
+    // bins: ..-5, -5..0, 0..5, 5..
+    for deviation in [-6, -5, -1, 0, 4, 5, 100] {
+        stats.record_deviation(deviation);
+    }
+    assert_eq!(stats.histogram(), &[1, 2, 2, 2]);
+}
+
+#[test]
+fn overruns_exceed_limit() {
+    let mut stats = JitterStats::<3>::new(10, 50);
+    for (expected, actual) in [
+        (1_000, 1_050),
+        (1_000, 1_051),
+        (1_000, 900),
+        (0, 51),
+        (51, 0),
+    ] {
+        stats.record(expected, actual);
+    }
+    assert_eq!(stats.overruns(), 2);
+}
@@ -0,0 +1,151 @@
+//! Tests of jogging, on virtual pins
+
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::{Instant, Timer};
+use esp_sandbox::stepper::{
+    Error,
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    jog::{Jog, JogConfig},
+};
+
+use crate::{
+    gpio::{Trace, VirtualPin},
+    time,
+};
+
+const CONFIG: JogConfig = JogConfig {
+    max_velocity: 2_000,
+    acceleration: 2_000,
+};
+/// Speed of the first step from rest, √(2a) (steps/s).
+const START_SPEED: u64 = 63;
+
+type TestStepper<'a> = Stepper<StepDir<Drv8825, VirtualPin<'a>>, SoftwareStepGenerator>;
+
+fn stepper(trace: &Trace) -> TestStepper<'_> {
+    let driver = StepDir::<Drv8825, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    Stepper::new(driver, SoftwareStepGenerator::new())
+}
+
+/// Times of the STEP rising edges (µs).
+fn step_times(trace: &Trace) -> Vec<u64> {
+    trace
+        .changes()
+        .iter()
+        .filter(|change| trace.signals()[change.signal].name == "step" && change.high())
+        .map(|change| change.time_us)
+        .collect()
+}
+
+fn periods(times: &[u64]) -> Vec<u64> {
+    times.windows(2).map(|pair| pair[1] - pair[0]).collect()
+}
+
+#[test]
+fn accelerates_to_target() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let jog = Jog::<NoopRawMutex>::new();
+    jog.set_velocity(1_000);
+    let (result, _) = time::run(join(jog.run(&mut stepper, &CONFIG), async {
+        Timer::after_millis(1_000).await;
+        jog.stop();
+    }));
+    assert_eq!(result, Ok(()));
+
+    // v² grows by up to 2a each step, reaching 1000 steps/s after at least v²/2a = 250 steps
+    let periods = periods(&step_times(&trace));
+    let reached = periods.iter().position(|&period| period == 1_000).unwrap();
+    assert!((250..300).contains(&reached), "{reached} steps");
+    let ramp = &periods[..reached];
+    assert_eq!(ramp[0], 1_000_000_u64.div_ceil(START_SPEED));
+    assert!(ramp.windows(2).all(|pair| pair[1] <= pair[0]), "{ramp:?}");
+    let cruise = &periods[reached..reached + 200];
+    assert!(cruise.iter().all(|&period| period == 1_000), "{cruise:?}");
+    assert!(!stepper.is_moving());
+}
+
+#[test]
+fn decelerates_to_rest_before_reversing() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let jog = Jog::<NoopRawMutex>::new();
+    jog.set_velocity(1_000);
+    let (result, _) = time::run(join(jog.run(&mut stepper, &CONFIG), async {
+        Timer::after_millis(1_000).await;
+        jog.set_velocity(-1_000);
+        Timer::after_millis(1_000).await;
+        jog.stop();
+    }));
+    assert_eq!(result, Ok(()));
+
+    let dir = trace
+        .signals()
+        .iter()
+        .position(|signal| signal.name == "dir");
+    let reversals = trace.changes_of(dir.unwrap());
+    assert_eq!(reversals.len(), 1, "{reversals:?}");
+    assert!(!reversals[0].high());
+    let reversed_at = reversals[0].time_us;
+
+    // the forward steps slow to the speed of the first step from rest before DIR changes
+    let times = step_times(&trace);
+    let forward: Vec<u64> = times
+        .iter()
+        .copied()
+        .filter(|&time| time < reversed_at)
+        .collect();
+    let reverse = times.len() - forward.len();
+    let tail = periods(&forward[forward.len() - 200..]);
+    assert!(tail.windows(2).all(|pair| pair[1] >= pair[0]), "{tail:?}");
+    assert!(tail[tail.len() - 1] > 10 * 1_000, "{tail:?}");
+    assert!(reversed_at > 1_000_000 + 400_000);
+    assert_eq!(stepper.position(), forward.len() as i32 - reverse as i32);
+}
+
+#[test]
+fn waits_for_target_and_returns_at_rest() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let jog = Jog::<NoopRawMutex>::new();
+    let (returned_at, _) = time::run(join(
+        async {
+            jog.run(&mut stepper, &CONFIG).await.unwrap();
+            Instant::now().as_micros()
+        },
+        async {
+            // a zero target does not start the jog
+            jog.stop();
+            Timer::after_millis(100).await;
+            assert!(trace.changes().is_empty());
+            jog.set_velocity(500);
+            Timer::after_millis(500).await;
+            jog.stop();
+            Timer::after_millis(1_000).await;
+        },
+    ));
+
+    let times = step_times(&trace);
+    assert!(times[0] > 100_000);
+    assert!(*times.last().unwrap() < returned_at);
+    assert!(returned_at < 1_000_000);
+    assert_eq!(stepper.position(), times.len() as i32);
+    assert!(!stepper.is_moving());
+}
+
+#[test]
+fn stops_at_end_of_position_range() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let jog = Jog::<NoopRawMutex>::new();
+    stepper.set_position(i32::MAX - 3);
+    jog.set_velocity(1_000);
+    let result = time::run(jog.run(&mut stepper, &CONFIG));
+    assert_eq!(result, Err(Error::PositionOverflow));
+    assert_eq!(stepper.position(), i32::MAX);
+    assert_eq!(step_times(&trace).len(), 3);
+    assert!(!stepper.is_moving());
+}
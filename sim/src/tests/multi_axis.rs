@@ -0,0 +1,76 @@
+//! Tests of lockstep multi-axis moves, on virtual pins
+
+use core::iter;
+
+use esp_sandbox::stepper::{
+    Error,
+    driver::{Drv8825, StepDir},
+    multi_axis::MultiAxis,
+};
+
+use crate::{gpio::Trace, time};
+
+const STEP_PERIOD_US: u32 = 1_000;
+
+#[test]
+fn moves_reach_targets() {
+    let trace = Trace::new();
+    let pins = [
+        ("x_dir", "x_step"),
+        ("y_dir", "y_step"),
+        ("z_dir", "z_step"),
+    ]
+    .map(|(dir, step)| (trace.pin(dir, true), trace.pin(step, false)));
+    let step_signals = pins.each_ref().map(|(_, step)| step.signal());
+    let mut axes = MultiAxis::new(pins.map(|(dir, step)| StepDir::<Drv8825, _, _>::new(dir, step)));
+
+    // (targets, major axis steps)
+    let moves = [
+        ([100, 40, -30], 100),
+        ([-20, 40, 90], 120),
+        ([-20, 41, 90], 1),
+    ];
+    time::run(async {
+        let mut positions = [0; 3];
+        for (targets, major_steps) in moves {
+            let edges = step_signals.map(|signal| trace.changes_of(signal).len());
+            assert_eq!(axes.move_to(targets), Ok(major_steps));
+            assert_eq!(
+                axes.run(iter::repeat(STEP_PERIOD_US)).await,
+                Ok(major_steps)
+            );
+            assert_eq!(axes.positions(), targets);
+
+            // each axis steps once per step of its own delta
+            for ((signal, edges), (target, position)) in step_signals
+                .iter()
+                .zip(edges)
+                .zip(targets.iter().zip(positions))
+            {
+                let steps = (trace.changes_of(*signal).len() - edges) / 2;
+                assert_eq!(steps as u32, target.abs_diff(position), "{targets:?}");
+            }
+            positions = targets;
+        }
+    });
+}
+
+#[test]
+fn overflowing_delta_is_an_error() {
+    let trace = Trace::new();
+    let pins = [("x_dir", "x_step"), ("y_dir", "y_step")]
+        .map(|(dir, step)| (trace.pin(dir, true), trace.pin(step, false)));
+    let dir_signals = pins.each_ref().map(|(dir, _)| dir.signal());
+    let mut axes = MultiAxis::new(pins.map(|(dir, step)| StepDir::<Drv8825, _, _>::new(dir, step)));
+
+    axes.set_positions([i32::MIN, 0]);
+    assert_eq!(axes.move_to([i32::MAX, -5]), Err(Error::PositionOverflow));
+    // nothing changes, so the next move plans from the same positions
+    assert!(
+        dir_signals
+            .iter()
+            .all(|&signal| trace.changes_of(signal).is_empty())
+    );
+    assert_eq!(axes.move_to([i32::MIN + 5, -5]), Ok(5));
+    assert_eq!(axes.positions(), [i32::MIN, 0]);
+}
@@ -0,0 +1,183 @@
+//! Tests of the move queue, on virtual pins
+
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+use embassy_time::{Instant, Timer};
+use esp_sandbox::stepper::{
+    Direction, Error,
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    queue::{MoveOutcome, MoveQueue, MoveRequest},
+};
+
+use crate::{
+    gpio::{Trace, VirtualPin},
+    time,
+};
+
+const ACCELERATION: u32 = 2_000; // steps/s²
+
+type TestStepper<'a> = Stepper<StepDir<Drv8825, VirtualPin<'a>>, SoftwareStepGenerator>;
+
+fn stepper(trace: &Trace) -> TestStepper<'_> {
+    let driver = StepDir::<Drv8825, _>::new(trace.pin("dir", true), trace.pin("step", false));
+    Stepper::new(driver, SoftwareStepGenerator::new())
+}
+
+const fn request(steps: u32, direction: Direction) -> MoveRequest {
+    MoveRequest {
+        steps,
+        velocity: 1_000,
+        direction,
+    }
+}
+
+#[test]
+fn moves_run_in_order() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let queue = MoveQueue::<NoopRawMutex, 4>::new();
+    let requests = [
+        request(100, Direction::Forward),
+        request(50, Direction::Reverse),
+        request(30, Direction::Forward),
+    ];
+    let tickets = requests.map(|request| queue.try_submit(request).unwrap());
+    assert_eq!(queue.len(), 3);
+    time::run(async {
+        for position in [100, 50, 80] {
+            let outcome = queue.run_next(&mut stepper, ACCELERATION).await;
+            assert_eq!(outcome, MoveOutcome::Completed);
+            assert_eq!(stepper.position(), position);
+        }
+        for ticket in tickets {
+            assert_eq!(queue.wait(ticket).await, MoveOutcome::Completed);
+        }
+    });
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn full_queue_refuses_moves() {
+    let queue = MoveQueue::<NoopRawMutex, 2>::new();
+    assert!(queue.try_submit(request(1, Direction::Forward)).is_some());
+    assert!(queue.try_submit(request(1, Direction::Forward)).is_some());
+    assert!(queue.try_submit(request(1, Direction::Forward)).is_none());
+}
+
+#[test]
+fn cancel_discards_queued_moves() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let queue = MoveQueue::<NoopRawMutex, 4>::new();
+    let cancelled = [
+        queue.try_submit(request(100, Direction::Forward)).unwrap(),
+        queue.try_submit(request(100, Direction::Forward)).unwrap(),
+    ];
+    queue.cancel();
+    // moves submitted after cancelling are unaffected
+    let kept = queue.try_submit(request(20, Direction::Reverse)).unwrap();
+    time::run(async {
+        for ticket in cancelled {
+            let outcome = queue.run_next(&mut stepper, ACCELERATION).await;
+            assert_eq!(outcome, MoveOutcome::Cancelled { steps: 0 });
+            assert_eq!(queue.wait(ticket).await, outcome);
+        }
+        queue.run_next(&mut stepper, ACCELERATION).await;
+        assert_eq!(queue.wait(kept).await, MoveOutcome::Completed);
+    });
+    assert_eq!(stepper.position(), -20);
+}
+
+#[test]
+fn cancel_decelerates_active_move() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let queue = MoveQueue::<NoopRawMutex, 4>::new();
+    // 1000 steps/s reached after 250 steps, cruising from 0.5 s to 1 s
+    let ticket = queue
+        .try_submit(request(1_000, Direction::Forward))
+        .unwrap();
+    let cancel_us = 750_500;
+    let (outcome, _) = time::run(join(queue.run_next(&mut stepper, ACCELERATION), async {
+        Timer::at(Instant::from_micros(cancel_us)).await;
+        queue.cancel();
+    }));
+
+    let MoveOutcome::Cancelled { steps } = outcome else {
+        panic!("{outcome:?}");
+    };
+    assert!((500..750).contains(&steps), "{steps} steps");
+    assert_eq!(stepper.position(), steps as i32);
+    assert!(!stepper.is_moving());
+
+    // the deceleration ramp is taken after the cancel, with periods lengthening to rest
+    let rising: Vec<u64> = trace
+        .changes()
+        .iter()
+        .filter(|change| trace.signals()[change.signal].name == "step" && change.high())
+        .map(|change| change.time_us)
+        .collect();
+    let after_cancel = rising.iter().filter(|&&time| time > cancel_us).count();
+    assert_eq!(after_cancel, 250);
+    let periods: Vec<u64> = rising.windows(2).map(|pair| pair[1] - pair[0]).collect();
+    let tail = &periods[periods.len() - 249..];
+    assert!(tail.windows(2).all(|pair| pair[1] >= pair[0]), "{tail:?}");
+    assert!(tail[tail.len() - 1] > 10 * tail[0]);
+    time::run(async {
+        assert_eq!(queue.wait(ticket).await, outcome);
+    });
+}
+
+#[test]
+fn old_outcomes_expire() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let queue = MoveQueue::<NoopRawMutex, 2>::new();
+    time::run(async {
+        let mut tickets = Vec::new();
+        for _ in 0..3 {
+            tickets.push(queue.try_submit(request(10, Direction::Forward)).unwrap());
+            queue.run_next(&mut stepper, ACCELERATION).await;
+        }
+        // only the outcomes of the last two moves are kept
+        assert_eq!(queue.wait(tickets[0]).await, MoveOutcome::Expired);
+        assert_eq!(queue.wait(tickets[1]).await, MoveOutcome::Completed);
+        assert_eq!(queue.wait(tickets[2]).await, MoveOutcome::Completed);
+    });
+}
+
+#[test]
+fn invalid_requests_fail_without_stepping() {
+    let trace = Trace::new();
+    let mut stepper = stepper(&trace);
+    let queue = MoveQueue::<NoopRawMutex, 4>::new();
+    let requests = [
+        (
+            MoveRequest {
+                velocity: 0,
+                ..request(10, Direction::Forward)
+            },
+            Error::ZeroVelocity,
+        ),
+        (
+            request(u32::MAX, Direction::Reverse),
+            Error::PositionOverflow,
+        ),
+        (
+            request(i32::MAX as u32, Direction::Reverse),
+            Error::PositionOverflow,
+        ),
+    ];
+    stepper.set_position(-10);
+    time::run(async {
+        for (request, error) in requests {
+            queue.try_submit(request).unwrap();
+            let outcome = queue.run_next(&mut stepper, ACCELERATION).await;
+            assert_eq!(outcome, MoveOutcome::Failed(error));
+        }
+    });
+    assert_eq!(stepper.position(), -10);
+    assert!(trace.changes().is_empty());
+}
@@ -0,0 +1,93 @@
+//! Tests of the RMT pulse code encoder
+
+use esp_sandbox::stepper::generator::{MAX_CODE_TICKS, RmtEncoder, pulse_code};
+
+/// Decode pulse codes (up to the end marker) into (level, ticks) phases, merging consecutive
+/// halves of the same level. Returns the phases and the number of codes before the marker.
+fn decode(codes: &[u32]) -> (Vec<(bool, u64)>, usize) {
+    let mut phases: Vec<(bool, u64)> = Vec::new();
+    for (index, &code) in codes.iter().enumerate() {
+        for half in [code & 0xffff, code >> 16] {
+            let (level, ticks) = (half & 0x8000 != 0, (half & MAX_CODE_TICKS) as u64);
+            if ticks == 0 {
+                assert_eq!(code, 0, "zero length outside the end marker");
+                assert_eq!(index, codes.len() - 1, "codes after the end marker");
+                return (phases, index);
+            }
+            match phases.last_mut() {
+                Some((last, total)) if *last == level => *total += ticks,
+                _ => phases.push((level, ticks)),
+            }
+        }
+    }
+    panic!("no end marker");
+}
+
+#[test]
+fn single_step_is_one_code() {
+    let codes: Vec<u32> = RmtEncoder::new([100], 1).collect();
+    assert_eq!(codes, [pulse_code(true, 50, false, 50), 0]);
+}
+
+#[test]
+fn empty_input_is_end_marker() {
+    let mut encoder = RmtEncoder::new([], 1);
+    assert_eq!(encoder.by_ref().collect::<Vec<u32>>(), [0]);
+    assert_eq!(encoder.steps(), 0);
+}
+
+#[test]
+fn phases_match_periods() {
+    let periods = [100, 1, 1_000, 3, 70_000, 2_000_000];
+    for ticks_per_us in [1, 8, 80] {
+        let mut encoder = RmtEncoder::new(periods, ticks_per_us);
+        let codes: Vec<u32> = encoder.by_ref().collect();
+        assert_eq!(encoder.steps(), periods.len() as u32);
+
+        // each step is a high phase then a low phase, with the period's total length
+        let (phases, _) = decode(&codes);
+        assert_eq!(phases.len(), 2 * periods.len(), "{ticks_per_us} ticks/us");
+        for (step, &period_us) in phases.chunks(2).zip(&periods) {
+            let ticks = period_us as u64 * ticks_per_us as u64;
+            let [(true, high), (false, low)] = step else {
+                panic!("step {step:?} is not a high then low phase");
+            };
+            assert_eq!(*high, (ticks / 2).clamp(1, MAX_CODE_TICKS as u64));
+            assert_eq!(high + low, ticks.max(2), "period {period_us} us");
+        }
+    }
+}
+
+#[test]
+fn long_periods_are_split_not_clamped() {
+    // u32::MAX µs at 80 ticks/us overflows u32 ticks, and needs thousands of codes
+    let period_us = u32::MAX;
+    let codes: Vec<u32> = RmtEncoder::new([period_us], 80).collect();
+    let (phases, len) = decode(&codes);
+    let total: u64 = phases.iter().map(|&(_, ticks)| ticks).sum();
+    assert_eq!(total, period_us as u64 * 80);
+    assert_eq!(phases[0], (true, MAX_CODE_TICKS as u64));
+    assert!(len as u64 >= total / (2 * MAX_CODE_TICKS as u64));
+}
+
+#[test]
+fn high_phases_fit_in_one_code() {
+    // batches may be split between any two codes, as the idle gap only extends a low phase
+    let periods = [1, 2, 100, 65_535, 65_536, 200_000, 5];
+    let codes: Vec<u32> = RmtEncoder::new(periods, 1).collect();
+    for pair in codes.windows(2) {
+        let high_end = pair[0] >> 31 != 0;
+        let high_start = pair[1] & 0x8000 != 0;
+        assert!(
+            !(high_end && high_start),
+            "high phase spans codes {:#010x} {:#010x}",
+            pair[0],
+            pair[1]
+        );
+    }
+    let high_halves: u32 = codes
+        .iter()
+        .map(|&code| (code >> 15 & 1) + (code >> 31))
+        .sum();
+    assert_eq!(high_halves, periods.len() as u32);
+}
@@ -0,0 +1,250 @@
+//! Tests of the S-curve motion profile
+//!
+//! The step train is checked through the position of the move over time, interpolated
+//! between steps, so that velocity and acceleration can be estimated far more precisely than
+//! from the (whole microsecond) period of individual steps.
+
+use esp_sandbox::motion::scurve::SCurveProfile;
+
+/// Profiles covering long moves (with and without a constant acceleration phase), and moves
+/// too short to reach the velocity limit, as (max velocity, max acceleration, jerk, steps).
+const PROFILES: [(u32, u32, u32, u32); 5] = [
+    (2_133, 4_000, 20_000, 6_400),
+    (1_500, 3_000, 15_000, 3_000),
+    (2_133, 40_000, 2_000, 6_400),
+    (20_000, 100_000, 1_000_000, 100_000),
+    (2_133, 4_000, 20_000, 300),
+];
+
+/// Half width of the window used to estimate acceleration (µs).
+const WINDOW_US: f64 = 10_000.0;
+
+/// Relative tolerance on the velocity, acceleration and jerk limits.
+const TOLERANCE: f64 = 0.05;
+
+/// Step train of a profile, as the time at the end of each step (µs).
+struct StepTrain {
+    profile: SCurveProfile,
+    jerk: u32,
+    delays: Vec<u32>,
+    times_us: Vec<f64>,
+}
+
+impl StepTrain {
+    fn new((max_velocity, max_acceleration, jerk, steps): (u32, u32, u32, u32)) -> Self {
+        let profile = SCurveProfile::new(max_velocity, max_acceleration, jerk, steps);
+        let delays: Vec<u32> = profile.delays().collect();
+        let times_us = delays
+            .iter()
+            .scan(0.0, |time_us, &delay| {
+                *time_us += delay as f64;
+                Some(*time_us)
+            })
+            .collect();
+        Self {
+            profile,
+            jerk,
+            delays,
+            times_us,
+        }
+    }
+
+    fn duration_us(&self) -> f64 {
+        self.times_us.last().copied().unwrap_or(0.0)
+    }
+
+    /// Position (steps) at `time_us`, interpolated between steps.
+    fn position(&self, time_us: f64) -> f64 {
+        let index = self.times_us.partition_point(|&end_us| end_us <= time_us);
+        let Some(&end_us) = self.times_us.get(index) else {
+            return self.times_us.len() as f64;
+        };
+        let period_us = self.delays[index] as f64;
+        index as f64 + 1.0 - (end_us - time_us) / period_us
+    }
+
+    /// Acceleration (steps/s²) at `time_us`, from the positions either side of it.
+    fn acceleration(&self, time_us: f64) -> f64 {
+        let h = WINDOW_US;
+        let second_difference =
+            self.position(time_us + h) - 2.0 * self.position(time_us) + self.position(time_us - h);
+        second_difference / (h * h) * 1e12
+    }
+
+    /// Whether the steps around `time_us` are short enough for [`StepTrain::acceleration`]
+    /// to resolve (i.e. away from the slow first and last steps).
+    fn is_resolved(&self, time_us: f64) -> bool {
+        let is_short = |time_us: f64| {
+            let index = self.times_us.partition_point(|&end_us| end_us <= time_us);
+            let delay = self.delays.get(index);
+            time_us >= 0.0 && delay.is_some_and(|&delay| delay as f64 <= WINDOW_US / 2.0)
+        };
+        is_short(time_us - WINDOW_US) && is_short(time_us + WINDOW_US)
+    }
+
+    /// Times (µs) at which each phase after the first starts.
+    fn phase_boundaries_us(&self) -> [f64; 6] {
+        // ramp timing from the peak values, as for the profile
+        let peak_velocity = self.profile.peak_velocity() as f64;
+        let peak_acceleration = self.profile.peak_acceleration() as f64;
+        let jerk_us = peak_acceleration / self.jerk as f64 * 1e6;
+        let accel_us = (peak_velocity / peak_acceleration * 1e6 - jerk_us).max(0.0);
+        let ramp_us = 2.0 * jerk_us + accel_us;
+
+        // the deceleration ramp takes as many steps as the acceleration ramp, including the
+        // step in which it ends
+        let accel_steps = self.times_us.partition_point(|&end_us| end_us < ramp_us) + 1;
+        let decel_us = self.times_us[self.times_us.len() - accel_steps - 1];
+        [
+            jerk_us,
+            jerk_us + accel_us,
+            ramp_us,
+            decel_us,
+            decel_us + jerk_us,
+            decel_us + jerk_us + accel_us,
+        ]
+    }
+}
+
+#[test]
+fn step_count_is_exact() {
+    for limits in PROFILES.into_iter().chain([
+        (2_133, 4_000, 20_000, 3),
+        (2_133, 4_000, 20_000, 1),
+        (2_133, 4_000, 20_000, 0),
+    ]) {
+        let profile = SCurveProfile::new(limits.0, limits.1, limits.2, limits.3);
+        let delays = profile.delays();
+        assert_eq!(delays.len(), limits.3 as usize);
+        assert_eq!(delays.count(), limits.3 as usize, "{profile:?}");
+        assert_eq!(
+            profile.duration_us(),
+            StepTrain::new(limits).duration_us() as u64
+        );
+    }
+}
+
+#[test]
+fn period_change_is_bounded() {
+    for limits in PROFILES {
+        let train = StepTrain::new(limits);
+        let peak_acceleration = train.profile.peak_acceleration() as f64 * (1.0 + TOLERANCE);
+
+        // over a step, velocity changes by at most a·t, so the period changes by at most
+        // a·t³ (t being the period) plus a microsecond of rounding
+        for (index, pair) in train.delays.windows(2).enumerate() {
+            let period_s = pair[0].max(pair[1]) as f64 / 1e6;
+            let limit_us = peak_acceleration * period_s.powi(3) * 1e6 + 1.0;
+            let change_us = pair[0].abs_diff(pair[1]) as f64;
+            assert!(
+                change_us <= limit_us,
+                "{:?}: period changes from {} to {} us at step {index}",
+                train.profile,
+                pair[0],
+                pair[1]
+            );
+        }
+
+        // velocity peaks at the profile's peak, and ends as it started
+        let peak_period = *train.delays.iter().min().unwrap();
+        let peak_velocity = 1e6 / peak_period as f64;
+        let expected = train.profile.peak_velocity() as f64;
+        assert!(
+            (peak_velocity - expected).abs() <= expected * TOLERANCE,
+            "{:?}: peaks at {peak_velocity:.0} steps/s",
+            train.profile
+        );
+        assert_eq!(train.delays.first(), train.delays.last());
+    }
+}
+
+#[test]
+fn acceleration_is_continuous_at_phase_boundaries() {
+    for limits in PROFILES {
+        let train = StepTrain::new(limits);
+        let peak_acceleration = train.profile.peak_acceleration() as f64;
+
+        // acceleration changes at most at the jerk limit, so it can't step at a boundary
+        let mut previous_us = 0.0;
+        for (phase, boundary_us) in train.phase_boundaries_us().into_iter().enumerate() {
+            let before_us = boundary_us - WINDOW_US;
+            let after_us = boundary_us + WINDOW_US;
+            assert!(
+                train.is_resolved(before_us) && train.is_resolved(after_us),
+                "{:?}: phase {} starts among steps too slow to resolve",
+                train.profile,
+                phase + 2
+            );
+            let change = train.acceleration(after_us) - train.acceleration(before_us);
+            let limit = train.jerk as f64 * (after_us - before_us) / 1e6 * (1.0 + TOLERANCE)
+                + peak_acceleration * TOLERANCE;
+            assert!(
+                change.abs() <= limit,
+                "{:?}: acceleration changes by {change:.0} steps/s² across the start of \
+                 phase {}",
+                train.profile,
+                phase + 2
+            );
+            assert!(boundary_us >= previous_us);
+            previous_us = boundary_us;
+        }
+
+        // and never exceeds the peak acceleration
+        let mut time_us = 0.0;
+        while time_us < train.duration_us() {
+            let acceleration = train.acceleration(time_us);
+            assert!(
+                !train.is_resolved(time_us)
+                    || acceleration.abs() <= peak_acceleration * (1.0 + TOLERANCE),
+                "{:?}: accelerating at {acceleration:.0} steps/s² at {time_us} us",
+                train.profile
+            );
+            time_us += WINDOW_US;
+        }
+    }
+}
+
+#[test]
+fn short_move_reduces_peak() {
+    for steps in [300, 100, 20] {
+        let train = StepTrain::new((2_133, 4_000, 20_000, steps));
+        let profile = train.profile;
+        assert!(profile.peak_velocity() < 2_133, "{profile:?}");
+        assert!(profile.peak_acceleration() <= 4_000, "{profile:?}");
+
+        // the ramps fit within the move: each takes a mean velocity of half the peak over
+        // its duration (two jerk phases, without a constant acceleration phase)
+        let ramp_s = 2.0 * profile.peak_acceleration() as f64 / 20_000.0;
+        let ramp_steps = profile.peak_velocity() as f64 / 2.0 * ramp_s;
+        assert!(2.0 * ramp_steps <= steps as f64, "{profile:?}");
+
+        // the move speeds up then slows down, without cruising beyond the reduced peak
+        let peak_velocity = 1e6 / *train.delays.iter().min().unwrap() as f64;
+        assert!(
+            peak_velocity <= profile.peak_velocity() as f64 * (1.0 + TOLERANCE),
+            "{profile:?}: peaks at {peak_velocity:.0} steps/s"
+        );
+    }
+
+    // a longer move just reaches the full velocity
+    let profile = SCurveProfile::new(2_133, 4_000, 20_000, 6_400);
+    assert_eq!(profile.peak_velocity(), 2_133);
+    assert_eq!(profile.peak_acceleration(), 4_000);
+}
+
+#[test]
+fn shortest_moves_start_and_end_under_jerk() {
+    // a step from rest under the jerk alone takes cbrt(6 / j)
+    let jerk_step_us = (6.0 / 20_000.0_f64).cbrt() * 1e6;
+    for steps in [1, 2, 3, 4] {
+        let profile = SCurveProfile::new(2_133, 4_000, 20_000, steps);
+        let delays: Vec<u32> = profile.delays().collect();
+        assert!((delays[0] as f64 - jerk_step_us).abs() <= 1.0, "{delays:?}");
+        assert_eq!(delays.first(), delays.last());
+
+        // steps between the first and last are faster than them
+        if let [first, middle @ .., _] = delays.as_slice() {
+            assert!(middle.iter().all(|delay| delay < first), "{delays:?}");
+        }
+    }
+}
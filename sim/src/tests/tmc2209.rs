@@ -0,0 +1,210 @@
+//! Tests of the TMC2209 UART frames, register decoding and driver, against a mock UART
+
+use std::collections::{HashMap, VecDeque};
+
+use embedded_io_async::{ErrorKind, ErrorType, Read, Write};
+use esp_sandbox::stepper::tmc2209::{
+    Chopconf, DATA_FRAME_LEN, DrvStatus, IholdIrun, Register, Tmc2209Uart, UartError, crc8,
+    parse_reply, read_request, write_frame,
+};
+
+use crate::time;
+
+/// Write of the default GCONF flags (PDN_DISABLE, MSTEP_REG_SELECT, MULTISTEP_FILT) to the
+/// driver at address 0, with its CRC as given by the datasheet algorithm.
+const GCONF_WRITE: [u8; 8] = [0x05, 0x00, 0x80, 0x00, 0x00, 0x01, 0xc0, 0xf6];
+
+/// Read request of IOIN from the driver at address 0.
+const IOIN_REQUEST: [u8; 4] = [0x05, 0x00, 0x06, 0x6f];
+
+/// Reply to [`IOIN_REQUEST`] from a TMC2209 (version 0x21) with ENN low and DIR high.
+const IOIN_REPLY: [u8; 8] = [0x05, 0xff, 0x06, 0x21, 0x00, 0x00, 0x40, 0x4f];
+
+/// Single-wire UART echoing every frame written, and replying to read requests from a
+/// register map.
+#[derive(Default)]
+struct MockUart {
+    registers: HashMap<u8, u32>,
+    /// Frames written, in order.
+    written: Vec<Vec<u8>>,
+    pending: Vec<u8>,
+    received: VecDeque<u8>,
+}
+
+impl ErrorType for MockUart {
+    type Error = ErrorKind;
+}
+
+impl Write for MockUart {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, ErrorKind> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    async fn flush(&mut self) -> Result<(), ErrorKind> {
+        let frame = std::mem::take(&mut self.pending);
+        self.received.extend(&frame);
+        if let [0x05, _, register, _] = frame[..] {
+            let value = self.registers.get(&register).copied().unwrap_or(0);
+            let mut reply = [0x05, 0xff, register, 0, 0, 0, 0, 0];
+            reply[3..7].copy_from_slice(&value.to_be_bytes());
+            reply[7] = crc8(&reply[..7]);
+            self.received.extend(reply);
+        } else {
+            let value = u32::from_be_bytes(frame[3..7].try_into().unwrap());
+            self.registers.insert(frame[2] & 0x7f, value);
+        }
+        self.written.push(frame);
+        Ok(())
+    }
+}
+
+impl Read for MockUart {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, ErrorKind> {
+        let len = buf.len().min(self.received.len());
+        for (slot, byte) in buf.iter_mut().zip(self.received.drain(..len)) {
+            *slot = byte;
+        }
+        Ok(len)
+    }
+}
+
+#[test]
+fn frames_match_recorded_bytes() {
+    assert_eq!(write_frame(0, Register::Gconf, 0x1c0), GCONF_WRITE);
+    assert_eq!(read_request(0, Register::Ioin), IOIN_REQUEST);
+    assert_eq!(crc8(&IOIN_REPLY[..DATA_FRAME_LEN - 1]), IOIN_REPLY[7]);
+    assert_eq!(parse_reply(&IOIN_REPLY, Register::Ioin), Ok(0x2100_0040));
+
+    // other addresses and registers set the low bits of the address byte
+    let frame = write_frame(3, Register::IholdIrun, 0x0001_1f10);
+    assert_eq!(frame[..7], [0x05, 0x03, 0x90, 0x00, 0x01, 0x1f, 0x10]);
+    assert_eq!(frame[7], crc8(&frame[..7]));
+}
+
+#[test]
+fn replies_are_checked() {
+    let mut corrupted = IOIN_REPLY;
+    corrupted[5] ^= 0x01;
+    assert_eq!(parse_reply(&corrupted, Register::Ioin), Err(UartError::Crc));
+    assert_eq!(
+        parse_reply(&IOIN_REPLY, Register::DrvStatus),
+        Err(UartError::UnexpectedReply)
+    );
+    // a reply addressed to a driver rather than the master
+    let mut misaddressed = IOIN_REPLY;
+    misaddressed[1] = 0x00;
+    misaddressed[7] = crc8(&misaddressed[..7]);
+    assert_eq!(
+        parse_reply(&misaddressed, Register::Ioin),
+        Err(UartError::UnexpectedReply)
+    );
+}
+
+#[test]
+fn drv_status_decodes_flags() {
+    let status = DrvStatus::from_bits(0);
+    assert!(!status.is_faulted());
+    assert_eq!(status.current_scale, 0);
+
+    // standstill in StealthChop at current scale 20, with an overtemperature warning and open
+    // load on phase B
+    let status = DrvStatus::from_bits(1 << 31 | 1 << 30 | 20 << 16 | 1 << 7 | 1 << 0);
+    assert_eq!(
+        status,
+        DrvStatus {
+            overtemperature_warning: true,
+            overtemperature: false,
+            short_to_ground: [false; 2],
+            short_to_supply: [false; 2],
+            open_load: [false, true],
+            current_scale: 20,
+            stealth: true,
+            standstill: true,
+        }
+    );
+    // warnings and open load leave the outputs enabled
+    assert!(!status.is_faulted());
+
+    for (bit, name) in [
+        (1, "ot"),
+        (2, "s2ga"),
+        (3, "s2gb"),
+        (4, "s2vsa"),
+        (5, "s2vsb"),
+    ] {
+        assert!(DrvStatus::from_bits(1 << bit).is_faulted(), "{name}");
+    }
+    // bits outside the current scale field are ignored
+    assert_eq!(DrvStatus::from_bits(0x00ff_0000).current_scale, 31);
+}
+
+#[test]
+fn chopconf_decodes_and_encodes() {
+    assert_eq!(Chopconf::DEFAULT.microsteps(), 256);
+    assert!(!Chopconf::DEFAULT.vsense());
+
+    for (microsteps, mres) in [(256, 0), (128, 1), (16, 4), (2, 7), (1, 8)] {
+        let chopconf = Chopconf::DEFAULT.with_microsteps(microsteps).unwrap();
+        assert_eq!(chopconf.0, 0x1000_0053 | mres << 24, "{microsteps}");
+        assert_eq!(chopconf.microsteps(), microsteps);
+    }
+    for microsteps in [0, 3, 12, 512] {
+        assert_eq!(Chopconf::DEFAULT.with_microsteps(microsteps), None);
+    }
+
+    let chopconf = Chopconf::DEFAULT.with_vsense(true);
+    assert_eq!(chopconf.0, 0x1002_0053);
+    assert!(chopconf.vsense());
+    assert_eq!(chopconf.with_vsense(false), Chopconf::DEFAULT);
+
+    // reserved MRES values select full steps
+    assert_eq!(Chopconf(0x0f00_0000).microsteps(), 1);
+}
+
+#[test]
+fn driver_exchanges_frames() {
+    let mut uart = MockUart::default();
+    uart.registers.insert(Register::Ioin as u8, 0x2100_0040);
+    let mut driver = Tmc2209Uart::new(&mut uart, 0);
+    time::run(async {
+        driver.init().await.unwrap();
+        assert_eq!(driver.version().await, Ok(0x21));
+        driver.set_microsteps(16).await.unwrap();
+        assert_eq!(
+            driver.set_microsteps(3).await,
+            Err(UartError::UnsupportedMicrosteps(3))
+        );
+        assert_eq!(driver.microsteps(), 16);
+    });
+    assert_eq!(uart.written[1], GCONF_WRITE);
+    assert_eq!(uart.written[4], IOIN_REQUEST);
+    assert_eq!(uart.registers[&(Register::Chopconf as u8)], 0x1400_0053);
+}
+
+#[test]
+fn zero_run_current_is_rejected() {
+    let mut uart = MockUart::default();
+    let mut driver = Tmc2209Uart::new(&mut uart, 0);
+    time::run(async {
+        assert_eq!(
+            driver.set_current(0, 0).await,
+            Err(UartError::UnsupportedCurrent(0))
+        );
+        // 800 mA with 110 mΩ sense resistors is current scale 25 (of 31) with VSENSE set,
+        // holding at half current
+        driver.set_current(800, 400).await.unwrap();
+    });
+
+    // the rejected setting sent nothing
+    assert_eq!(uart.written.len(), 2);
+    let ihold_irun = IholdIrun {
+        hold: 12,
+        run: 25,
+        hold_delay: 1,
+    };
+    assert_eq!(
+        uart.registers[&(Register::IholdIrun as u8)],
+        ihold_irun.bits()
+    );
+}
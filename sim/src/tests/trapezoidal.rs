@@ -0,0 +1,111 @@
+//! Tests of the trapezoidal motion profile
+
+use esp_sandbox::motion::trapezoidal::TrapezoidalProfile;
+
+/// Profiles covering the `stepper_async` demo, short moves and other velocity limits, as
+/// (max velocity, acceleration, steps).
+const PROFILES: [(u32, u32, u32); 7] = [
+    (2_133, 4_000, 6_400),
+    (2_133, 4_000, 1_000),
+    (2_133, 4_000, 7),
+    (2_133, 4_000, 1),
+    (500, 100_000, 200),
+    (20_000, 50_000, 100_000),
+    (1, 1, 3),
+];
+
+#[test]
+fn step_count_is_exact() {
+    for (max_velocity, acceleration, steps) in PROFILES.into_iter().chain([(2_133, 4_000, 0)]) {
+        let profile = TrapezoidalProfile::new(max_velocity, acceleration, steps);
+        let delays = profile.delays();
+        assert_eq!(delays.len(), steps as usize);
+        assert_eq!(delays.count(), steps as usize, "{profile:?}");
+        assert_eq!(
+            2 * profile.accel_steps() + profile.cruise_steps(),
+            steps,
+            "{profile:?}"
+        );
+    }
+}
+
+#[test]
+fn deceleration_mirrors_acceleration() {
+    for (max_velocity, acceleration, steps) in PROFILES {
+        let profile = TrapezoidalProfile::new(max_velocity, acceleration, steps);
+        let delays: Vec<u32> = profile.delays().collect();
+        let reversed: Vec<u32> = delays.iter().rev().copied().collect();
+        assert_eq!(delays, reversed, "{profile:?}");
+
+        // periods shorten until the middle of the move (each step's time is rounded to the
+        // microsecond, so consecutive periods may differ by one either way), and never drop
+        // below the cruise period
+        let half = &delays[..delays.len().div_ceil(2)];
+        assert!(
+            half.windows(2).all(|pair| pair[0] + 1 >= pair[1]),
+            "{profile:?}"
+        );
+        assert!(
+            delays
+                .iter()
+                .all(|&delay| delay >= profile.cruise_period_us())
+        );
+    }
+}
+
+#[test]
+fn ramp_follows_acceleration() {
+    // time to reach each step is sqrt(2n / a), to within a microsecond per step
+    let profile = TrapezoidalProfile::new(2_133, 4_000, 6_400);
+    let mut elapsed_us = 0;
+    for (index, delay) in profile
+        .delays()
+        .take(profile.accel_steps() as usize)
+        .enumerate()
+    {
+        elapsed_us += delay as u64;
+        let expected_us = (2.0 * (index + 1) as f64 / 4_000.0).sqrt() * 1e6;
+        assert!(
+            (elapsed_us as f64 - expected_us).abs() <= 1.0,
+            "step {index}"
+        );
+    }
+    assert_eq!(profile.accel_steps(), 2_133 * 2_133 / (2 * 4_000));
+    assert_eq!(profile.cruise_period_us(), 1_000_000_u32.div_ceil(2_133));
+}
+
+#[test]
+fn short_move_is_triangular() {
+    // reaching 2133 steps/s takes 568 steps, so 1000 steps peak below it
+    for steps in [1_000, 1_001, 7, 2] {
+        let profile = TrapezoidalProfile::new(2_133, 4_000, steps);
+        assert_eq!(profile.accel_steps(), steps / 2);
+        assert_eq!(profile.cruise_steps(), steps % 2);
+
+        // the apex is slower than max velocity, and an odd step at the apex continues the ramp
+        // (no slower than the step before it, to within the rounding of both periods)
+        let delays: Vec<u32> = profile.delays().collect();
+        assert!(
+            profile.cruise_period_us() > 1_000_000 / 2_133,
+            "{profile:?}"
+        );
+        assert!(
+            delays.iter().all(|&delay| delay > 1_000_000 / 2_133),
+            "{profile:?}"
+        );
+        if steps % 2 == 1 {
+            let apex = delays[(steps / 2) as usize];
+            assert_eq!(apex, profile.cruise_period_us());
+            assert!(apex <= delays[(steps / 2) as usize - 1] + 2, "{profile:?}");
+        }
+    }
+}
+
+#[test]
+fn duration_is_sum_of_delays() {
+    for (max_velocity, acceleration, steps) in PROFILES {
+        let profile = TrapezoidalProfile::new(max_velocity, acceleration, steps);
+        let total: u64 = profile.delays().map(u64::from).sum();
+        assert_eq!(profile.duration_us(), total, "{profile:?}");
+    }
+}
@@ -0,0 +1,140 @@
+//! Tests of the ULN2003 coil sequences, on virtual pins
+
+use esp_sandbox::stepper::{
+    Direction, Error,
+    driver::StepperDriver,
+    uln2003::{StepMode, Uln2003},
+};
+
+use crate::gpio::{Trace, VirtualPin};
+
+type TestDriver<'a> = Uln2003<VirtualPin<'a>>;
+
+fn driver(trace: &Trace, mode: StepMode) -> TestDriver<'_> {
+    let pins = ["in1", "in2", "in3", "in4"].map(|name| trace.pin(name, false));
+    Uln2003::new(pins, mode)
+}
+
+/// Coils currently energised, as IN1-IN4 e.g. "1100".
+fn coils(trace: &Trace) -> String {
+    (0..4)
+        .map(|signal| if trace.value(signal) != 0 { '1' } else { '0' })
+        .collect()
+}
+
+/// Take `count` steps in `direction`, returning the coils energised after each.
+fn steps(
+    trace: &Trace,
+    driver: &mut TestDriver,
+    direction: Direction,
+    count: usize,
+) -> Vec<String> {
+    driver.set_direction(direction).unwrap();
+    (0..count)
+        .map(|_| {
+            driver.set_step(true).unwrap();
+            driver.set_step(false).unwrap();
+            coils(trace)
+        })
+        .collect()
+}
+
+#[test]
+fn modes_walk_their_sequences() {
+    let cases = [
+        (
+            StepMode::Wave,
+            Direction::Forward,
+            &["0100", "0010", "0001", "1000", "0100"][..],
+        ),
+        (
+            StepMode::Wave,
+            Direction::Reverse,
+            &["0001", "0010", "0100", "1000", "0001"],
+        ),
+        (
+            StepMode::Full,
+            Direction::Forward,
+            &["0110", "0011", "1001", "1100", "0110"],
+        ),
+        (
+            StepMode::Full,
+            Direction::Reverse,
+            &["1001", "0011", "0110", "1100", "1001"],
+        ),
+        (
+            StepMode::Half,
+            Direction::Forward,
+            &[
+                "1100", "0100", "0110", "0010", "0011", "0001", "1001", "1000", "1100",
+            ],
+        ),
+        (
+            StepMode::Half,
+            Direction::Reverse,
+            &[
+                "1001", "0001", "0011", "0010", "0110", "0100", "1100", "1000", "1001",
+            ],
+        ),
+    ];
+    for (mode, direction, expected) in cases {
+        let trace = Trace::new();
+        let mut driver = driver(&trace, mode);
+        assert_eq!(coils(&trace), "0000", "{mode:?} starts with the coils off");
+        let taken = steps(&trace, &mut driver, direction, expected.len());
+        assert_eq!(taken, expected, "{mode:?} {direction:?}");
+    }
+}
+
+#[test]
+fn reversing_retraces_the_sequence() {
+    let trace = Trace::new();
+    let mut driver = driver(&trace, StepMode::Half);
+    let forward = steps(&trace, &mut driver, Direction::Forward, 3);
+    let reverse = steps(&trace, &mut driver, Direction::Reverse, 3);
+    assert_eq!(forward, ["1100", "0100", "0110"]);
+    assert_eq!(reverse, ["0100", "1100", "1000"]);
+    assert_eq!(driver.direction(), Direction::Reverse);
+}
+
+#[test]
+fn disabling_releases_the_coils() {
+    let trace = Trace::new();
+    let mut driver = driver(&trace, StepMode::Full);
+    steps(&trace, &mut driver, Direction::Forward, 2);
+    assert_eq!(coils(&trace), "0011");
+
+    // disabling, or sleeping, holds the position in the sequence
+    driver.set_enabled(false).unwrap();
+    assert_eq!(coils(&trace), "0000");
+    driver.set_enabled(true).unwrap();
+    assert_eq!(coils(&trace), "0011");
+    driver.set_sleep(true).unwrap();
+    assert_eq!(coils(&trace), "0000");
+    driver.set_sleep(false).unwrap();
+    assert_eq!(coils(&trace), "0011");
+
+    // resetting returns to the start of the sequence
+    driver.set_reset(true).unwrap();
+    assert_eq!(coils(&trace), "0000");
+    driver.set_reset(false).unwrap();
+    let taken = steps(&trace, &mut driver, Direction::Forward, 1);
+    assert_eq!(taken, ["0110"]);
+}
+
+#[test]
+fn microsteps_select_full_or_half_step() {
+    let trace = Trace::new();
+    let mut driver = driver(&trace, StepMode::Wave);
+    assert_eq!(driver.microsteps(), 1);
+    driver.set_microsteps(2).unwrap();
+    assert_eq!(driver.mode(), StepMode::Half);
+    assert_eq!(driver.microsteps(), 2);
+    driver.set_microsteps(1).unwrap();
+    assert_eq!(driver.mode(), StepMode::Full);
+    assert_eq!(
+        driver.set_microsteps(4),
+        Err(Error::UnsupportedMicrosteps(4))
+    );
+    assert_eq!(driver.mode(), StepMode::Full);
+}
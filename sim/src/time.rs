@@ -0,0 +1,72 @@
+//! Simulated embassy time driver
+//!
+//! Time only advances when the simulated future is blocked on a timer, at which point it jumps
+//! straight to the earliest pending alarm. Runs are therefore deterministic and take no longer
+//! than the CPU time needed to execute the stepping logic.
+//!
+//! Each thread has its own clock, so unit tests running in parallel don't disturb each other.
+
+use core::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+use std::cell::RefCell;
+
+use embassy_time_driver::{Driver, time_driver_impl};
+
+struct State {
+    /// Current time (ticks).
+    now: u64,
+    /// Times of the pending alarms (ticks).
+    alarms: Vec<u64>,
+}
+
+thread_local! {
+    static STATE: RefCell<State> = const {
+        RefCell::new(State {
+            now: 0,
+            alarms: Vec::new(),
+        })
+    };
+}
+
+struct SimDriver;
+
+impl Driver for SimDriver {
+    fn now(&self) -> u64 {
+        STATE.with_borrow(|state| state.now)
+    }
+
+    fn schedule_wake(&self, at: u64, _waker: &Waker) {
+        // the single simulated future is polled again after every alarm, so it needn't be woken
+        STATE.with_borrow_mut(|state| state.alarms.push(at));
+    }
+}
+
+time_driver_impl!(static DRIVER: SimDriver = SimDriver);
+
+/// Run a future to completion, advancing simulated time whenever it is waiting on a timer.
+///
+/// Panics if the future is pending without any timer to wake it.
+pub fn run<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let mut cx = Context::from_waker(Waker::noop());
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        STATE.with_borrow_mut(|state| {
+            let next = state
+                .alarms
+                .iter()
+                .copied()
+                .min()
+                .expect("simulation stalled with no timers pending");
+            state.now = state.now.max(next);
+            let now = state.now;
+            state.alarms.retain(|&at| at > now);
+        });
+    }
+}
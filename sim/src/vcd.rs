@@ -0,0 +1,54 @@
+//! Value change dump (VCD) output, as read by GTKWave and other waveform viewers
+
+use std::io::{self, Write};
+
+use crate::gpio::Trace;
+
+/// Identifier code of a signal in the dump (printable ASCII, starting from `!`).
+fn identifier(signal: usize) -> char {
+    char::from(b'!' + u8::try_from(signal).expect("too many signals"))
+}
+
+/// Write a trace as a VCD file with a timescale of 1 µs.
+pub fn write(out: &mut impl Write, trace: &Trace, module: &str) -> io::Result<()> {
+    writeln!(out, "$timescale 1us $end")?;
+    writeln!(out, "$scope module {module} $end")?;
+    for (index, signal) in trace.signals().iter().enumerate() {
+        writeln!(
+            out,
+            "$var wire {} {} {} $end",
+            signal.width,
+            identifier(index),
+            signal.name
+        )?;
+    }
+    writeln!(out, "$upscope $end")?;
+    writeln!(out, "$enddefinitions $end")?;
+
+    writeln!(out, "#0")?;
+    writeln!(out, "$dumpvars")?;
+    for (index, signal) in trace.signals().iter().enumerate() {
+        write_value(out, signal.width, signal.initial, index)?;
+    }
+    writeln!(out, "$end")?;
+
+    let signals = trace.signals();
+    let mut time_us = 0;
+    for change in trace.changes().iter() {
+        if change.time_us != time_us {
+            time_us = change.time_us;
+            writeln!(out, "#{time_us}")?;
+        }
+        let width = signals[change.signal].width;
+        write_value(out, width, change.value, change.signal)?;
+    }
+    out.flush()
+}
+
+/// Write the value of a signal, as a scalar for pins and in binary for wider signals.
+fn write_value(out: &mut impl Write, width: u8, value: u16, signal: usize) -> io::Result<()> {
+    match width {
+        1 => writeln!(out, "{value}{}", identifier(signal)),
+        _ => writeln!(out, "b{value:b} {}", identifier(signal)),
+    }
+}
@@ -1,47 +1,90 @@
-//! Simple demo ramping a dc motor via ESP32C3 & DBH12 driver
+//! Simple demo ramping two dc motors via ESP32C3 & DBH12 driver
 //!
 //! Connections List (TODO: wiring schematic)
-//! - GPIO6: motor A IN1 (DBH12 IN1)
-//! - GPIO7: motor A IN2 (DBH12 IN2)
+//! - GPIO3: motor A current sense (DBH12 CT1)
+//! - GPIO4: motor B current sense (DBH12 CT2)
+//! - GPIO5: motor B IN1 (DBH12 IN2A)
+//! - GPIO6: motor A IN1 (DBH12 IN1A)
+//! - GPIO7: motor A IN2 (DBH12 IN1B)
 //! - GPIO9: button (momentary, wired to ground)
-//! - GPIO21: motor A enable (DBH12 EN)
+//! - GPIO10: motor B IN2 (DBH12 IN2B)
+//! - GPIO20: motor B enable (DBH12 EN2)
+//! - GPIO21: motor A enable (DBH12 EN1)
 //!
+//! Both motors ramp together, each on its own pair of LEDC channels (0/1 for motor A, 2/3
+//! for motor B), with the fades of both awaited concurrently.
+//!
+//! The current monitor task samples both current sense outputs every SAMPLE_PERIOD_MS. If
+//! the filtered current of a motor exceeds TRIP_CURRENT_MA (e.g. a stalled motor), it pulls
+//! that motor's EN low and latches the fault. The demo then stops, and the driver stays
+//! disabled until the button is pressed to clear the fault.
 
 #![no_std]
 #![no_main]
 #![feature(impl_trait_in_assoc_type)]
 
-use defmt::info;
+use defmt::{error, info, warn};
 use embassy_executor::Spawner;
-use embassy_time::Timer;
+use embassy_futures::join::join;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Ticker, Timer};
 use esp_hal::{
+    Async,
+    analog::adc::{Adc, AdcCalCurve, AdcConfig, AdcPin, Attenuation},
     gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull},
-    ledc::{
-        LSGlobalClkSource, Ledc, LowSpeed,
-        channel::{self, ChannelIFace},
-        timer::{self, TimerIFace},
-    },
+    ledc::{LSGlobalClkSource, Ledc, LowSpeed, channel, timer},
+    peripherals::{ADC1, GPIO3, GPIO4},
     time::Rate,
     timer::timg::TimerGroup,
 };
+use esp_sandbox::motor::{
+    current::{CurrentCalibration, CurrentMonitor, EnableLatch},
+    hbridge::{Dbh12, HBridge},
+    ledc::{FadeChannel, bind_fade_interrupt, configure_timer},
+};
+use static_cell::StaticCell;
 use {defmt_rtt as _, esp_backtrace as _};
 
 // motor parameters
-const PWM_MIN: u8 = 0;
-const PWM_MAX: u8 = 95;
+const PWM_MIN: i8 = 0;
+const PWM_MAX: i8 = 95;
 const RAMP_DURATION: u16 = 5000;
 const PAUSE_DURATION: u64 = 5;
 
+// current monitor parameters (calibrate against a known load)
+const CURRENT_MV_PER_AMP: u32 = 100;
+const CURRENT_OFFSET_MV: i32 = 0;
+const TRIP_CURRENT_MA: u32 = 8_000;
+const SMOOTHING: u8 = 3; // time constant of about 2^SMOOTHING samples
+const SAMPLE_PERIOD_MS: u64 = 1;
+
+// Calculated values
+const CALIBRATION: CurrentCalibration =
+    CurrentCalibration::new(CURRENT_MV_PER_AMP, CURRENT_OFFSET_MV);
+const MONITOR: CurrentMonitor = CurrentMonitor::new(CALIBRATION, TRIP_CURRENT_MA, SMOOTHING);
+
+/// Enable output shared between a motor and the current monitor.
+type Latch = EnableLatch<CriticalSectionRawMutex, Output<'static>>;
+
+/// Current sense input of a motor, calibrated by the ADC.
+type SensePin<P> = AdcPin<P, ADC1<'static>, AdcCalCurve<ADC1<'static>>>;
+
 #[esp_hal_embassy::main]
-async fn main(_spawner: Spawner) {
+async fn main(spawner: Spawner) {
     // initialize hardware
     let peripherals = esp_hal::init(esp_hal::Config::default());
 
-    // disable driver and drive PWM pins low during setup
+    // disable drivers and drive PWM pins low during setup
     let output_config = OutputConfig::default().with_pull(Pull::Down);
-    let mut enable = Output::new(peripherals.GPIO21, Level::Low, output_config);
-    let in0 = Output::new(peripherals.GPIO6, Level::Low, output_config);
-    let in1 = Output::new(peripherals.GPIO7, Level::Low, output_config);
+    let enable_a = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let enable_b = Output::new(peripherals.GPIO20, Level::Low, output_config);
+    let in_a1 = Output::new(peripherals.GPIO6, Level::Low, output_config);
+    let in_a2 = Output::new(peripherals.GPIO7, Level::Low, output_config);
+    let in_b1 = Output::new(peripherals.GPIO5, Level::Low, output_config);
+    let in_b2 = Output::new(peripherals.GPIO10, Level::Low, output_config);
+    static LATCHES: StaticCell<[Latch; 2]> = StaticCell::new();
+    let latches: &'static [Latch; 2] =
+        LATCHES.init([EnableLatch::new(enable_a), EnableLatch::new(enable_b)]);
 
     // initialize embassy
     let timg0 = TimerGroup::new(peripherals.TIMG0);
@@ -50,23 +93,42 @@ async fn main(_spawner: Spawner) {
     // initialize ledc peripheral
     let mut ledc = Ledc::new(peripherals.LEDC);
     ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    bind_fade_interrupt(&mut ledc);
 
-    // initialize ledc timers
-    let timer_config = timer::config::Config {
-        duty: timer::config::Duty::Duty8Bit,
-        clock_source: timer::LSClockSource::APBClk,
-        frequency: Rate::from_khz(1),
-    };
+    // initialize ledc timer, shared by both motors
     let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
-    let mut lstimer1 = ledc.timer::<LowSpeed>(timer::Number::Timer1);
-    lstimer0.configure(timer_config).unwrap();
-    lstimer1.configure(timer_config).unwrap();
+    configure_timer(
+        &mut lstimer0,
+        timer::config::Duty::Duty8Bit,
+        Rate::from_khz(1),
+    )
+    .unwrap();
 
     // initialize pwm channels
-    let mut channel0 = ledc.channel(channel::Number::Channel0, in0);
-    let mut channel1 = ledc.channel(channel::Number::Channel1, in1);
-    configure_channel(&mut channel0, &lstimer0);
-    configure_channel(&mut channel1, &lstimer1);
+    let mut channels = [
+        FadeChannel::new(&ledc, channel::Number::Channel0, in_a1),
+        FadeChannel::new(&ledc, channel::Number::Channel1, in_a2),
+        FadeChannel::new(&ledc, channel::Number::Channel2, in_b1),
+        FadeChannel::new(&ledc, channel::Number::Channel3, in_b2),
+    ];
+    for channel in &mut channels {
+        channel.configure(&lstimer0).unwrap();
+    }
+    let [channel_a1, channel_a2, channel_b1, channel_b2] = channels;
+    let [latch_a, latch_b] = latches;
+    let mut motor_a =
+        HBridge::<Dbh12, _>::new(channel_a1, channel_a2).with_enable(latch_a.output());
+    let mut motor_b =
+        HBridge::<Dbh12, _>::new(channel_b1, channel_b2).with_enable(latch_b.output());
+
+    // initialize current sensing
+    let mut adc_config = AdcConfig::new();
+    let sense_a = adc_config
+        .enable_pin_with_cal::<_, AdcCalCurve<ADC1>>(peripherals.GPIO3, Attenuation::_11dB);
+    let sense_b = adc_config
+        .enable_pin_with_cal::<_, AdcCalCurve<ADC1>>(peripherals.GPIO4, Attenuation::_11dB);
+    let adc = Adc::new(peripherals.ADC1, adc_config).into_async();
+    spawner.must_spawn(current_monitor(adc, sense_a, sense_b, latches));
 
     // initialize input button
     let mut input = Input::new(
@@ -74,64 +136,91 @@ async fn main(_spawner: Spawner) {
         InputConfig::default().with_pull(Pull::Up),
     );
 
+    let ramps = [
+        ("forward ramp up", PWM_MAX),
+        ("forward ramp down", PWM_MIN),
+        ("backward ramp up", -PWM_MAX),
+        ("backward ramp down", -PWM_MIN),
+    ];
+    let tripped = || latches.iter().any(|latch| latch.fault().is_some());
     loop {
         // wait for input button to be triggered
         info!("waiting for input...");
         input.wait_for_falling_edge().await;
-
-        // enable driver
-        enable.set_high();
-
-        // motor demo
-        info!("starting forward ramp up");
-        channel0
-            .start_duty_fade(PWM_MIN, PWM_MAX, RAMP_DURATION)
-            .unwrap();
-        while channel0.is_duty_fade_running() {
-            Timer::after_millis(10).await;
+        for (name, latch) in ["A", "B"].iter().zip(latches) {
+            if let Some(fault) = latch.fault() {
+                info!(
+                    "clearing motor {} overcurrent fault ({} mA)",
+                    name, fault.current_ma
+                );
+                latch.clear().unwrap();
+            }
         }
 
-        info!("starting forward ramp down");
-        channel0
-            .start_duty_fade(PWM_MAX, PWM_MIN, RAMP_DURATION)
-            .unwrap();
-        while channel0.is_duty_fade_running() {
-            Timer::after_millis(10).await;
+        // enable drivers
+        motor_a.set_enabled(true).unwrap();
+        motor_b.set_enabled(true).unwrap();
+
+        // motor demo, abandoned on overcurrent
+        for (name, speed) in ramps {
+            if tripped() {
+                break;
+            }
+            info!("starting {}", name);
+            let (a, b) = join(
+                motor_a.ramp(speed, RAMP_DURATION),
+                motor_b.ramp(speed, RAMP_DURATION),
+            )
+            .await;
+            a.unwrap();
+            b.unwrap();
         }
 
-        info!("starting backward ramp up");
-        channel1
-            .start_duty_fade(PWM_MIN, PWM_MAX, RAMP_DURATION)
-            .unwrap();
-        while channel1.is_duty_fade_running() {
-            Timer::after_millis(10).await;
+        // disable drivers
+        for motor in [&mut motor_a, &mut motor_b] {
+            motor.set_speed(0).unwrap();
+            motor.set_enabled(false).unwrap();
         }
-
-        info!("starting backward ramp down");
-        channel1
-            .start_duty_fade(PWM_MAX, PWM_MIN, RAMP_DURATION)
-            .unwrap();
-        while channel1.is_duty_fade_running() {
-            Timer::after_millis(10).await;
+        if tripped() {
+            warn!("stopped on overcurrent, press the button to clear the fault");
+            continue;
         }
 
-        // disable driver
-        enable.set_low();
-
         info!("pausing");
         Timer::after_secs(PAUSE_DURATION).await;
     }
 }
 
-/// Configure ledc channel for PWM output.
-fn configure_channel<'a>(
-    channel: &mut channel::Channel<'a, LowSpeed>,
-    timer: &'a timer::Timer<'a, LowSpeed>,
+/// Task watching the current of both motors, disabling a driver on overcurrent
+#[embassy_executor::task]
+async fn current_monitor(
+    mut adc: Adc<'static, ADC1<'static>, Async>,
+    mut sense_a: SensePin<GPIO3<'static>>,
+    mut sense_b: SensePin<GPIO4<'static>>,
+    latches: &'static [Latch; 2],
 ) {
-    let config = channel::config::Config {
-        timer,
-        duty_pct: 0,
-        pin_config: channel::config::PinConfig::PushPull,
-    };
-    channel.configure(config).unwrap()
+    let mut monitors = [MONITOR; 2];
+    let mut ticker = Ticker::every(Duration::from_millis(SAMPLE_PERIOD_MS));
+    loop {
+        let samples = [
+            adc.read_oneshot(&mut sense_a).await,
+            adc.read_oneshot(&mut sense_b).await,
+        ];
+        let motors = ["A", "B"].iter().zip(&mut monitors).zip(latches);
+        for (((name, monitor), latch), mv) in motors.zip(samples) {
+            // a tripped motor is disabled until its fault is cleared
+            if latch.fault().is_some() {
+                continue;
+            }
+            if let Err(fault) = monitor.update(mv.into()) {
+                latch.trip(fault).unwrap();
+                monitor.reset();
+                error!(
+                    "motor {} overcurrent ({} mA), driver disabled",
+                    name, fault.current_ma
+                );
+            }
+        }
+        ticker.next().await;
+    }
 }
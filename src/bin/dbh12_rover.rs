@@ -0,0 +1,125 @@
+//! Demo driving a two wheeled rover via ESP32C3 & DBH12 driver
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO5: right motor IN1 (DBH12 IN2A)
+//! - GPIO6: left motor IN1 (DBH12 IN1A)
+//! - GPIO7: left motor IN2 (DBH12 IN1B)
+//! - GPIO9: button (momentary, wired to ground)
+//! - GPIO10: right motor IN2 (DBH12 IN2B)
+//! - GPIO20: right motor enable (DBH12 EN2)
+//! - GPIO21: left motor enable (DBH12 EN1)
+//!
+//! Wire the motors so that positive speeds drive both wheels forward (the right motor is
+//! usually mirrored, so its leads are swapped). Speeds are open-loop estimates from
+//! MAX_SPEED_MM_S (the wheel speed at full duty), so measure it on the floor for the distances
+//! and angles below to come out right.
+//!
+//! On each button press the rover drives a SIDE_MM square, pivoting on the spot at each
+//! corner, then a figure of eight from two ARC_RADIUS_MM circles. The same moves are checked
+//! against the rover's integrated pose by the differential scenario of the host simulator
+//! (see sim/).
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use esp_hal::{
+    gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull},
+    ledc::{LSGlobalClkSource, Ledc, LowSpeed, channel, timer},
+    time::Rate,
+    timer::timg::TimerGroup,
+};
+use esp_sandbox::motor::{
+    differential::{DifferentialDrive, DriveConfig, Saturation},
+    hbridge::{Dbh12, HBridge},
+    ledc::{configure_channel, configure_timer},
+};
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const CONFIG: DriveConfig = DriveConfig {
+    track_width_mm: 150.0,
+    max_speed_mm_s: 500.0,
+    max_duty: 95,
+    saturation: Saturation::Scale,
+};
+const SIDE_MM: f32 = 300.0;
+const ARC_RADIUS_MM: f32 = 200.0;
+const SPEED_MM_S: f32 = 200.0;
+const PIVOT_RATE_DEG_S: f32 = 90.0;
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // disable drivers and drive PWM pins low during setup
+    let output_config = OutputConfig::default().with_pull(Pull::Down);
+    let enable_left = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let enable_right = Output::new(peripherals.GPIO20, Level::Low, output_config);
+
+    // initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // initialize ledc peripheral
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    // initialize ledc timer, shared by both motors
+    let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    configure_timer(
+        &mut lstimer0,
+        timer::config::Duty::Duty8Bit,
+        Rate::from_khz(1),
+    )
+    .unwrap();
+
+    // initialize pwm channels (0/1 for the left motor, 2/3 for the right)
+    let mut channels = [
+        ledc.channel(channel::Number::Channel0, peripherals.GPIO6),
+        ledc.channel(channel::Number::Channel1, peripherals.GPIO7),
+        ledc.channel(channel::Number::Channel2, peripherals.GPIO5),
+        ledc.channel(channel::Number::Channel3, peripherals.GPIO10),
+    ];
+    for channel in &mut channels {
+        configure_channel(channel, &lstimer0).unwrap();
+    }
+    let [left_in1, left_in2, right_in1, right_in2] = channels;
+    let left = HBridge::<Dbh12, _>::new(left_in1, left_in2).with_enable(enable_left);
+    let right = HBridge::<Dbh12, _>::new(right_in1, right_in2).with_enable(enable_right);
+    let mut rover = DifferentialDrive::new(left, right, CONFIG);
+
+    // initialize input button
+    let mut input = Input::new(
+        peripherals.GPIO9,
+        InputConfig::default().with_pull(Pull::Up),
+    );
+
+    loop {
+        // wait for input button to be triggered
+        info!("waiting for input...");
+        input.wait_for_falling_edge().await;
+        rover.set_enabled(true).unwrap();
+
+        info!("driving square");
+        for _ in 0..4 {
+            rover.straight_by(SIDE_MM, SPEED_MM_S).await.unwrap();
+            rover.pivot_by(90.0, PIVOT_RATE_DEG_S).await.unwrap();
+        }
+
+        info!("driving figure of eight");
+        rover
+            .arc_by(360.0, ARC_RADIUS_MM, SPEED_MM_S)
+            .await
+            .unwrap();
+        rover
+            .arc_by(360.0, -ARC_RADIUS_MM, SPEED_MM_S)
+            .await
+            .unwrap();
+
+        rover.set_enabled(false).unwrap();
+    }
+}
@@ -0,0 +1,160 @@
+//! Demo of closed-loop dc motor speed control via ESP32C3 & DBH12 driver
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO0: motor encoder channel A
+//! - GPIO1: motor encoder channel B
+//! - GPIO6: motor A IN1 (DBH12 IN1)
+//! - GPIO7: motor A IN2 (DBH12 IN2)
+//! - GPIO9: button (momentary, wired to ground)
+//! - GPIO21: motor A enable (DBH12 EN)
+//!
+//! Example is written for a geared motor with a hall effect quadrature encoder on the motor
+//! shaft (e.g. JGA25-370), with speeds measured at the gearbox output. For a single hall
+//! sensor, count its pulses with a PulseCounter and measure them with Tachometer::pulses.
+//! Channel A must lead channel B when the motor turns forward (swap them otherwise), or the
+//! speed loop drives the motor away from its target.
+//!
+//! Each button press steps through TARGETS_RPM. The speed loop runs every LOOP_PERIOD_MS,
+//! with feed-forward from NO_LOAD_RPM and the duty limited to PWM_MAX. The target, measured
+//! speed and duty are reported every REPORT_PERIOD_MS. The same loop is run against a
+//! simulated motor by the speed_pid scenario of the host simulator (see sim/).
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_time::{Duration, Timer};
+use esp_hal::{
+    gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pull},
+    ledc::{LSGlobalClkSource, Ledc, LowSpeed, channel, timer},
+    time::Rate,
+    timer::timg::TimerGroup,
+};
+use esp_sandbox::{
+    encoder::{EncoderCount, QuadratureEncoder},
+    motor::{
+        hbridge::{Dbh12, HBridge},
+        ledc::{configure_channel, configure_timer},
+        pid::{Pid, PidGains},
+        speed::{SharedSpeed, SpeedController, Tachometer},
+    },
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const PWM_MAX: i8 = 95;
+const LOOP_PERIOD_MS: u64 = 10;
+const ENCODER_LINES_PER_REV: u32 = 11; // per motor revolution
+const GEAR_RATIO: u32 = 30;
+const NO_LOAD_RPM: f32 = 300.0; // at the gearbox output, at full duty
+const GAINS: PidGains = PidGains {
+    kp: 0.15, // %/rpm
+    ki: 1.5,  // %/(rpm·s)
+    kd: 0.0,  // %/(rpm/s)
+    kff: 100.0 / NO_LOAD_RPM,
+};
+const TARGETS_RPM: [f32; 5] = [100.0, 200.0, 0.0, -150.0, 0.0];
+const REPORT_PERIOD_MS: u64 = 500;
+
+// Calculated values
+const COUNTS_PER_REV: u32 = 4 * ENCODER_LINES_PER_REV * GEAR_RATIO;
+
+/// H-bridge driven by the speed loop
+type Motor = HBridge<Dbh12, channel::Channel<'static, LowSpeed>, Output<'static>>;
+
+// count shared between the encoder and speed loop tasks
+static ENCODER_COUNT: EncoderCount = EncoderCount::new();
+
+// target and measured speed shared between the speed loop and main tasks
+static SPEED: SharedSpeed = SharedSpeed::new();
+
+#[esp_hal_embassy::main]
+async fn main(spawner: Spawner) {
+    // initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // disable driver and drive PWM pins low during setup
+    let output_config = OutputConfig::default().with_pull(Pull::Down);
+    let enable = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let in0 = Output::new(peripherals.GPIO6, Level::Low, output_config);
+    let in1 = Output::new(peripherals.GPIO7, Level::Low, output_config);
+
+    // initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // initialize ledc peripheral
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    // initialize ledc timer, shared with the speed loop task
+    static LSTIMER0: StaticCell<timer::Timer<'static, LowSpeed>> = StaticCell::new();
+    let lstimer0 = LSTIMER0.init(ledc.timer::<LowSpeed>(timer::Number::Timer0));
+    configure_timer(lstimer0, timer::config::Duty::Duty8Bit, Rate::from_khz(1)).unwrap();
+    let lstimer0: &'static timer::Timer<'static, LowSpeed> = lstimer0;
+
+    // initialize pwm channels
+    let mut channel0 = ledc.channel(channel::Number::Channel0, in0);
+    let mut channel1 = ledc.channel(channel::Number::Channel1, in1);
+    configure_channel(&mut channel0, lstimer0).unwrap();
+    configure_channel(&mut channel1, lstimer0).unwrap();
+    let mut motor = HBridge::<Dbh12, _>::new(channel0, channel1).with_enable(enable);
+    motor.set_enabled(true).unwrap();
+
+    // initialize async tasks
+    spawner.must_spawn(encoder_manager(
+        peripherals.GPIO0.into(),
+        peripherals.GPIO1.into(),
+    ));
+    spawner.must_spawn(speed_loop(motor));
+
+    // initialize input button
+    let mut input = Input::new(
+        peripherals.GPIO9,
+        InputConfig::default().with_pull(Pull::Up),
+    );
+
+    // step through the targets on each button press, reporting in between
+    for target in TARGETS_RPM.iter().cycle() {
+        info!("target speed: {} rpm", target);
+        SPEED.set_target(*target);
+        loop {
+            let report = Timer::after_millis(REPORT_PERIOD_MS);
+            match select(input.wait_for_falling_edge(), report).await {
+                Either::First(_) => break,
+                Either::Second(_) => info!(
+                    "target: {} rpm, measured: {} rpm, duty: {}%",
+                    SPEED.target(),
+                    SPEED.measured(),
+                    SPEED.duty(),
+                ),
+            }
+        }
+    }
+}
+
+/// Task decoding the encoder into ENCODER_COUNT
+#[embassy_executor::task]
+async fn encoder_manager(a_pin: AnyPin<'static>, b_pin: AnyPin<'static>) {
+    let input_config = InputConfig::default().with_pull(Pull::Up);
+    let a = Input::new(a_pin, input_config);
+    let b = Input::new(b_pin, input_config);
+    let error = QuadratureEncoder::new(a, b).run(&ENCODER_COUNT).await;
+    warn!("encoder stopped: {}", error);
+}
+
+/// Task running the speed loop, following the target in SPEED
+#[embassy_executor::task]
+async fn speed_loop(mut motor: Motor) {
+    let pid = Pid::new(GAINS, -PWM_MAX as f32, PWM_MAX as f32);
+    let tachometer = Tachometer::quadrature(&ENCODER_COUNT, COUNTS_PER_REV);
+    let period = Duration::from_millis(LOOP_PERIOD_MS);
+    let mut controller = SpeedController::new(pid, tachometer, period);
+    let error = controller.run(&mut motor, &SPEED).await;
+    warn!("speed loop stopped: {}", error);
+    motor.set_enabled(false).unwrap();
+}
@@ -13,14 +13,14 @@ use defmt::info;
 use embassy_executor::Spawner;
 use embassy_time::Timer;
 use esp_hal::{
-    ledc::{
-        LSGlobalClkSource, Ledc, LowSpeed,
-        channel::{self, ChannelIFace},
-        timer::{self, TimerIFace},
-    },
+    ledc::{LSGlobalClkSource, Ledc, LowSpeed, channel, timer},
     time::Rate,
     timer::timg::TimerGroup,
 };
+use esp_sandbox::motor::{
+    hbridge::{Drv8871, HBridge},
+    ledc::{FadeChannel, bind_fade_interrupt, configure_timer},
+};
 use {defmt_rtt as _, esp_backtrace as _};
 
 #[esp_hal_embassy::main]
@@ -33,63 +33,38 @@ async fn main(_spawner: Spawner) {
     // initialize ledc peripheral
     let mut ledc = Ledc::new(peripherals.LEDC);
     ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+    bind_fade_interrupt(&mut ledc);
 
-    // initialize ledc timers
-    let timer_config = timer::config::Config {
-        duty: timer::config::Duty::Duty8Bit,
-        clock_source: timer::LSClockSource::APBClk,
-        frequency: Rate::from_khz(1),
-    };
+    // initialize ledc timer
     let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
-    let mut lstimer1 = ledc.timer::<LowSpeed>(timer::Number::Timer1);
-    lstimer0.configure(timer_config).unwrap();
-    lstimer1.configure(timer_config).unwrap();
+    configure_timer(
+        &mut lstimer0,
+        timer::config::Duty::Duty8Bit,
+        Rate::from_khz(1),
+    )
+    .unwrap();
 
     //initialize pwm channels
-    let mut channel0 = ledc.channel(channel::Number::Channel0, peripherals.GPIO8);
-    let mut channel1 = ledc.channel(channel::Number::Channel1, peripherals.GPIO9);
-    configure_channel(&mut channel0, &lstimer0);
-    configure_channel(&mut channel1, &lstimer1);
+    let mut channel0 = FadeChannel::new(&ledc, channel::Number::Channel0, peripherals.GPIO8);
+    let mut channel1 = FadeChannel::new(&ledc, channel::Number::Channel1, peripherals.GPIO9);
+    channel0.configure(&lstimer0).unwrap();
+    channel1.configure(&lstimer0).unwrap();
+    let mut motor = HBridge::<Drv8871, _>::new(channel0, channel1);
 
     loop {
         info!("starting forward ramp up");
-        channel0.start_duty_fade(0, 100, 2500).unwrap();
-        while channel0.is_duty_fade_running() {
-            Timer::after_millis(10).await;
-        }
+        motor.ramp(100, 2500).await.unwrap();
 
         info!("starting forward ramp down");
-        channel0.start_duty_fade(100, 0, 2500).unwrap();
-        while channel0.is_duty_fade_running() {
-            Timer::after_millis(10).await;
-        }
+        motor.ramp(0, 2500).await.unwrap();
 
         info!("starting backward ramp up");
-        channel1.start_duty_fade(0, 100, 2500).unwrap();
-        while channel1.is_duty_fade_running() {
-            Timer::after_millis(10).await;
-        }
+        motor.ramp(-100, 2500).await.unwrap();
 
         info!("starting backward ramp down");
-        channel1.start_duty_fade(100, 0, 2500).unwrap();
-        while channel1.is_duty_fade_running() {
-            Timer::after_millis(10).await;
-        }
+        motor.ramp(0, 2500).await.unwrap();
 
         info!("pausing");
         Timer::after_secs(5).await;
     }
 }
-
-/// Configure ledc channel for PWM output.
-fn configure_channel<'a>(
-    channel: &mut channel::Channel<'a, LowSpeed>,
-    timer: &'a timer::Timer<'a, LowSpeed>,
-) {
-    let config = channel::config::Config {
-        timer,
-        duty_pct: 0,
-        pin_config: channel::config::PinConfig::PushPull,
-    };
-    channel.configure(config).unwrap()
-}
@@ -0,0 +1,167 @@
+//! Demo driving two stepper axes from G-code received over UART via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO4: UART TX (to host RX)
+//! - GPIO5: UART RX (from host TX)
+//! - GPIO6: X axis stepper (DRV8825 DIR)
+//! - GPIO7: X axis stepper (DRV8825 STEP)
+//! - GPIO20: Y axis stepper (DRV8825 DIR)
+//! - GPIO21: Y axis stepper (DRV8825 STEP)
+//!
+//! Example is written assuming both DRV8825 boards are configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! Each received line is answered with "ok" (or the position for M114) once it has been
+//! executed, or with "error: ..." if it could not be parsed, so host tools can stream
+//! programs line by line.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use core::fmt::Write;
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use esp_hal::{
+    Async,
+    gpio::{AnyPin, Level, Output, OutputConfig},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    timer::{AnyTimer, timg::TimerGroup},
+    uart::{self, Uart, UartTx},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::{
+    gcode::{
+        interpreter::{Interpreter, InterpreterConfig},
+        parser::{LineBuffer, parse_line},
+    },
+    stepper::{
+        driver::{Drv8825, StepDir},
+        multi_axis::MultiAxis,
+        units::{Microsteps, Steps},
+    },
+};
+use heapless::String;
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const MM_PER_REV: f32 = 8.0; // lead screw pitch
+const MAX_VELOCITY: u32 = 2_000; // steps/s
+const ACCELERATION: u32 = 4_000; // steps/s²
+const DEFAULT_FEEDRATE: f32 = 600.0; // mm/min
+const BAUD_RATE: u32 = 115_200;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const STEPS_PER_MM: f32 = STEPS_PER_REV.get() as f32 / MM_PER_REV;
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize serial link to host
+    let uart_config = uart::Config::default().with_baudrate(BAUD_RATE);
+    let uart = Uart::new(peripherals.UART1, uart_config)
+        .unwrap()
+        .with_tx(peripherals.GPIO4)
+        .with_rx(peripherals.GPIO5)
+        .into_async();
+
+    // Initialize higher priority executor for step generation (see stepper_async.rs)
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let spawner = executor.start(Priority::Priority3);
+    spawner.must_spawn(gcode_manager(
+        uart,
+        [
+            (peripherals.GPIO6.into(), peripherals.GPIO7.into()),
+            (peripherals.GPIO20.into(), peripherals.GPIO21.into()),
+        ],
+    ));
+}
+
+/// Task reading G-code lines from the host and executing them
+#[embassy_executor::task]
+async fn gcode_manager(uart: Uart<'static, Async>, pins: [(AnyPin<'static>, AnyPin<'static>); 2]) {
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let drivers = pins.map(|(dir_pin, step_pin)| {
+        let dir = Output::new(dir_pin, Level::High, output_config);
+        let step = Output::new(step_pin, Level::Low, output_config);
+        StepDir::<Drv8825, _>::new(dir, step)
+            .with_fixed_microsteps(MICRO_STEP_MODE.get())
+            .unwrap()
+    });
+    let mut axes = MultiAxis::new(drivers);
+    let mut interpreter = Interpreter::new(InterpreterConfig {
+        steps_per_unit: [STEPS_PER_MM; 2],
+        max_velocity: MAX_VELOCITY,
+        acceleration: ACCELERATION,
+        default_feedrate: DEFAULT_FEEDRATE,
+    });
+
+    let (mut rx, mut tx) = uart.split();
+    let mut line_buffer = LineBuffer::<96>::new();
+    let mut rx_buffer = [0u8; 32];
+    info!("waiting for G-code...");
+
+    // Event loop
+    loop {
+        let Ok(len) = rx.read_async(&mut rx_buffer).await else {
+            warn!("uart read failed");
+            continue;
+        };
+        for byte in &rx_buffer[..len] {
+            let Some(line) = line_buffer.push(*byte) else {
+                continue;
+            };
+
+            let mut response: String<96> = String::new();
+            match line.map(parse_line) {
+                Ok(Ok(Some(command))) => {
+                    info!("executing: {}", command);
+                    match interpreter.execute(&mut axes, command).await {
+                        Ok(result) => write!(response, "{result}"),
+                        Err(e) => write!(response, "error: {e:?}"),
+                    }
+                }
+                Ok(Ok(None)) => write!(response, "ok"),
+                Ok(Err(e)) => write!(response, "error: {e:?}"),
+                Err(e) => write!(response, "error: {e:?}"),
+            }
+            .ok();
+            response.push('\n').ok();
+            write_all(&mut tx, response.as_bytes()).await;
+        }
+    }
+}
+
+/// Write a response to the host, discarding it if the UART fails.
+async fn write_all(tx: &mut UartTx<'static, Async>, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+        match tx.write_async(bytes).await {
+            Ok(written) => bytes = &bytes[written..],
+            Err(_) => {
+                warn!("uart write failed");
+                return;
+            }
+        }
+    }
+}
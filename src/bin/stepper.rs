@@ -15,10 +15,16 @@ use esp_hal::{
     gpio::{Level, Output, OutputConfig},
     main,
 };
+use esp_sandbox::stepper::{
+    driver::{Drv8825, StepDir, StepperDriver},
+    units::{Microsteps, Rpm, StepInterval, Steps},
+};
 use {defmt_rtt as _, esp_backtrace as _};
 
-const RPM: u32 = 60;
-const NUM_STEPS: u32 = 1600;
+const RPM: Rpm = Rpm::new(60);
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(8).checked_for::<Drv8825>();
+const NUM_STEPS: Steps = MICRO_STEP_MODE.steps_per_rev(200);
+const STEP_INTERVAL: StepInterval = StepInterval::from_rpm(RPM, NUM_STEPS).checked_for::<Drv8825>();
 
 #[main]
 fn main() -> ! {
@@ -26,24 +32,28 @@ fn main() -> ! {
     let peripherals = esp_hal::init(esp_hal::Config::default());
     let delay = Delay::new();
 
-    // Initialize led
+    // Initialize stepper driver
     let output_config = OutputConfig::default();
-    let mut _dir = Output::new(peripherals.GPIO20, Level::High, output_config);
-    let mut step = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let dir = Output::new(peripherals.GPIO20, Level::High, output_config);
+    let step = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let mut driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
 
-    // Calculate delay time for square wave
-    let delay_time: u32 = 60 * 1_000_000 / RPM / NUM_STEPS / 2;
-    info!("delay time: {}", delay_time);
+    // Delay times for square wave
+    let high_time = STEP_INTERVAL.high_us();
+    let low_time = STEP_INTERVAL.low_us();
+    info!("delay time: {} / {}", high_time, low_time);
 
     // Event loop
     let mut counter = 0;
     loop {
         info!("{}: start rotation", counter);
-        for _ in 0..NUM_STEPS {
-            step.set_high();
-            delay.delay_micros(delay_time);
-            step.set_low();
-            delay.delay_micros(delay_time);
+        for _ in 0..NUM_STEPS.get() {
+            driver.set_step(true).unwrap();
+            delay.delay_micros(high_time);
+            driver.set_step(false).unwrap();
+            delay.delay_micros(low_time);
         }
         info!("pause");
         delay.delay_millis(2_000);
@@ -0,0 +1,84 @@
+//! Demo rotating a 28BYJ-48 unipolar stepper motor via ESP32C3 & ULN2003 board
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO4: stepper (ULN2003 IN1)
+//! - GPIO5: stepper (ULN2003 IN2)
+//! - GPIO6: stepper (ULN2003 IN3)
+//! - GPIO7: stepper (ULN2003 IN4)
+//!
+//! The ULN2003 board and motor are powered from 5V, separately from the ESP32C3 3.3V rail.
+//!
+//! The output shaft turns one revolution forward and back using the STEP_MODE coil sequence,
+//! pausing after each move. The coils are switched off once idle for IDLE_TIMEOUT_MS, since
+//! the motor otherwise draws its full current (and gets hot) while stationary.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_time::Duration;
+use esp_hal::{
+    gpio::{Level, Output, OutputConfig},
+    timer::timg::TimerGroup,
+};
+use esp_sandbox::stepper::{
+    axis::{Axis, AxisConfig, Transmission},
+    controller::Stepper,
+    generator::SoftwareStepGenerator,
+    uln2003::{GEAR_RATIO_28BYJ48, MOTOR_STEPS_PER_REV_28BYJ48, StepMode, Uln2003},
+    units::{Microsteps, Steps},
+};
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const STEP_MODE: StepMode = StepMode::Half;
+const MAX_VELOCITY: f32 = 60.0; // degrees/s (10 RPM)
+const ACCELERATION: f32 = 120.0; // degrees/s²
+const PAUSE_SEC: u64 = 2;
+const IDLE_TIMEOUT_MS: u64 = 500;
+
+// Calculated values
+const STEPS_PER_REV: Steps =
+    Microsteps::new(STEP_MODE.divisor()).steps_per_rev(MOTOR_STEPS_PER_REV_28BYJ48);
+const AXIS_CONFIG: AxisConfig =
+    AxisConfig::new(STEPS_PER_REV, GEAR_RATIO_28BYJ48, Transmission::Rotary);
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // Initialize motor control GPIO (coils off)
+    let output_config = OutputConfig::default();
+    let pins = [
+        Output::new(peripherals.GPIO4, Level::Low, output_config),
+        Output::new(peripherals.GPIO5, Level::Low, output_config),
+        Output::new(peripherals.GPIO6, Level::Low, output_config),
+        Output::new(peripherals.GPIO7, Level::Low, output_config),
+    ];
+    let driver = Uln2003::new(pins, STEP_MODE);
+    let stepper = Stepper::new(driver, SoftwareStepGenerator::new())
+        .with_idle_timeout(Duration::from_millis(IDLE_TIMEOUT_MS));
+    let mut axis = Axis::new(stepper, AXIS_CONFIG);
+    info!(
+        "steps per output rev: {}",
+        AXIS_CONFIG.steps_per_unit() * 360.0
+    );
+
+    // Event loop
+    loop {
+        for delta in [360.0, -360.0] {
+            axis.move_by(delta).unwrap();
+            axis.run(MAX_VELOCITY, ACCELERATION).await.unwrap();
+            info!("position (degrees): {}", axis.position());
+            let pause = Duration::from_secs(PAUSE_SEC);
+            axis.stepper_mut().pause(pause).await.unwrap();
+        }
+    }
+}
@@ -4,42 +4,36 @@
 //! - GPIO20: stepper (DRV8825 DIR)
 //! - GPIO21: stepper (DRV8825 STEP)
 //!
-//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE_DIVISOR value
-//! e.g. MICRO_STEP_MODE_DIVISOR = 8 -> 1/8th step mode
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! Each rotation follows a trapezoidal profile, accelerating at ACCELERATION up to RPM before
+//! decelerating back to rest. These parameters and the event loop live in
+//! `esp_sandbox::stepper::cycle`, so that the host simulator in `sim/` runs the same stepping
+//! logic against simulated time and writes the STEP/DIR signals to a VCD trace.
 
 #![no_std]
 #![no_main]
 #![feature(impl_trait_in_assoc_type)]
 
+use core::ops::ControlFlow;
+
 use defmt::info;
 use embassy_executor::Spawner;
-use embassy_time::{Duration, Instant, Ticker, Timer};
+use embassy_time::Timer;
 use esp_hal::{
     gpio::{AnyPin, Level, Output, OutputConfig},
     interrupt::{Priority, software::SoftwareInterruptControl},
     timer::{AnyTimer, timg::TimerGroup},
 };
 use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::stepper::{
+    cycle::{self, CRUISE_INTERVAL, MICRO_STEP_MODE, PROFILE, RUN_TIME_US, TOTAL_CYCLE_TIME_US},
+    driver::{Drv8825, StepDir},
+};
 use static_cell::StaticCell;
 use {defmt_rtt as _, esp_backtrace as _};
 
-// Inputs
-const MOTOR_STEPS_PER_REV: u32 = 200;
-const MICRO_STEP_MODE_DIVISOR: u32 = 2;
-const RPM: u32 = 320;
-const NUM_REVS: u32 = 16;
-const PAUSE_SEC: u32 = 2;
-const SAMPLE_INTERVAL: u32 = 100;
-
-// Calculated values
-const NUM_STEPS: u32 = NUM_REVS * MOTOR_STEPS_PER_REV * MICRO_STEP_MODE_DIVISOR;
-const LOG_SAMPLES: u32 = NUM_STEPS / SAMPLE_INTERVAL;
-const DELAY_TIME_US: u32 =
-    60 * 1_000_000 / (2 * RPM * MOTOR_STEPS_PER_REV * MICRO_STEP_MODE_DIVISOR);
-const STEP_TIME_US: u32 = DELAY_TIME_US * 2;
-const RUN_TIME_US: u32 = NUM_STEPS * STEP_TIME_US;
-const TOTAL_CYCLE_TIME_US: u32 = RUN_TIME_US + PAUSE_SEC * 1_000_000;
-
 #[esp_hal_embassy::main]
 async fn main(_spawner: Spawner) {
     // Initialize hardware
@@ -71,74 +65,28 @@ async fn main(_spawner: Spawner) {
 /// Task to manage PWM output signal to DRV8825 driver
 #[embassy_executor::task]
 async fn pwm_manager(dir_pin: AnyPin<'static>, step_pin: AnyPin<'static>) {
-    info!("delay time (us): {}", DELAY_TIME_US);
+    info!("cruise step time (us): {}", CRUISE_INTERVAL.as_micros());
+    info!("accel steps: {}", PROFILE.accel_steps());
     info!("cycle time (us): {}", RUN_TIME_US);
     info!("total cycle time (us): {}", TOTAL_CYCLE_TIME_US);
-    info!("log samples (us): {}", LOG_SAMPLES);
 
     // Initial delay to prevent initialization issues
     Timer::after_millis(100).await;
 
     // Initialize motor control GPIO
     let output_config = OutputConfig::default();
-    let mut _dir = Output::new(dir_pin, Level::High, output_config);
-    let mut step = Output::new(step_pin, Level::Low, output_config);
-
-    // for logging key times
-    let mut high_start_times = [Instant::from_micros(0); LOG_SAMPLES as usize];
-    let mut low_start_times = [Instant::from_micros(0); LOG_SAMPLES as usize];
-    let mut end_cycle_times = [Instant::from_micros(0); LOG_SAMPLES as usize];
-
-    // start cycle
-    let overall_time = Instant::now();
-    let mut cycle_ticker = Ticker::every(Duration::from_micros(TOTAL_CYCLE_TIME_US.into()));
+    let dir = Output::new(dir_pin, Level::High, output_config);
+    let step = Output::new(step_pin, Level::Low, output_config);
+    let mut driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
 
     // Event loop
-    loop {
-        // get time at the start of this rotation
-        let initial_time = Instant::now();
-        let mut ticker = Ticker::every(Duration::from_micros(DELAY_TIME_US.into()));
-
-        // perform 1 rotation
-        for i in 0..NUM_STEPS as usize {
-            let is_sample = i % SAMPLE_INTERVAL as usize == 0;
-            let log_index = i / SAMPLE_INTERVAL as usize;
-
-            step.set_high();
-            if is_sample {
-                high_start_times[log_index] = Instant::now();
-            };
-            ticker.next().await;
-
-            step.set_low();
-            if is_sample {
-                low_start_times[log_index] = Instant::now();
-            };
-            ticker.next().await;
-
-            if is_sample {
-                end_cycle_times[log_index] = Instant::now();
-            };
-        }
-
-        // log out timing data
-        for i in 0..LOG_SAMPLES as usize {
-            let high_start_time = high_start_times[i];
-            let low_start_time = low_start_times[i];
-            let end_cycle_time = end_cycle_times[i];
-
-            let high_time = low_start_time.duration_since(high_start_time).as_micros();
-            let low_time = end_cycle_time.duration_since(low_start_time).as_micros();
-            let total_cycle_time = end_cycle_time.duration_since(high_start_time).as_micros();
-            let total_elapsed = end_cycle_time.duration_since(initial_time).as_millis();
-            let overall_elapsed = end_cycle_time.duration_since(overall_time).as_secs();
-            info!(
-                "step {}: {} - {} - {} - {} - {}",
-                i, high_time, low_time, total_cycle_time, total_elapsed, overall_elapsed
-            );
-        }
-
-        // wait until "pause" period ends
-        cycle_ticker.next().await;
-    }
+    cycle::run(&mut driver, |rotation, stats| {
+        // log out timing summary
+        info!("rotation {}: {}", rotation.index, stats);
+        ControlFlow::Continue(())
+    })
+    .await
+    .unwrap();
 }
@@ -0,0 +1,102 @@
+//! Demo microstepping a bipolar stepper motor via ESP32C3 & two DRV8871 H-bridges
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO8: stepper coil A (DRV8871 #1 IN1)
+//! - GPIO9: stepper coil A (DRV8871 #1 IN2)
+//! - GPIO6: stepper coil B (DRV8871 #2 IN1)
+//! - GPIO7: stepper coil B (DRV8871 #2 IN2)
+//!
+//! The coil currents follow sine/cosine tables in software, at MICROSTEPS microsteps per full
+//! step (4 to 32). The H-bridges drive the coils in voltage mode, so AMPLITUDE_PCT should keep
+//! the supply voltage times the duty at or below the motor's rated voltage.
+//!
+//! The motor turns one revolution forward and back, pausing after each move. The coils are
+//! switched off once idle for IDLE_TIMEOUT_MS.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_time::Duration;
+use esp_hal::{
+    ledc::{LSGlobalClkSource, Ledc, LowSpeed, channel, timer},
+    time::Rate,
+    timer::timg::TimerGroup,
+};
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    motor::ledc::{configure_channel, configure_timer},
+    stepper::{
+        bipolar::DualHBridge,
+        controller::Stepper,
+        driver::StepperDriver,
+        generator::SoftwareStepGenerator,
+        units::{Microsteps, Steps},
+    },
+};
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICROSTEPS: Microsteps = Microsteps::new(8);
+const AMPLITUDE_PCT: u8 = 80;
+const PWM_FREQUENCY_KHZ: u32 = 20; // above the audible range
+const MAX_VELOCITY: u32 = 800; // steps/s
+const ACCELERATION: u32 = 4_000; // steps/s²
+const PAUSE_SEC: u64 = 2;
+const IDLE_TIMEOUT_MS: u64 = 500;
+
+// Calculated values
+const STEPS_PER_REV: Steps = MICROSTEPS.steps_per_rev(MOTOR_STEPS_PER_REV);
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // Initialize ledc peripheral
+    let mut ledc = Ledc::new(peripherals.LEDC);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    // Initialize ledc timer, shared by all four H-bridge inputs
+    let mut lstimer0 = ledc.timer::<LowSpeed>(timer::Number::Timer0);
+    let frequency = Rate::from_khz(PWM_FREQUENCY_KHZ);
+    configure_timer(&mut lstimer0, timer::config::Duty::Duty10Bit, frequency).unwrap();
+
+    // Initialize pwm channels (coils off)
+    let mut coil_a = [
+        ledc.channel(channel::Number::Channel0, peripherals.GPIO8),
+        ledc.channel(channel::Number::Channel1, peripherals.GPIO9),
+    ];
+    let mut coil_b = [
+        ledc.channel(channel::Number::Channel2, peripherals.GPIO6),
+        ledc.channel(channel::Number::Channel3, peripherals.GPIO7),
+    ];
+    for channel in coil_a.iter_mut().chain(&mut coil_b) {
+        configure_channel(channel, &lstimer0).unwrap();
+    }
+
+    let mut driver = DualHBridge::new(coil_a, coil_b).with_amplitude(AMPLITUDE_PCT);
+    driver.set_microsteps(MICROSTEPS.get()).unwrap();
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new())
+        .with_idle_timeout(Duration::from_millis(IDLE_TIMEOUT_MS));
+
+    // Event loop
+    loop {
+        for delta in [STEPS_PER_REV.as_i32(), -STEPS_PER_REV.as_i32()] {
+            stepper.move_by(delta).unwrap();
+            let steps = stepper.remaining_steps();
+            let profile = TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, steps);
+            stepper.run(profile).await.unwrap();
+            info!("position (steps): {}", stepper.position());
+            let pause = Duration::from_secs(PAUSE_SEC);
+            stepper.pause(pause).await.unwrap();
+        }
+    }
+}
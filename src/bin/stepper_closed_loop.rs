@@ -0,0 +1,155 @@
+//! Demo of a stepper motor with quadrature encoder feedback via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO0: encoder channel A
+//! - GPIO1: encoder channel B
+//! - GPIO20: stepper (DRV8825 DIR)
+//! - GPIO21: stepper (DRV8825 STEP)
+//!
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! Channel A must lead channel B when the motor steps forward (swap them otherwise), or every
+//! move ends in a following error fault.
+//!
+//! The motor moves back and forth while the supervisor checks the encoder after every step.
+//! Missed steps (e.g. from briefly holding the shaft) are made up at the end of each move,
+//! while a stall aborts the move and re-aligns the commanded position to the encoder.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use esp_hal::{
+    gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pull},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    timer::{AnyTimer, timg::TimerGroup},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::{
+    encoder::{EncoderCount, QuadratureEncoder},
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        controller::Stepper,
+        driver::{Drv8825, StepDir},
+        generator::SoftwareStepGenerator,
+        supervisor::{Supervisor, SupervisorConfig, SupervisorError},
+        units::{Microsteps, Steps},
+    },
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const ENCODER_LINES_PER_REV: u32 = 600;
+const MAX_VELOCITY: u32 = 800; // steps/s
+const ACCELERATION: u32 = 2_000; // steps/s²
+const CORRECTION_PERIOD_US: u32 = 5_000;
+const NUM_REVS: u32 = 2;
+const PAUSE_SEC: u64 = 2;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const STEPS_PER_FULL_STEP: Steps = MICRO_STEP_MODE.steps_per_rev(1);
+const NUM_STEPS: i32 = STEPS_PER_REV.times(NUM_REVS).as_i32();
+const SUPERVISOR_CONFIG: SupervisorConfig = SupervisorConfig {
+    steps_per_rev: STEPS_PER_REV.get(),
+    counts_per_rev: 4 * ENCODER_LINES_PER_REV,
+    // rotor lag under load is up to ~2 full steps
+    correction_threshold: STEPS_PER_FULL_STEP.times(2).get(),
+    fault_threshold: STEPS_PER_FULL_STEP.times(8).get(),
+};
+
+// count shared between the encoder and step manager tasks
+static ENCODER_COUNT: EncoderCount = EncoderCount::new();
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize higher priority executor for step generation and encoder decoding
+    // (see stepper_async.rs)
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let spawner = executor.start(Priority::Priority3);
+    spawner.must_spawn(encoder_manager(
+        peripherals.GPIO0.into(),
+        peripherals.GPIO1.into(),
+    ));
+    spawner.must_spawn(step_manager(
+        peripherals.GPIO20.into(),
+        peripherals.GPIO21.into(),
+    ));
+}
+
+/// Task decoding the encoder into ENCODER_COUNT
+#[embassy_executor::task]
+async fn encoder_manager(a_pin: AnyPin<'static>, b_pin: AnyPin<'static>) {
+    let input_config = InputConfig::default().with_pull(Pull::Up);
+    let a = Input::new(a_pin, input_config);
+    let b = Input::new(b_pin, input_config);
+    let error = QuadratureEncoder::new(a, b).run(&ENCODER_COUNT).await;
+    warn!("encoder stopped: {}", error);
+}
+
+/// Task moving the stepper under encoder supervision
+#[embassy_executor::task]
+async fn step_manager(dir_pin: AnyPin<'static>, step_pin: AnyPin<'static>) {
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let dir = Output::new(dir_pin, Level::High, output_config);
+    let step = Output::new(step_pin, Level::Low, output_config);
+    let driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+    let mut supervisor = Supervisor::new(SUPERVISOR_CONFIG);
+    supervisor.align(stepper.position(), ENCODER_COUNT.get());
+
+    // Event loop
+    loop {
+        for delta in [NUM_STEPS, -NUM_STEPS] {
+            stepper.move_by(delta).unwrap();
+            let profile =
+                TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, stepper.remaining_steps());
+            let result = supervisor
+                .run(&mut stepper, &ENCODER_COUNT, profile, CORRECTION_PERIOD_US)
+                .await;
+
+            let count = ENCODER_COUNT.get();
+            match result {
+                Ok(corrected) => info!(
+                    "position: {}, following error: {}, corrected: {}, encoder errors: {}",
+                    stepper.position(),
+                    supervisor.following_error(stepper.position(), count),
+                    corrected,
+                    ENCODER_COUNT.errors(),
+                ),
+                Err(SupervisorError::FollowingError(error)) => {
+                    warn!("stalled with following error {}, re-aligning", error);
+                    stepper.set_position(supervisor.measured_steps(count));
+                }
+                Err(e) => panic!("{}", e),
+            }
+            Timer::after_secs(PAUSE_SEC).await;
+        }
+    }
+}
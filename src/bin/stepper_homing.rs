@@ -0,0 +1,133 @@
+//! Demo homing a stepper motor against a hall effect limit switch via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO3: stepper (DRV8825 nENBL)
+//! - GPIO4: stepper (DRV8825 nSLEEP)
+//! - GPIO5: stepper (DRV8825 nRESET)
+//! - GPIO6: stepper (DRV8825 nFAULT, open drain)
+//! - GPIO8: hall effect sensor (limit switch, low when triggered)
+//! - GPIO20: stepper (DRV8825 DIR)
+//! - GPIO21: stepper (DRV8825 STEP)
+//!
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode, and that the motor drives a
+//! lead screw with a lead of LEAD_UM.
+//!
+//! After homing, the axis repeatedly moves out to TRAVEL_MM and back to zero. The driver is
+//! disabled once idle for IDLE_TIMEOUT_MS during each pause, and woken again before the next
+//! move. A driver fault aborts the move, after which the driver is reset and the axis re-homed.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::{error, info, warn};
+use embassy_executor::Spawner;
+use embassy_time::{Duration, Timer};
+use esp_hal::{
+    gpio::{Input, InputConfig, Level, Output, OutputConfig, Pull},
+    timer::timg::TimerGroup,
+};
+use esp_sandbox::stepper::{
+    Direction, Error,
+    axis::{Axis, AxisConfig, GearRatio, Transmission},
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    homing::{self, HomingConfig},
+    units::{Microsteps, Steps},
+};
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const LEAD_UM: u32 = 8_000;
+const MAX_VELOCITY: f32 = 20.0; // mm/s
+const ACCELERATION: f32 = 80.0; // mm/s²
+const TRAVEL_MM: f32 = 32.0;
+const PAUSE_SEC: u64 = 2;
+const IDLE_TIMEOUT_MS: u64 = 500;
+
+// Calculated values
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const AXIS_CONFIG: AxisConfig = AxisConfig::new(
+    STEPS_PER_REV,
+    GearRatio::DIRECT,
+    Transmission::lead_screw(LEAD_UM),
+);
+
+const HOMING_CONFIG: HomingConfig = HomingConfig {
+    direction: Direction::Reverse,
+    fast_period_us: 1_000,
+    slow_period_us: 5_000,
+    max_travel: AXIS_CONFIG.to_steps(1.25 * TRAVEL_MM) as u32,
+    backoff_steps: AXIS_CONFIG.to_steps(2.0) as u32,
+    active_low: true,
+    timeout: Duration::from_secs(30),
+};
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // Initialize limit switch & stepper (starting disabled and asleep)
+    let input_config = InputConfig::default().with_pull(Pull::Up);
+    let mut switch = Input::new(peripherals.GPIO8, input_config);
+    let fault = Input::new(peripherals.GPIO6, input_config);
+    let output_config = OutputConfig::default();
+    let enable = Output::new(peripherals.GPIO3, Level::High, output_config);
+    let sleep = Output::new(peripherals.GPIO4, Level::Low, output_config);
+    let reset = Output::new(peripherals.GPIO5, Level::High, output_config);
+    let dir = Output::new(peripherals.GPIO20, Level::High, output_config);
+    let step = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_enable(enable)
+        .with_sleep(sleep)
+        .with_reset(reset)
+        .with_fault(fault)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+    let stepper = Stepper::new(driver, SoftwareStepGenerator::new())
+        .with_idle_timeout(Duration::from_millis(IDLE_TIMEOUT_MS));
+    let mut axis = Axis::new(stepper, AXIS_CONFIG);
+
+    // Event loop
+    loop {
+        // Establish reference position
+        info!("homing...");
+        if let Err(e) = homing::home(axis.stepper_mut(), &mut switch, &HOMING_CONFIG).await {
+            error!("homing failed: {}", e);
+            return;
+        }
+        axis.sync();
+        info!("homed");
+
+        // Move back and forth until the driver reports a fault
+        let error = 'moving: loop {
+            for target in [TRAVEL_MM, 0.0] {
+                axis.move_to(target).unwrap();
+                if let Err(e) = axis.run(MAX_VELOCITY, ACCELERATION).await {
+                    break 'moving e;
+                }
+                info!("position (mm): {}", axis.position());
+                let pause = Duration::from_secs(PAUSE_SEC);
+                axis.stepper_mut().pause(pause).await.unwrap();
+            }
+        };
+        if error != Error::Fault {
+            panic!("{}", error);
+        }
+        warn!(
+            "driver fault at position (mm) {}, resetting",
+            axis.position()
+        );
+        axis.stepper_mut().reset_driver().await.unwrap();
+        Timer::after_secs(PAUSE_SEC).await;
+    }
+}
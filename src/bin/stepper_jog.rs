@@ -0,0 +1,123 @@
+//! Demo jogging a stepper motor by hand with two buttons via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO9: jog forward button (momentary, wired to ground)
+//! - GPIO10: jog reverse button (momentary, wired to ground)
+//! - GPIO20: stepper (DRV8825 DIR)
+//! - GPIO21: stepper (DRV8825 STEP)
+//!
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! Holding a button jogs the motor at JOG_RPM in that direction, and releasing it ramps back
+//! to rest. Switching buttons while moving ramps through zero into the other direction.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::Timer;
+use esp_hal::{
+    gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pull},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    timer::{AnyTimer, timg::TimerGroup},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::stepper::{
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    jog::{Jog, JogConfig},
+    units::{Microsteps, Rpm, Steps},
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const JOG_RPM: Rpm = Rpm::new(120);
+const MAX_RPM: Rpm = Rpm::new(320);
+const ACCELERATION: u32 = 4_000; // steps/s²
+const BUTTON_POLL_MS: u64 = 20;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const JOG_VELOCITY: i32 = JOG_RPM.steps_per_sec(STEPS_PER_REV) as i32;
+const JOG_CONFIG: JogConfig = JogConfig {
+    max_velocity: MAX_RPM.steps_per_sec(STEPS_PER_REV),
+    acceleration: ACCELERATION,
+};
+
+// target velocity shared between the button and step manager tasks
+static JOG: Jog<CriticalSectionRawMutex> = Jog::new();
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize higher priority executor for step generation (see stepper_async.rs)
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let spawner = executor.start(Priority::Priority3);
+    spawner.must_spawn(step_manager(
+        peripherals.GPIO20.into(),
+        peripherals.GPIO21.into(),
+    ));
+
+    // Initialize jog buttons
+    let input_config = InputConfig::default().with_pull(Pull::Up);
+    let forward = Input::new(peripherals.GPIO9, input_config);
+    let reverse = Input::new(peripherals.GPIO10, input_config);
+
+    // Event loop
+    let mut velocity = 0;
+    loop {
+        let target = match (forward.is_low(), reverse.is_low()) {
+            (true, false) => JOG_VELOCITY,
+            (false, true) => -JOG_VELOCITY,
+            _ => 0,
+        };
+        if target != velocity {
+            info!("jog velocity: {}", target);
+            JOG.set_velocity(target);
+            velocity = target;
+        }
+        Timer::after_millis(BUTTON_POLL_MS).await;
+    }
+}
+
+/// Task following the jog velocity
+#[embassy_executor::task]
+async fn step_manager(dir_pin: AnyPin<'static>, step_pin: AnyPin<'static>) {
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let dir = Output::new(dir_pin, Level::High, output_config);
+    let step = Output::new(step_pin, Level::Low, output_config);
+    let driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+
+    // Event loop
+    loop {
+        JOG.run(&mut stepper, &JOG_CONFIG).await.unwrap();
+        info!("stopped at position: {}", stepper.position());
+    }
+}
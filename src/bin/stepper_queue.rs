@@ -0,0 +1,149 @@
+//! Demo queueing stepper moves from thread-mode tasks via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO9: button (momentary, wired to ground)
+//! - GPIO20: stepper (DRV8825 DIR)
+//! - GPIO21: stepper (DRV8825 STEP)
+//!
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! The main task repeatedly queues a few moves and waits for each to finish, while the step
+//! manager task executes them on the high-priority executor. Pressing the button cancels the
+//! active move (decelerating to a stop) along with any moves still in the queue.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::Timer;
+use esp_hal::{
+    gpio::{AnyPin, Input, InputConfig, Level, Output, OutputConfig, Pull},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    timer::{AnyTimer, timg::TimerGroup},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::stepper::{
+    Direction,
+    controller::Stepper,
+    driver::{Drv8825, StepDir},
+    generator::SoftwareStepGenerator,
+    queue::{MoveQueue, MoveRequest},
+    units::{Microsteps, Steps},
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const ACCELERATION: u32 = 4_000; // steps/s²
+const QUEUE_DEPTH: usize = 4;
+const PAUSE_SEC: u64 = 2;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const MOVES: [MoveRequest; 3] = [
+    MoveRequest {
+        steps: STEPS_PER_REV.times(4).get(),
+        velocity: 2_000,
+        direction: Direction::Forward,
+    },
+    MoveRequest {
+        steps: STEPS_PER_REV.get() / 2,
+        velocity: 400,
+        direction: Direction::Reverse,
+    },
+    MoveRequest {
+        steps: STEPS_PER_REV.times(7).get() / 2,
+        velocity: 1_000,
+        direction: Direction::Reverse,
+    },
+];
+
+// moves shared between the thread-mode tasks and the step manager
+static QUEUE: MoveQueue<CriticalSectionRawMutex, QUEUE_DEPTH> = MoveQueue::new();
+
+#[esp_hal_embassy::main]
+async fn main(spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize higher priority executor for step generation (see stepper_async.rs)
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let high_priority_spawner = executor.start(Priority::Priority3);
+    high_priority_spawner.must_spawn(step_manager(
+        peripherals.GPIO20.into(),
+        peripherals.GPIO21.into(),
+    ));
+
+    // Initialize cancel button on the thread-mode executor
+    let button = Input::new(
+        peripherals.GPIO9,
+        InputConfig::default().with_pull(Pull::Up),
+    );
+    spawner.must_spawn(cancel_manager(button));
+
+    // Event loop
+    loop {
+        let tickets = [
+            QUEUE.submit(MOVES[0]).await,
+            QUEUE.submit(MOVES[1]).await,
+            QUEUE.submit(MOVES[2]).await,
+        ];
+        info!("queued {} moves", tickets.len());
+        for ticket in tickets {
+            let outcome = QUEUE.wait(ticket).await;
+            info!("{}: {}", ticket, outcome);
+        }
+        Timer::after_secs(PAUSE_SEC).await;
+    }
+}
+
+/// Task executing queued moves on the stepper
+#[embassy_executor::task]
+async fn step_manager(dir_pin: AnyPin<'static>, step_pin: AnyPin<'static>) {
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let dir = Output::new(dir_pin, Level::High, output_config);
+    let step = Output::new(step_pin, Level::Low, output_config);
+    let driver = StepDir::<Drv8825, _>::new(dir, step)
+        .with_fixed_microsteps(MICRO_STEP_MODE.get())
+        .unwrap();
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+
+    // Event loop
+    loop {
+        QUEUE.run_next(&mut stepper, ACCELERATION).await;
+        info!("position: {}", stepper.position());
+    }
+}
+
+/// Task cancelling all moves when the button is pressed
+#[embassy_executor::task]
+async fn cancel_manager(mut button: Input<'static>) {
+    loop {
+        button.wait_for_falling_edge().await;
+        info!("cancelling moves");
+        QUEUE.cancel();
+
+        // debounce
+        Timer::after_millis(50).await;
+    }
+}
@@ -0,0 +1,145 @@
+//! Demo comparing software and RMT generated step pulses via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO20: stepper (DRV8825 DIR)
+//! - GPIO21: stepper (DRV8825 STEP)
+//!
+//! Example is written assuming the DRV8825 board is configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! Rotations alternate between the software (embassy timer) and RMT step generators, logging
+//! the time taken by each so the two can be compared against the profile duration. Each
+//! rotation takes the STEP pin over in turn: as a GPIO output for the software generator, and
+//! as the RMT channel output for the hardware generator.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_time::{Instant, Timer};
+use esp_hal::{
+    gpio::{AnyPin, Level, Output, OutputConfig},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    peripherals::RMT,
+    rmt::{Rmt, TxChannelConfig, TxChannelCreatorAsync},
+    time::Rate,
+    timer::{AnyTimer, timg::TimerGroup},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        driver::{Drv8825, NoPin, StepDir},
+        generator::{SoftwareStepGenerator, StepGenerator},
+        rmt::RmtStepGenerator,
+        units::{Microsteps, Rpm, Steps},
+    },
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const RPM: Rpm = Rpm::new(320);
+const ACCELERATION: u32 = 4_000; // steps/s²
+const NUM_REVS: u32 = 16;
+const PAUSE_SEC: u64 = 2;
+
+// RMT channel clock: 80 MHz source / 80 -> 1 tick per microsecond
+const RMT_CLK_DIVIDER: u8 = 80;
+const RMT_TICKS_PER_US: u32 = 1;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const NUM_STEPS: Steps = STEPS_PER_REV.times(NUM_REVS);
+const MAX_VELOCITY: u32 = RPM.steps_per_sec(STEPS_PER_REV);
+const PROFILE: TrapezoidalProfile =
+    TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, NUM_STEPS.get());
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize higher priority executor for step generation
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let spawner = executor.start(Priority::Priority3);
+    spawner.must_spawn(step_manager(
+        peripherals.RMT,
+        peripherals.GPIO20.into(),
+        peripherals.GPIO21.into(),
+    ));
+}
+
+/// Task alternating rotations between the software and RMT step generators
+#[embassy_executor::task]
+async fn step_manager(
+    mut rmt: RMT<'static>,
+    dir_pin: AnyPin<'static>,
+    mut step_pin: AnyPin<'static>,
+) {
+    info!("profile duration (us): {}", PROFILE.duration_us());
+
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO, with the DIR pin shared by both generators
+    let mut dir = Output::new(dir_pin, Level::High, OutputConfig::default());
+    let mut software_generator = SoftwareStepGenerator::new();
+    let tx_config = TxChannelConfig::default()
+        .with_clk_divider(RMT_CLK_DIVIDER)
+        .with_idle_output_level(Level::Low)
+        .with_idle_output(true);
+
+    // Event loop
+    loop {
+        {
+            // software generator, toggling the STEP pin as a GPIO output
+            let step = Output::new(step_pin.reborrow(), Level::Low, OutputConfig::default());
+            let mut driver = StepDir::<Drv8825, _, _>::new(&mut dir, step)
+                .with_fixed_microsteps(MICRO_STEP_MODE.get())
+                .unwrap();
+            let start = Instant::now();
+            let steps = software_generator
+                .steps(&mut driver, PROFILE)
+                .await
+                .unwrap();
+            let elapsed = start.elapsed().as_micros();
+            info!("software: {} steps in {} us", steps, elapsed);
+        }
+        Timer::after_secs(PAUSE_SEC).await;
+
+        {
+            // RMT generator, with the channel driving the STEP pin until the rotation ends
+            let rmt = Rmt::new(rmt.reborrow(), Rate::from_mhz(80))
+                .unwrap()
+                .into_async();
+            let channel = rmt
+                .channel0
+                .configure(step_pin.reborrow(), tx_config)
+                .unwrap();
+            let mut rmt_generator = RmtStepGenerator::new(channel, RMT_TICKS_PER_US);
+            let mut driver = StepDir::<Drv8825, _, _>::new(&mut dir, NoPin)
+                .with_fixed_microsteps(MICRO_STEP_MODE.get())
+                .unwrap();
+            let start = Instant::now();
+            let steps = rmt_generator.steps(&mut driver, PROFILE).await.unwrap();
+            let elapsed = start.elapsed().as_micros();
+            info!("rmt: {} steps in {} us", steps, elapsed);
+        }
+        Timer::after_secs(PAUSE_SEC).await;
+    }
+}
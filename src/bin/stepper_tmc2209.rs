@@ -0,0 +1,117 @@
+//! Demo configuring a TMC2209 over UART while stepping it via ESP32C3
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO4: UART TX (TMC2209 PDN_UART, via 1kΩ resistor)
+//! - GPIO5: UART RX (TMC2209 PDN_UART)
+//! - GPIO20: stepper (TMC2209 DIR)
+//! - GPIO21: stepper (TMC2209 STEP)
+//!
+//! MS1 & MS2 are tied low (UART address 0). The microstep resolution is set over UART to
+//! MICRO_STEP_MODE, so the MS pins no longer select it.
+//!
+//! The motor alternates between forward and reverse rotations in StealthChop and SpreadCycle
+//! modes, logging the driver status and StallGuard reading after each.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::{error, info};
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use esp_hal::{
+    gpio::{Level, Output, OutputConfig},
+    timer::timg::TimerGroup,
+    uart::{self, Uart},
+};
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        controller::Stepper,
+        driver::{StepDir, Tmc2209},
+        generator::SoftwareStepGenerator,
+        tmc2209::{ChopperMode, Tmc2209Uart},
+        units::{Microsteps, Rpm, Steps},
+    },
+};
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(16).checked_for::<Tmc2209>();
+const RUN_CURRENT_MA: u32 = 800;
+const HOLD_CURRENT_MA: u32 = 300;
+const RPM: Rpm = Rpm::new(120);
+const ACCELERATION: u32 = 8_000; // steps/s²
+const NUM_REVS: u32 = 4;
+const PAUSE_SEC: u64 = 2;
+const BAUD_RATE: u32 = 115_200;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const NUM_STEPS: i32 = STEPS_PER_REV.times(NUM_REVS).as_i32();
+const MAX_VELOCITY: u32 = RPM.steps_per_sec(STEPS_PER_REV);
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    esp_hal_embassy::init(timg0.timer0);
+
+    // Initialize driver configuration interface
+    let uart_config = uart::Config::default().with_baudrate(BAUD_RATE);
+    let uart = Uart::new(peripherals.UART1, uart_config)
+        .unwrap()
+        .with_tx(peripherals.GPIO4)
+        .with_rx(peripherals.GPIO5)
+        .into_async();
+    let mut tmc = Tmc2209Uart::new(uart, 0);
+
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    match tmc.version().await {
+        Ok(version) => info!("TMC2209 version: {=u8:#x}", version),
+        Err(e) => {
+            error!("TMC2209 not responding: {}", e);
+            return;
+        }
+    }
+    tmc.init().await.unwrap();
+    tmc.set_current(RUN_CURRENT_MA, HOLD_CURRENT_MA)
+        .await
+        .unwrap();
+    tmc.set_microsteps(MICRO_STEP_MODE.get()).await.unwrap();
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let dir = Output::new(peripherals.GPIO20, Level::High, output_config);
+    let step = Output::new(peripherals.GPIO21, Level::Low, output_config);
+    let driver = StepDir::<Tmc2209, _>::new(dir, step)
+        .with_fixed_microsteps(tmc.microsteps())
+        .unwrap();
+    let mut stepper = Stepper::new(driver, SoftwareStepGenerator::new());
+
+    // Event loop
+    loop {
+        for mode in [ChopperMode::StealthChop, ChopperMode::SpreadCycle] {
+            tmc.set_chopper_mode(mode).await.unwrap();
+
+            for delta in [NUM_STEPS, -NUM_STEPS] {
+                stepper.move_by(delta).unwrap();
+                let profile =
+                    TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, stepper.remaining_steps());
+                stepper.run(profile).await.unwrap();
+
+                // StallGuard only measures while moving, so this is the last reading taken
+                let stallguard = tmc.stallguard_result().await.unwrap();
+                let status = tmc.drv_status().await.unwrap();
+                info!("{}: stallguard {}, {}", mode, stallguard, status);
+                Timer::after_secs(PAUSE_SEC).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! Demo of coordinated straight-line moves on two stepper axes via ESP32C3 & DRV8825
+//!
+//! Connections List (TODO: wiring schematic)
+//! - GPIO6: X axis stepper (DRV8825 DIR)
+//! - GPIO7: X axis stepper (DRV8825 STEP)
+//! - GPIO20: Y axis stepper (DRV8825 DIR)
+//! - GPIO21: Y axis stepper (DRV8825 STEP)
+//!
+//! Example is written assuming both DRV8825 boards are configured per MICRO_STEP_MODE value
+//! e.g. MICRO_STEP_MODE = Microsteps::new(8) -> 1/8th step mode
+//!
+//! The axes repeatedly trace a square with both diagonals. Each move follows a trapezoidal
+//! profile on the major (longest) axis, with the other axis interpolated along the line.
+
+#![no_std]
+#![no_main]
+#![feature(impl_trait_in_assoc_type)]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_time::Timer;
+use esp_hal::{
+    gpio::{AnyPin, Level, Output, OutputConfig},
+    interrupt::{Priority, software::SoftwareInterruptControl},
+    timer::{AnyTimer, timg::TimerGroup},
+};
+use esp_hal_embassy::InterruptExecutor;
+use esp_sandbox::{
+    motion::trapezoidal::TrapezoidalProfile,
+    stepper::{
+        driver::{Drv8825, StepDir},
+        multi_axis::MultiAxis,
+        units::{Microsteps, Steps},
+    },
+};
+use static_cell::StaticCell;
+use {defmt_rtt as _, esp_backtrace as _};
+
+// Inputs
+const MOTOR_STEPS_PER_REV: u32 = 200;
+const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+const MAX_VELOCITY: u32 = 1_500; // steps/s
+const ACCELERATION: u32 = 4_000; // steps/s²
+const PAUSE_MS: u64 = 500;
+
+// Calculated values (checked at compile time)
+const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+const SIDE: i32 = STEPS_PER_REV.times(2).as_i32();
+const PATH: [[i32; 2]; 6] = [
+    [SIDE, 0],
+    [SIDE, SIDE],
+    [0, 0],
+    [0, SIDE],
+    [SIDE, 0],
+    [0, 0],
+];
+
+#[esp_hal_embassy::main]
+async fn main(_spawner: Spawner) {
+    // Initialize hardware
+    let peripherals = esp_hal::init(esp_hal::Config::default());
+    let sw_ints = SoftwareInterruptControl::new(peripherals.SW_INTERRUPT);
+
+    // Initialize embassy
+    let timg0 = TimerGroup::new(peripherals.TIMG0);
+    let timg1 = TimerGroup::new(peripherals.TIMG1);
+    let timer0: AnyTimer = timg0.timer0.into();
+    let timer1: AnyTimer = timg1.timer0.into();
+    esp_hal_embassy::init([timer0, timer1]);
+
+    // Initialize higher priority executor for step generation (see stepper_async.rs)
+    static EXECUTOR: StaticCell<InterruptExecutor<2>> = StaticCell::new();
+    let executor = InterruptExecutor::new(sw_ints.software_interrupt2);
+    let executor = EXECUTOR.init(executor);
+    let spawner = executor.start(Priority::Priority3);
+    spawner.must_spawn(motion_manager([
+        (peripherals.GPIO6.into(), peripherals.GPIO7.into()),
+        (peripherals.GPIO20.into(), peripherals.GPIO21.into()),
+    ]));
+}
+
+/// Task tracing the path with both axes
+#[embassy_executor::task]
+async fn motion_manager(pins: [(AnyPin<'static>, AnyPin<'static>); 2]) {
+    // Initial delay to prevent initialization issues
+    Timer::after_millis(100).await;
+
+    // Initialize motor control GPIO
+    let output_config = OutputConfig::default();
+    let drivers = pins.map(|(dir_pin, step_pin)| {
+        let dir = Output::new(dir_pin, Level::High, output_config);
+        let step = Output::new(step_pin, Level::Low, output_config);
+        StepDir::<Drv8825, _>::new(dir, step)
+            .with_fixed_microsteps(MICRO_STEP_MODE.get())
+            .unwrap()
+    });
+    let mut axes = MultiAxis::new(drivers);
+
+    // Event loop
+    loop {
+        for target in PATH {
+            let steps = axes.move_to(target).unwrap();
+            let profile = TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, steps);
+            axes.run(profile).await.unwrap();
+            info!("position: {}", axes.positions());
+            Timer::after_millis(PAUSE_MS).await;
+        }
+    }
+}
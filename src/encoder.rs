@@ -0,0 +1,204 @@
+//! Quadrature encoder decoding from GPIO edges
+//!
+//! The ESP32C3 has no pulse counter (PCNT) peripheral, so encoder channels are decoded in
+//! software: [`QuadratureEncoder::run`] waits for an edge on either channel, then samples
+//! both and steps a [`QuadratureDecoder`]. Each edge counts once, giving four counts per
+//! encoder line. On chips with a PCNT peripheral, its count can be published through an
+//! [`EncoderCount`] in the same way.
+//!
+//! Single channel pulse inputs (e.g. a hall sensor tachometer) are counted by
+//! [`PulseCounter`], which publishes through an [`EncoderCount`] as well. Such inputs give no
+//! direction, so their count only ever increases.
+//!
+//! If both channels change between samples (the edge rate is too high for the task), the
+//! direction is unknown. The transition is not counted and is reported as an error instead.
+
+use core::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+
+use embassy_futures::select::select;
+use embedded_hal::digital::InputPin;
+use embedded_hal_async::digital::Wait;
+
+/// Count change for each transition, indexed by `previous << 2 | current`, where each state
+/// is `a << 1 | b`. Forward (A leading B) runs through the states 00, 10, 11, 01. `None`
+/// marks transitions where both channels changed.
+const TRANSITIONS: [Option<i8>; 16] = [
+    Some(0),
+    Some(-1),
+    Some(1),
+    None,
+    Some(1),
+    Some(0),
+    None,
+    Some(-1),
+    Some(-1),
+    None,
+    Some(0),
+    Some(1),
+    None,
+    Some(1),
+    Some(-1),
+    Some(0),
+];
+
+/// Errors raised while decoding an encoder.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum EncoderError {
+    /// Reading a channel input failed.
+    Pin,
+    /// Both channels changed between samples, so the direction of travel is unknown.
+    InvalidTransition,
+}
+
+/// State machine decoding quadrature channel samples into a signed count.
+///
+/// The count increases while channel A leads channel B. Swap the channels to reverse it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct QuadratureDecoder {
+    state: u8,
+    count: i32,
+}
+
+impl QuadratureDecoder {
+    /// Create a decoder at count zero, from the current channel levels.
+    pub const fn new(a: bool, b: bool) -> Self {
+        Self {
+            state: (a as u8) << 1 | b as u8,
+            count: 0,
+        }
+    }
+
+    /// Current count (four per encoder line).
+    pub const fn count(&self) -> i32 {
+        self.count
+    }
+
+    /// Decode a new sample of the channels, returning the change in count.
+    ///
+    /// On an invalid transition, the count is unchanged but the new state is adopted so that
+    /// decoding can continue.
+    pub fn update(&mut self, a: bool, b: bool) -> Result<i8, EncoderError> {
+        let state = (a as u8) << 1 | b as u8;
+        let transition = TRANSITIONS[usize::from(self.state << 2 | state)];
+        self.state = state;
+        let delta = transition.ok_or(EncoderError::InvalidTransition)?;
+        self.count = self.count.wrapping_add(delta.into());
+        Ok(delta)
+    }
+}
+
+/// Encoder count shared between the task decoding the encoder and its readers.
+///
+/// Only the decoding task writes the count, so plain atomic loads and stores suffice (the
+/// ESP32C3 has no atomic read-modify-write instructions).
+pub struct EncoderCount {
+    count: AtomicI32,
+    errors: AtomicU32,
+}
+
+impl EncoderCount {
+    /// Create a count at zero.
+    pub const fn new() -> Self {
+        Self {
+            count: AtomicI32::new(0),
+            errors: AtomicU32::new(0),
+        }
+    }
+
+    /// Current count.
+    pub fn get(&self) -> i32 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// Number of invalid transitions seen, each of which may have lost counts.
+    pub fn errors(&self) -> u32 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    /// Publish a new count. Must only be called by the task decoding the encoder.
+    pub fn set(&self, count: i32) {
+        self.count.store(count, Ordering::Relaxed);
+    }
+
+    /// Record an invalid transition. Must only be called by the task decoding the encoder.
+    pub fn record_error(&self) {
+        let errors = self.errors.load(Ordering::Relaxed);
+        self.errors.store(errors.wrapping_add(1), Ordering::Relaxed);
+    }
+}
+
+impl Default for EncoderCount {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quadrature encoder decoded from edges on its A and B channel inputs.
+pub struct QuadratureEncoder<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A: InputPin + Wait, B: InputPin + Wait> QuadratureEncoder<A, B> {
+    /// Create an encoder from its channel inputs.
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+
+    /// Decode the encoder indefinitely, publishing the count (relative to the count at the
+    /// time of the call) to `count`.
+    ///
+    /// Intended to run in its own task. Returns only if a channel input fails.
+    pub async fn run(&mut self, count: &EncoderCount) -> EncoderError {
+        let offset = count.get();
+        let mut decoder = match self.sample() {
+            Ok((a, b)) => QuadratureDecoder::new(a, b),
+            Err(e) => return e,
+        };
+        loop {
+            let _ = select(self.a.wait_for_any_edge(), self.b.wait_for_any_edge()).await;
+            let (a, b) = match self.sample() {
+                Ok(levels) => levels,
+                Err(e) => return e,
+            };
+            match decoder.update(a, b) {
+                Ok(0) => {}
+                Ok(_) => count.set(offset.wrapping_add(decoder.count())),
+                Err(_) => count.record_error(),
+            }
+        }
+    }
+
+    /// Read the level of both channels.
+    fn sample(&mut self) -> Result<(bool, bool), EncoderError> {
+        let a = self.a.is_high().map_err(|_| EncoderError::Pin)?;
+        let b = self.b.is_high().map_err(|_| EncoderError::Pin)?;
+        Ok((a, b))
+    }
+}
+
+/// Single channel pulse input, counted on rising edges.
+pub struct PulseCounter<P> {
+    input: P,
+}
+
+impl<P: Wait> PulseCounter<P> {
+    /// Create a counter from its input.
+    pub fn new(input: P) -> Self {
+        Self { input }
+    }
+
+    /// Count pulses indefinitely, adding each one to `count`.
+    ///
+    /// Intended to run in its own task. Returns only if the input fails.
+    pub async fn run(&mut self, count: &EncoderCount) -> EncoderError {
+        let mut pulses = count.get();
+        loop {
+            if self.input.wait_for_rising_edge().await.is_err() {
+                return EncoderError::Pin;
+            }
+            pulses = pulses.wrapping_add(1);
+            count.set(pulses);
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Interpreter for a small subset of G-code
+//!
+//! Supported commands:
+//! - `G0`/`G1`: rapid/linear move, with optional `F` feedrate (units/min, positive)
+//! - `G28`: home the listed axes (all axes if none are listed)
+//! - `G90`/`G91`: absolute/relative positioning
+//! - `M17`/`M18`: enable/disable motors
+//! - `M114`: report position
+//!
+//! Parsing is independent of any hardware, so it can be exercised on a host (see the unit tests
+//! and the `gcode_line` fuzz target in `sim/`).
+
+pub mod interpreter;
+pub mod parser;
+
+/// Maximum number of axes addressable by G-code words, in the order X, Y, Z, A.
+pub const MAX_AXES: usize = 4;
+
+/// Letters of the axis words, indexed by axis.
+pub const AXIS_LETTERS: [char; MAX_AXES] = ['X', 'Y', 'Z', 'A'];
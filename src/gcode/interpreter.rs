@@ -0,0 +1,277 @@
+//! Execution of parsed G-code commands on a set of stepper axes
+
+use core::fmt;
+
+use super::{AXIS_LETTERS, MAX_AXES, parser::Command};
+use crate::{
+    motion::{split, trapezoidal::TrapezoidalProfile},
+    stepper::{Error, driver::StepperDriver, multi_axis::MultiAxis},
+};
+
+/// Axes driven by the interpreter.
+#[allow(async_fn_in_trait)]
+pub trait Machine<const N: usize> {
+    /// Error raised by the machine.
+    type Error;
+
+    /// Current position of each axis (steps).
+    fn positions(&self) -> [i32; N];
+
+    /// Plan a straight-line move to the given positions, returning the major axis step count.
+    fn move_to(&mut self, targets: [i32; N]) -> Result<u32, Self::Error>;
+
+    /// Execute the planned move, taking one major axis step per period (µs).
+    async fn run(&mut self, periods: impl IntoIterator<Item = u32>) -> Result<u32, Self::Error>;
+
+    /// Enable or disable all motors.
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Self::Error>;
+
+    /// Home the flagged axes against their reference sensors.
+    ///
+    /// Returns `false` if the machine has no reference sensors, in which case the interpreter
+    /// returns the flagged axes to position zero instead.
+    async fn home(&mut self, axes: [bool; N]) -> Result<bool, Self::Error> {
+        let _ = axes;
+        Ok(false)
+    }
+}
+
+impl<D: StepperDriver, const N: usize> Machine<N> for MultiAxis<D, N> {
+    type Error = Error;
+
+    fn positions(&self) -> [i32; N] {
+        MultiAxis::positions(self)
+    }
+
+    fn move_to(&mut self, targets: [i32; N]) -> Result<u32, Error> {
+        MultiAxis::move_to(self, targets)
+    }
+
+    async fn run(&mut self, periods: impl IntoIterator<Item = u32>) -> Result<u32, Error> {
+        MultiAxis::run(self, periods).await
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        MultiAxis::set_enabled(self, enabled)
+    }
+}
+
+/// Machine specific parameters used to convert G-code into steps.
+#[derive(Clone, Copy, Debug, defmt::Format)]
+pub struct InterpreterConfig<const N: usize> {
+    /// Steps per G-code unit (e.g. per mm) for each axis.
+    pub steps_per_unit: [f32; N],
+    /// Velocity of the major axis for rapid moves, and the limit for linear moves (steps/s).
+    pub max_velocity: u32,
+    /// Acceleration of the major axis (steps/s²).
+    pub acceleration: u32,
+    /// Feedrate used by linear moves until an `F` word is received (units/min).
+    pub default_feedrate: f32,
+}
+
+/// Errors raised while executing a command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ExecuteError<E> {
+    /// The command has a word for an axis beyond those of the machine.
+    UnsupportedAxis(char),
+    /// The machine raised an error.
+    Machine(E),
+}
+
+impl<E> From<E> for ExecuteError<E> {
+    fn from(error: E) -> Self {
+        Self::Machine(error)
+    }
+}
+
+/// Response to an executed command, formatted for the host as `ok` or `X:.. Y:.. ok`.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub enum Response<const N: usize> {
+    /// The command completed.
+    Ok,
+    /// Position of each axis (units).
+    Position([f32; N]),
+}
+
+impl<const N: usize> fmt::Display for Response<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Self::Position(position) = self {
+            for (letter, value) in AXIS_LETTERS.iter().zip(position) {
+                write!(f, "{letter}:{value:.3} ")?;
+            }
+        }
+        write!(f, "ok")
+    }
+}
+
+/// Modal G-code state for a machine with `N` axes (at most [`MAX_AXES`]).
+pub struct Interpreter<const N: usize> {
+    config: InterpreterConfig<N>,
+    absolute: bool,
+    feedrate: f32,
+    /// Commanded position minus the step target of each axis, in the range ±0.5 (steps), so
+    /// that rounding errors don't accumulate over relative moves.
+    residual: [f32; N],
+}
+
+impl<const N: usize> Interpreter<N> {
+    /// Create an interpreter in absolute positioning mode.
+    pub fn new(config: InterpreterConfig<N>) -> Self {
+        const { assert!(N <= MAX_AXES, "too many axes for G-code") };
+        Self {
+            config,
+            absolute: true,
+            feedrate: config.default_feedrate,
+            residual: [0.0; N],
+        }
+    }
+
+    /// Execute a command, waiting for any motion to complete.
+    pub async fn execute<M: Machine<N>>(
+        &mut self,
+        machine: &mut M,
+        command: Command,
+    ) -> Result<Response<N>, ExecuteError<M::Error>> {
+        match command {
+            Command::Move {
+                rapid,
+                target,
+                feedrate,
+            } => {
+                if let Some(feedrate) = feedrate {
+                    self.feedrate = feedrate;
+                }
+                let targets = self.target_steps(machine.positions(), &target)?;
+                let velocity = if rapid {
+                    self.config.max_velocity
+                } else {
+                    self.major_velocity(machine.positions(), targets)
+                };
+                self.move_to(machine, targets, velocity).await?;
+            }
+            Command::Home { axes } => {
+                if let Some(axis) = axes[N..].iter().position(|flagged| *flagged) {
+                    return Err(ExecuteError::UnsupportedAxis(AXIS_LETTERS[N + axis]));
+                }
+                let mut flagged = [false; N];
+                flagged.copy_from_slice(&axes[..N]);
+                if !flagged.contains(&true) {
+                    flagged = [true; N];
+                }
+                for (residual, flagged) in self.residual.iter_mut().zip(flagged) {
+                    if flagged {
+                        *residual = 0.0;
+                    }
+                }
+                if !machine.home(flagged).await? {
+                    let mut targets = machine.positions();
+                    for (target, flagged) in targets.iter_mut().zip(flagged) {
+                        if flagged {
+                            *target = 0;
+                        }
+                    }
+                    self.move_to(machine, targets, self.config.max_velocity)
+                        .await?;
+                }
+            }
+            Command::AbsolutePositioning => self.absolute = true,
+            Command::RelativePositioning => self.absolute = false,
+            Command::EnableMotors => machine.set_enabled(true)?,
+            Command::DisableMotors => machine.set_enabled(false)?,
+            Command::ReportPosition => {
+                let mut position = [0.0; N];
+                for ((position, steps), scale) in position
+                    .iter_mut()
+                    .zip(machine.positions())
+                    .zip(self.config.steps_per_unit)
+                {
+                    *position = steps as f32 / scale;
+                }
+                return Ok(Response::Position(position));
+            }
+        }
+        Ok(Response::Ok)
+    }
+
+    /// Move to the target with a trapezoidal profile on the major axis.
+    async fn move_to<M: Machine<N>>(
+        &self,
+        machine: &mut M,
+        targets: [i32; N],
+        velocity: u32,
+    ) -> Result<(), M::Error> {
+        let steps = machine.move_to(targets)?;
+        let profile = TrapezoidalProfile::new(velocity.max(1), self.config.acceleration, steps);
+        machine.run(profile).await?;
+        Ok(())
+    }
+
+    /// Target of a move in steps, applying the positioning mode to the given words.
+    ///
+    /// Only the whole steps of each target are moved to, with the remaining fraction carried
+    /// over to the next relative move.
+    fn target_steps<E>(
+        &mut self,
+        positions: [i32; N],
+        words: &[Option<f32>; MAX_AXES],
+    ) -> Result<[i32; N], ExecuteError<E>> {
+        if let Some(axis) = words[N..].iter().position(Option::is_some) {
+            return Err(ExecuteError::UnsupportedAxis(AXIS_LETTERS[N + axis]));
+        }
+        let mut targets = positions;
+        for (((target, residual), word), scale) in targets
+            .iter_mut()
+            .zip(&mut self.residual)
+            .zip(words)
+            .zip(self.config.steps_per_unit)
+        {
+            if let Some(value) = word {
+                if self.absolute {
+                    (*target, *residual) = split(value * scale);
+                } else {
+                    // only the change is converted, so large positions don't cost precision
+                    let (delta, remainder) = split(*residual + value * scale);
+                    *target = target.saturating_add(delta);
+                    *residual = remainder;
+                }
+            }
+        }
+        Ok(targets)
+    }
+
+    /// Major axis velocity which moves the tool along the path at the current feedrate.
+    fn major_velocity(&self, positions: [i32; N], targets: [i32; N]) -> u32 {
+        let mut path_sq = 0.0;
+        let mut major_steps = 0;
+        for ((position, target), scale) in positions
+            .iter()
+            .zip(targets)
+            .zip(self.config.steps_per_unit)
+        {
+            let steps = target.abs_diff(*position);
+            let units = steps as f32 / scale;
+            path_sq += units * units;
+            major_steps = major_steps.max(steps);
+        }
+        if path_sq == 0.0 {
+            return self.config.max_velocity;
+        }
+
+        // velocity (steps/s) = major steps / move duration, where duration = path / feedrate
+        let velocity = major_steps as f32 * (self.feedrate / 60.0) / sqrt(path_sq);
+        (velocity as u32).clamp(1, self.config.max_velocity)
+    }
+}
+
+/// Square root of a non-negative value, via Newton's method (`f32::sqrt` needs `std`).
+fn sqrt(value: f32) -> f32 {
+    if value <= 0.0 {
+        return 0.0;
+    }
+    // halving the exponent gives a starting point within a factor of two
+    let mut estimate = f32::from_bits((value.to_bits() >> 1) + 0x1fc0_0000);
+    for _ in 0..4 {
+        estimate = 0.5 * (estimate + value / estimate);
+    }
+    estimate
+}
@@ -0,0 +1,230 @@
+//! Line parser for the supported G-code subset
+//!
+//! Line numbers (`N`) and checksums (`*`) are accepted and ignored, as are comments in
+//! parentheses or following a semicolon.
+
+use super::{AXIS_LETTERS, MAX_AXES};
+
+/// A parsed G-code command.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub enum Command {
+    /// `G0` (rapid) or `G1` (linear) move. Axes without a word keep their position.
+    Move {
+        rapid: bool,
+        target: [Option<f32>; MAX_AXES],
+        feedrate: Option<f32>,
+    },
+    /// `G28`: home the listed axes, or every axis of the machine if none are listed.
+    Home { axes: [bool; MAX_AXES] },
+    /// `G90`: interpret coordinates as absolute positions.
+    AbsolutePositioning,
+    /// `G91`: interpret coordinates as offsets from the current position.
+    RelativePositioning,
+    /// `M17`: enable all motors.
+    EnableMotors,
+    /// `M18`: disable all motors.
+    DisableMotors,
+    /// `M114`: report the current position.
+    ReportPosition,
+}
+
+/// Errors raised while parsing a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ParseError {
+    /// A character which does not start a word was found.
+    UnexpectedCharacter(char),
+    /// A word letter was not followed by a valid number.
+    InvalidNumber(char),
+    /// The command is not part of the supported subset.
+    UnsupportedCommand(char, u16),
+    /// The line contains parameters but no command.
+    MissingCommand,
+    /// The line contains more than one command.
+    MultipleCommands,
+    /// A word is not valid for the line's command.
+    UnexpectedWord(char),
+    /// A comment in parentheses was not closed.
+    UnterminatedComment,
+    /// A feedrate was zero or negative.
+    InvalidFeedrate,
+}
+
+/// Parse a single line, returning `None` for lines without a command (e.g. only a comment).
+pub fn parse_line(line: &str) -> Result<Option<Command>, ParseError> {
+    let mut command = None;
+    let mut axes = [None; MAX_AXES];
+    let mut bare_axis = None;
+    let mut feedrate = None;
+
+    let mut rest = line;
+    while let Some(c) = rest.chars().next() {
+        rest = &rest[c.len_utf8()..];
+        match c {
+            c if c.is_ascii_whitespace() => {}
+            ';' | '*' => break,
+            '(' => {
+                let end = rest.find(')').ok_or(ParseError::UnterminatedComment)?;
+                rest = &rest[end + 1..];
+            }
+            c if c.is_ascii_alphabetic() => {
+                let letter = c.to_ascii_uppercase();
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_digit() || matches!(c, '.' | '-' | '+')))
+                    .unwrap_or(rest.len());
+                let axis = AXIS_LETTERS.iter().position(|axis| *axis == letter);
+
+                // axis words may omit their value (e.g. `G28 X`), which is only valid for homing
+                let value: f32 = match (&rest[..len], axis) {
+                    ("", Some(_)) => {
+                        bare_axis = Some(letter);
+                        0.0
+                    }
+                    (number, _) => number
+                        .parse()
+                        .map_err(|_| ParseError::InvalidNumber(letter))?,
+                };
+                rest = &rest[len..];
+
+                match letter {
+                    'G' | 'M' => {
+                        if command.is_some() {
+                            return Err(ParseError::MultipleCommands);
+                        }
+                        command = Some((letter, code(letter, value)?));
+                    }
+                    'F' => feedrate = Some(value),
+                    'N' => {}
+                    _ => match axis {
+                        Some(axis) => axes[axis] = Some(value),
+                        None => return Err(ParseError::UnexpectedWord(letter)),
+                    },
+                }
+            }
+            c => return Err(ParseError::UnexpectedCharacter(c)),
+        }
+    }
+
+    let Some((letter, code)) = command else {
+        return if axes.iter().any(Option::is_some) || feedrate.is_some() {
+            Err(ParseError::MissingCommand)
+        } else {
+            Ok(None)
+        };
+    };
+
+    let command = match (letter, code) {
+        ('G', 0 | 1) => Command::Move {
+            rapid: code == 0,
+            target: axes,
+            feedrate,
+        },
+        ('G', 28) => Command::Home {
+            axes: axes.map(|axis| axis.is_some()),
+        },
+        ('G', 90) => Command::AbsolutePositioning,
+        ('G', 91) => Command::RelativePositioning,
+        ('M', 17) => Command::EnableMotors,
+        ('M', 18 | 84) => Command::DisableMotors,
+        ('M', 114) => Command::ReportPosition,
+        (letter, code) => return Err(ParseError::UnsupportedCommand(letter, code)),
+    };
+
+    // only moves take a feedrate, and only moves and homing take axis words
+    if feedrate.is_some() && !matches!(command, Command::Move { .. }) {
+        return Err(ParseError::UnexpectedWord('F'));
+    }
+    if let Some(axis) = axes.iter().position(Option::is_some)
+        && !matches!(command, Command::Move { .. } | Command::Home { .. })
+    {
+        return Err(ParseError::UnexpectedWord(AXIS_LETTERS[axis]));
+    }
+    if feedrate.is_some_and(|feedrate| feedrate <= 0.0) {
+        return Err(ParseError::InvalidFeedrate);
+    }
+    if let Some(letter) = bare_axis
+        && !matches!(command, Command::Home { .. })
+    {
+        return Err(ParseError::InvalidNumber(letter));
+    }
+    Ok(Some(command))
+}
+
+/// Convert the value of a command word into its (integer) code.
+fn code(letter: char, value: f32) -> Result<u16, ParseError> {
+    let code = value as u16;
+    if code as f32 == value {
+        Ok(code)
+    } else {
+        Err(ParseError::InvalidNumber(letter))
+    }
+}
+
+/// Accumulates received bytes into lines.
+pub struct LineBuffer<const LEN: usize> {
+    buffer: [u8; LEN],
+    len: usize,
+    overflowed: bool,
+    complete: bool,
+}
+
+/// Errors raised when a received line cannot be returned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum LineError {
+    /// The line did not fit in the buffer, and was discarded.
+    TooLong,
+    /// The line was not valid UTF-8.
+    InvalidUtf8,
+}
+
+impl<const LEN: usize> LineBuffer<LEN> {
+    /// Create an empty buffer.
+    pub const fn new() -> Self {
+        Self {
+            buffer: [0; LEN],
+            len: 0,
+            overflowed: false,
+            complete: false,
+        }
+    }
+
+    /// Add a received byte, returning the line once a line ending is received.
+    ///
+    /// Empty lines (including the second byte of a `\r\n` ending) are skipped.
+    pub fn push(&mut self, byte: u8) -> Option<Result<&str, LineError>> {
+        if self.complete {
+            self.len = 0;
+            self.overflowed = false;
+            self.complete = false;
+        }
+
+        match byte {
+            b'\n' | b'\r' if self.len == 0 && !self.overflowed => None,
+            b'\n' | b'\r' => {
+                self.complete = true;
+                if self.overflowed {
+                    return Some(Err(LineError::TooLong));
+                }
+                Some(
+                    core::str::from_utf8(&self.buffer[..self.len])
+                        .map_err(|_| LineError::InvalidUtf8),
+                )
+            }
+            byte => {
+                match self.buffer.get_mut(self.len) {
+                    Some(slot) => {
+                        *slot = byte;
+                        self.len += 1;
+                    }
+                    None => self.overflowed = true,
+                }
+                None
+            }
+        }
+    }
+}
+
+impl<const LEN: usize> Default for LineBuffer<LEN> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
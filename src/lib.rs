@@ -0,0 +1,13 @@
+//! Shared building blocks for the experiments in `src/bin`
+//!
+//! Logic in this crate is kept independent of esp-hal wherever possible, so that it can be
+//! compiled and exercised on a host machine as well as on the ESP32C3.
+
+#![no_std]
+
+pub mod encoder;
+pub mod gcode;
+pub mod motion;
+pub mod motor;
+pub mod stepper;
+pub mod timing;
@@ -0,0 +1,30 @@
+//! Motion profiles for step/dir stepper drivers
+//!
+//! Profiles yield the period of each step in microseconds, which the step generating task
+//! uses to schedule STEP pin transitions. Velocities are expressed in steps/s and
+//! accelerations in steps/s².
+
+pub mod interpolation;
+pub mod scurve;
+pub mod trapezoidal;
+
+/// Number of microseconds per second.
+pub(crate) const US_PER_SEC: u64 = 1_000_000;
+
+/// Number of microseconds per minute.
+pub(crate) const US_PER_MIN: u64 = 60 * US_PER_SEC;
+
+/// Round to the nearest integer, away from zero on ties (saturating at the limits of `i32`).
+pub(crate) const fn round(value: f32) -> i32 {
+    if value >= 0.0 {
+        (value + 0.5) as i32
+    } else {
+        (value - 0.5) as i32
+    }
+}
+
+/// Split a fractional number of steps into the nearest whole number and the remainder.
+pub(crate) fn split(steps: f32) -> (i32, f32) {
+    let whole = round(steps);
+    (whole, steps - whole as f32)
+}
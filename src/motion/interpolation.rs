@@ -0,0 +1,66 @@
+//! Bresenham-style linear interpolation between several axes
+//!
+//! The axis with the most steps (the major axis) steps on every tick, while the other axes
+//! step on evenly distributed ticks so that the combined move follows a straight line.
+
+/// Iterator over the ticks of a straight-line move, yielding which axes step on each tick.
+///
+/// Every axis takes exactly as many steps as the magnitude of its delta.
+#[derive(Clone, Debug)]
+pub struct LinearInterpolator<const N: usize> {
+    steps: [u32; N],
+    errors: [u32; N],
+    major_steps: u32,
+    tick: u32,
+}
+
+impl<const N: usize> LinearInterpolator<N> {
+    /// Create an interpolator for a move by the given per-axis deltas (steps).
+    ///
+    /// Only the magnitudes of the deltas are used; directions are set by the caller.
+    pub fn new(deltas: [i32; N]) -> Self {
+        let steps = deltas.map(i32::unsigned_abs);
+        let major_steps = steps.iter().copied().max().unwrap_or(0);
+        Self {
+            steps,
+            // start half way through the first interval so steps are centred within it
+            errors: [major_steps / 2; N],
+            major_steps,
+            tick: 0,
+        }
+    }
+
+    /// Number of ticks in the move, equal to the step count of the major axis.
+    pub fn major_steps(&self) -> u32 {
+        self.major_steps
+    }
+}
+
+impl<const N: usize> Iterator for LinearInterpolator<N> {
+    type Item = [bool; N];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.tick >= self.major_steps {
+            return None;
+        }
+        self.tick += 1;
+
+        let mut stepping = [false; N];
+        for ((error, steps), stepping) in self.errors.iter_mut().zip(self.steps).zip(&mut stepping)
+        {
+            *error += steps;
+            if *error >= self.major_steps {
+                *error -= self.major_steps;
+                *stepping = true;
+            }
+        }
+        Some(stepping)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.major_steps - self.tick) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<const N: usize> ExactSizeIterator for LinearInterpolator<N> {}
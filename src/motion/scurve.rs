@@ -0,0 +1,322 @@
+//! Jerk-limited (S-curve) motion profile
+//!
+//! Step periods are generated incrementally by integrating acceleration and velocity over each
+//! step, using Q16 fixed point arithmetic only. This keeps the per-step cost low enough for the
+//! high-priority interrupt executor on targets without an FPU.
+
+use super::US_PER_SEC;
+
+/// Number of fractional bits used by fixed point kinematic quantities.
+const FRAC_BITS: u32 = 16;
+
+/// Phases of a rest-to-rest move. The cruise phase is measured in steps rather than time.
+const PHASE_CRUISE: usize = 3;
+const PHASE_DONE: usize = 7;
+
+/// Direction of jerk during each phase.
+const JERK_SIGNS: [i64; 7] = [1, 0, -1, 0, -1, 0, 1];
+
+/// Jerk-limited profile with seven phases: jerk up, constant acceleration, jerk down, cruise,
+/// then the mirror image of the acceleration phases to decelerate.
+///
+/// Velocity is in steps/s, acceleration in steps/s² and jerk in steps/s³. When the move is
+/// too short to reach `max_velocity` (or `max_acceleration`), the peak values are reduced so
+/// that the acceleration and deceleration ramps still fit within the move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct SCurveProfile {
+    steps: u32,
+    jerk: u32,
+    peak_velocity: u32,
+    peak_acceleration: u32,
+    jerk_time_us: u32,
+    accel_time_us: u32,
+}
+
+impl SCurveProfile {
+    /// Create a profile for a move of `steps` steps.
+    ///
+    /// All limits must be non-zero.
+    pub const fn new(max_velocity: u32, max_acceleration: u32, jerk: u32, steps: u32) -> Self {
+        assert!(max_velocity > 0, "max velocity must be non-zero");
+        assert!(max_acceleration > 0, "max acceleration must be non-zero");
+        assert!(jerk > 0, "jerk must be non-zero");
+
+        // find the highest peak velocity whose ramps fit within the move, which is no lower
+        // than the velocity reached by the first step (taken under the jerk alone)
+        let first_period_us = jerk_step_period_us(jerk) as u64;
+        let first_velocity =
+            jerk as u64 * first_period_us * first_period_us / (2 * US_PER_SEC * US_PER_SEC);
+        let mut low = if first_velocity < max_velocity as u64 {
+            first_velocity as u32
+        } else {
+            max_velocity
+        };
+        let mut high = max_velocity;
+        if ramp_steps(max_velocity, max_acceleration, jerk) * 2 > steps as u64 {
+            while low < high {
+                let mid = low + (high - low).div_ceil(2);
+                if ramp_steps(mid, max_acceleration, jerk) * 2 <= steps as u64 {
+                    low = mid;
+                } else {
+                    high = mid - 1;
+                }
+            }
+        } else {
+            low = max_velocity;
+        }
+
+        let peak_velocity = low;
+        let (peak_acceleration, jerk_time_us, accel_time_us) =
+            ramp_timing(peak_velocity, max_acceleration, jerk);
+        Self {
+            steps,
+            jerk,
+            peak_velocity,
+            peak_acceleration,
+            jerk_time_us,
+            accel_time_us,
+        }
+    }
+
+    /// Total number of steps in the move.
+    pub const fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Highest velocity reached during the move (steps/s).
+    pub const fn peak_velocity(&self) -> u32 {
+        self.peak_velocity
+    }
+
+    /// Highest acceleration reached during the move (steps/s²).
+    pub const fn peak_acceleration(&self) -> u32 {
+        self.peak_acceleration
+    }
+
+    /// Iterator over the period of every step in the move (µs).
+    pub const fn delays(&self) -> SCurveDelays {
+        SCurveDelays {
+            profile: *self,
+            state: Kinematics {
+                phase: 0,
+                phase_elapsed_us: 0,
+                acceleration: 0,
+                velocity: 0,
+            },
+            index: 0,
+            accel_steps: 0,
+            min_velocity: 0,
+        }
+    }
+
+    /// Total duration of the move (µs).
+    pub fn duration_us(&self) -> u64 {
+        self.delays().map(u64::from).sum()
+    }
+
+    /// Duration of the given phase (µs).
+    const fn phase_duration_us(&self, phase: usize) -> u32 {
+        match phase {
+            0 | 2 | 4 | 6 => self.jerk_time_us,
+            1 | 5 => self.accel_time_us,
+            _ => 0,
+        }
+    }
+}
+
+impl IntoIterator for SCurveProfile {
+    type Item = u32;
+    type IntoIter = SCurveDelays;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.delays()
+    }
+}
+
+/// Fixed point kinematic state within a profile.
+#[derive(Clone, Copy, Debug)]
+struct Kinematics {
+    phase: usize,
+    phase_elapsed_us: u32,
+    /// Acceleration (Q16 steps/s²).
+    acceleration: i64,
+    /// Velocity (Q16 steps/s).
+    velocity: i64,
+}
+
+/// Iterator over the step periods of an [`SCurveProfile`] (µs).
+#[derive(Clone, Debug)]
+pub struct SCurveDelays {
+    profile: SCurveProfile,
+    state: Kinematics,
+    index: u32,
+    /// Steps taken during the acceleration phases, mirrored by the deceleration phases.
+    accel_steps: u32,
+    /// Velocity after the first step (Q16 steps/s), used as a floor while decelerating.
+    min_velocity: i64,
+}
+
+impl SCurveDelays {
+    /// Advance the kinematic state by `dt_us`, crossing phase boundaries as required.
+    fn advance(&self, mut state: Kinematics, mut dt_us: u32) -> Kinematics {
+        let profile = &self.profile;
+        let jerk = (profile.jerk as i64) << FRAC_BITS;
+        while dt_us > 0 && state.phase != PHASE_CRUISE && state.phase != PHASE_DONE {
+            let remaining_us = profile.phase_duration_us(state.phase) - state.phase_elapsed_us;
+            let dt = dt_us.min(remaining_us);
+
+            // v += a·t + j·t²/2, a += j·t
+            let delta_accel = JERK_SIGNS[state.phase] * jerk * dt as i64 / US_PER_SEC as i64;
+            state.velocity +=
+                (state.acceleration * dt as i64 + delta_accel * dt as i64 / 2) / US_PER_SEC as i64;
+            state.acceleration += delta_accel;
+            state.phase_elapsed_us += dt;
+            dt_us -= dt;
+
+            if state.phase_elapsed_us == profile.phase_duration_us(state.phase) {
+                state = self.finish_phase(state);
+            }
+        }
+        state
+    }
+
+    /// Move to the next phase, snapping to its ideal starting state to remove rounding drift.
+    fn finish_phase(&self, mut state: Kinematics) -> Kinematics {
+        let peak_accel = (self.profile.peak_acceleration as i64) << FRAC_BITS;
+        let peak_velocity = (self.profile.peak_velocity as i64) << FRAC_BITS;
+        state.phase += 1;
+        state.phase_elapsed_us = 0;
+        match state.phase {
+            1 => state.acceleration = peak_accel,
+            PHASE_CRUISE => {
+                state.acceleration = 0;
+                state.velocity = peak_velocity;
+            }
+            5 => state.acceleration = -peak_accel,
+            PHASE_DONE => state.acceleration = 0,
+            _ => {}
+        }
+        state
+    }
+
+    /// Period of a step starting from the current state (µs).
+    fn next_period_us(&mut self) -> u32 {
+        // starting from rest, the first step is covered by the jerk alone
+        let jerk_step_us = jerk_step_period_us(self.profile.jerk);
+        if self.state.velocity <= 0 && self.index == 0 {
+            self.state = self.advance(self.state, jerk_step_us);
+            self.min_velocity = self.state.velocity.max(1);
+            return jerk_step_us;
+        }
+
+        // and coming to rest, the last step mirrors it (rather than running at the floor)
+        if self.index > 0 && self.index + 1 == self.profile.steps {
+            self.state = self.advance(self.state, jerk_step_us);
+            return jerk_step_us;
+        }
+
+        // predictor-corrector: estimate the period from the current velocity, then use the
+        // mean of the current and predicted velocities over that period
+        let velocity = self.state.velocity.max(self.min_velocity);
+        let predicted_us = period_us(velocity);
+        let predicted = self.advance(self.state, predicted_us);
+        let mean_velocity = ((velocity + predicted.velocity.max(self.min_velocity)) / 2).max(1);
+        let period_us = period_us(mean_velocity);
+        self.state = self.advance(self.state, period_us);
+        period_us
+    }
+}
+
+impl Iterator for SCurveDelays {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let steps = self.profile.steps;
+        if self.index >= steps {
+            return None;
+        }
+
+        let period_us = if self.state.phase == PHASE_CRUISE {
+            // start decelerating once the remaining steps mirror the acceleration phases
+            if steps - self.index <= self.accel_steps {
+                self.state.phase += 1;
+                self.next_period_us()
+            } else {
+                period_us(self.state.velocity)
+            }
+        } else {
+            let in_accel = self.state.phase < PHASE_CRUISE;
+            let period_us = self.next_period_us();
+            if in_accel {
+                self.accel_steps += 1;
+            }
+            period_us
+        };
+
+        self.index += 1;
+        Some(period_us)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.profile.steps - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for SCurveDelays {}
+
+/// Period of one step at the given Q16 velocity (µs).
+fn period_us(velocity: i64) -> u32 {
+    (((US_PER_SEC as i64) << FRAC_BITS) / velocity.max(1)) as u32
+}
+
+/// Peak acceleration, jerk phase duration (µs) and constant acceleration duration (µs) of a
+/// ramp from rest to `velocity`.
+const fn ramp_timing(velocity: u32, max_acceleration: u32, jerk: u32) -> (u32, u32, u32) {
+    let (velocity, max_acceleration, jerk) =
+        (velocity as u64, max_acceleration as u64, jerk as u64);
+    if velocity * jerk < max_acceleration * max_acceleration {
+        // max acceleration is never reached, so the ramp is two jerk phases only
+        let peak_acceleration = (velocity * jerk).isqrt();
+        let jerk_time_us = peak_acceleration * US_PER_SEC / jerk;
+        (peak_acceleration as u32, jerk_time_us as u32, 0)
+    } else {
+        let jerk_time_us = max_acceleration * US_PER_SEC / jerk;
+        let accel_time_us = velocity * US_PER_SEC / max_acceleration - jerk_time_us;
+        (
+            max_acceleration as u32,
+            jerk_time_us as u32,
+            accel_time_us as u32,
+        )
+    }
+}
+
+/// Number of steps taken by a ramp from rest to `velocity`, including the step in which the
+/// ramp ends.
+///
+/// The ramp is symmetric about its midpoint, so its mean velocity is `velocity / 2`.
+const fn ramp_steps(velocity: u32, max_acceleration: u32, jerk: u32) -> u64 {
+    let (_, jerk_time_us, accel_time_us) = ramp_timing(velocity, max_acceleration, jerk);
+    let ramp_time_us = 2 * jerk_time_us as u64 + accel_time_us as u64;
+    (velocity as u64 * ramp_time_us).div_ceil(2 * US_PER_SEC) + 1
+}
+
+/// Period of a step from rest under the jerk alone (µs), i.e. solving 1 = j·t³/6.
+const fn jerk_step_period_us(jerk: u32) -> u32 {
+    icbrt(6 * US_PER_SEC * US_PER_SEC * US_PER_SEC / jerk as u64) as u32
+}
+
+/// Integer cube root, rounded down.
+const fn icbrt(value: u64) -> u64 {
+    let mut low: u64 = 0;
+    let mut high = 2_642_245; // cbrt(u64::MAX), rounded down
+    while low < high {
+        let mid = (low + high).div_ceil(2);
+        if mid * mid * mid <= value {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    low
+}
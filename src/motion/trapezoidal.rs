@@ -0,0 +1,158 @@
+//! Trapezoidal (constant acceleration) motion profile
+
+use super::US_PER_SEC;
+
+/// Constant acceleration profile with accelerate, cruise and decelerate phases.
+///
+/// Moves which are too short to reach `max_velocity` produce a triangular profile, with the
+/// deceleration phase starting as soon as half of the steps have been taken.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct TrapezoidalProfile {
+    acceleration: u32,
+    steps: u32,
+    accel_steps: u32,
+    cruise_period_us: u32,
+}
+
+impl TrapezoidalProfile {
+    /// Create a profile for a move of `steps` steps.
+    ///
+    /// `max_velocity` is in steps/s and `acceleration` in steps/s². Both must be non-zero.
+    pub const fn new(max_velocity: u32, acceleration: u32, steps: u32) -> Self {
+        assert!(max_velocity > 0, "max velocity must be non-zero");
+        assert!(acceleration > 0, "acceleration must be non-zero");
+
+        // steps required to reach max velocity: v² / 2a
+        let full_accel_steps =
+            (max_velocity as u64 * max_velocity as u64 / (2 * acceleration as u64)) as u32;
+        let accel_steps = if full_accel_steps < steps / 2 {
+            full_accel_steps
+        } else {
+            steps / 2
+        };
+        let cruise_period_us = US_PER_SEC.div_ceil(max_velocity as u64) as u32;
+
+        let mut profile = Self {
+            acceleration,
+            steps,
+            accel_steps,
+            cruise_period_us,
+        };
+
+        // a triangular profile peaks below max velocity, so the "cruise" step(s) at the apex
+        // must run at the period reached at the end of the acceleration phase
+        let apex_period_us = profile.ramp_period_us(accel_steps);
+        if apex_period_us > cruise_period_us {
+            profile.cruise_period_us = apex_period_us;
+        }
+        profile
+    }
+
+    /// Total number of steps in the move.
+    pub const fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Number of steps in the acceleration phase (equal to the deceleration phase).
+    pub const fn accel_steps(&self) -> u32 {
+        self.accel_steps
+    }
+
+    /// Number of steps taken at constant velocity.
+    pub const fn cruise_steps(&self) -> u32 {
+        self.steps - 2 * self.accel_steps
+    }
+
+    /// Step period at cruising velocity (µs).
+    pub const fn cruise_period_us(&self) -> u32 {
+        self.cruise_period_us
+    }
+
+    /// Period of the step with the given index (µs), or `None` if out of range.
+    pub const fn period_us(&self, index: u32) -> Option<u32> {
+        if index >= self.steps {
+            return None;
+        }
+        let decel_start = self.steps - self.accel_steps;
+        let period = if index < self.accel_steps {
+            self.ramp_period_us(index)
+        } else if index >= decel_start {
+            self.ramp_period_us(self.steps - 1 - index)
+        } else {
+            self.cruise_period_us
+        };
+        Some(period)
+    }
+
+    /// Iterator over the period of every step in the move (µs).
+    pub const fn delays(&self) -> StepDelays {
+        StepDelays {
+            profile: *self,
+            index: 0,
+        }
+    }
+
+    /// Total duration of the move (µs).
+    pub const fn duration_us(&self) -> u64 {
+        // the deceleration phase mirrors the acceleration phase
+        let mut ramp_us = 0;
+        let mut index = 0;
+        while index < self.accel_steps {
+            ramp_us += self.ramp_period_us(index) as u64;
+            index += 1;
+        }
+        2 * ramp_us + self.cruise_steps() as u64 * self.cruise_period_us as u64
+    }
+
+    /// Period of the `index`th step of a ramp starting from rest (µs).
+    ///
+    /// Uses the exact time between steps, t(n + 1) - t(n) where t(n) = sqrt(2n / a), rather
+    /// than an incremental approximation, so each period can be computed independently.
+    const fn ramp_period_us(&self, index: u32) -> u32 {
+        let period = self.time_at_step_us(index + 1) - self.time_at_step_us(index);
+        if period > self.cruise_period_us as u64 {
+            period as u32
+        } else {
+            self.cruise_period_us
+        }
+    }
+
+    /// Time taken to reach the given step from rest (µs).
+    const fn time_at_step_us(&self, step: u32) -> u64 {
+        let numerator = (2 * step as u64).saturating_mul(US_PER_SEC * US_PER_SEC);
+        (numerator / self.acceleration as u64).isqrt()
+    }
+}
+
+impl IntoIterator for TrapezoidalProfile {
+    type Item = u32;
+    type IntoIter = StepDelays;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.delays()
+    }
+}
+
+/// Iterator over the step periods of a [`TrapezoidalProfile`] (µs).
+#[derive(Clone, Debug)]
+pub struct StepDelays {
+    profile: TrapezoidalProfile,
+    index: u32,
+}
+
+impl Iterator for StepDelays {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let period = self.profile.period_us(self.index)?;
+        self.index += 1;
+        Some(period)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.profile.steps - self.index) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for StepDelays {}
@@ -0,0 +1,27 @@
+//! Brushed DC motor drivers and control
+
+pub mod current;
+pub mod differential;
+pub mod hbridge;
+#[cfg(target_arch = "riscv32")]
+pub mod ledc;
+pub mod pid;
+pub mod speed;
+
+/// Errors raised while driving a DC motor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// Writing a GPIO pin failed.
+    Pin,
+    /// A PWM channel or timer reported an error.
+    Pwm,
+    /// The speed is outside -100..=100 (%).
+    InvalidSpeed(i8),
+    /// The operation needs a pin which was not provided to the driver.
+    MissingPin,
+    /// Sampling a current sense input failed.
+    Sense,
+    /// The commanded motion cannot be completed (e.g. an arc of zero radius, or a turn at
+    /// zero speed).
+    InvalidMotion,
+}
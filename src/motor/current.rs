@@ -0,0 +1,197 @@
+//! Motor current sensing with overcurrent shutdown
+//!
+//! A [`CurrentMonitor`] converts current sense voltages (e.g. the DBH12 current sense output,
+//! read with the ADC) to currents with a [`CurrentCalibration`], smooths them with a first
+//! order low pass filter, and trips once the filtered current exceeds its threshold.
+//!
+//! The H-bridge enable output is shared through an [`EnableLatch`]: the motor task drives it
+//! through [`EnableLatch::output`] as usual, while a trip pulls it low and holds it low,
+//! whatever the motor task requests, until the fault is cleared with [`EnableLatch::clear`].
+//! Monitors can therefore be updated from their own task (one task can sample every motor's
+//! sense input in turn), and shut the bridge down even while the motor task is blocked (e.g.
+//! awaiting a ramp).
+
+use core::cell::RefCell;
+
+use embassy_sync::blocking_mutex::{Mutex, raw::RawMutex};
+use embedded_hal::digital::{ErrorKind, ErrorType, OutputPin};
+
+use super::Error;
+
+/// Conversion from current sense voltage to motor current.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct CurrentCalibration {
+    /// Change in sense voltage per amp of motor current (mV/A).
+    pub mv_per_amp: u32,
+    /// Sense voltage with no motor current (mV).
+    pub offset_mv: i32,
+}
+
+impl CurrentCalibration {
+    /// Create a calibration from the sense voltage per amp (mV/A) and at zero current (mV).
+    pub const fn new(mv_per_amp: u32, offset_mv: i32) -> Self {
+        assert!(mv_per_amp > 0, "current sense gain must be non-zero");
+        Self {
+            mv_per_amp,
+            offset_mv,
+        }
+    }
+
+    /// Motor current (mA) for a sense voltage (mV), rounded towards zero.
+    pub const fn to_milliamps(&self, mv: i32) -> i32 {
+        let ma = (mv as i64 - self.offset_mv as i64) * 1_000 / self.mv_per_amp as i64;
+        ma as i32
+    }
+}
+
+/// Overcurrent trip, latched until cleared.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Overcurrent {
+    /// Filtered current when the monitor tripped (mA).
+    pub current_ma: i32,
+}
+
+/// Filtered motor current with a trip threshold.
+pub struct CurrentMonitor {
+    calibration: CurrentCalibration,
+    threshold_ma: u32,
+    /// Each sample moves the filtered current 1/2^smoothing of the way to the sample.
+    smoothing: u8,
+    /// Filtered current, scaled by 2^smoothing (mA).
+    accumulator: i64,
+}
+
+impl CurrentMonitor {
+    /// Create a monitor tripping when the magnitude of the filtered current exceeds
+    /// `threshold_ma`, with the filtered current starting at zero.
+    ///
+    /// Each sample moves the filtered current 1/2^`smoothing` of the way to the sample, which
+    /// is a time constant of about 2^`smoothing` sample periods. Zero disables filtering.
+    pub const fn new(calibration: CurrentCalibration, threshold_ma: u32, smoothing: u8) -> Self {
+        assert!(smoothing < 16, "smoothing must be less than 16");
+        Self {
+            calibration,
+            threshold_ma,
+            smoothing,
+            accumulator: 0,
+        }
+    }
+
+    /// Filtered current (mA).
+    pub fn current_ma(&self) -> i32 {
+        (self.accumulator >> self.smoothing) as i32
+    }
+
+    /// Reset the filtered current to zero, e.g. after a fault is cleared.
+    pub fn reset(&mut self) {
+        self.accumulator = 0;
+    }
+
+    /// Filter a sense voltage sample (mV), returning the filtered current (mA), or the trip if
+    /// it exceeds the threshold.
+    pub fn update(&mut self, mv: i32) -> Result<i32, Overcurrent> {
+        let sample = self.calibration.to_milliamps(mv) as i64;
+        self.accumulator += sample - (self.accumulator >> self.smoothing);
+        let current_ma = self.current_ma();
+        if current_ma.unsigned_abs() > self.threshold_ma {
+            Err(Overcurrent { current_ma })
+        } else {
+            Ok(current_ma)
+        }
+    }
+}
+
+/// State of an [`EnableLatch`].
+struct LatchState<P> {
+    pin: P,
+    /// Level last requested through [`EnableLatch::output`].
+    requested: bool,
+    fault: Option<Overcurrent>,
+}
+
+/// Active high enable output held low once tripped, shared between the motor and monitor
+/// tasks.
+pub struct EnableLatch<M: RawMutex, P> {
+    state: Mutex<M, RefCell<LatchState<P>>>,
+}
+
+impl<M: RawMutex, P: OutputPin> EnableLatch<M, P> {
+    /// Create a latch for an enable pin, assumed to be low initially.
+    pub const fn new(pin: P) -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(LatchState {
+                pin,
+                requested: false,
+                fault: None,
+            })),
+        }
+    }
+
+    /// Output pin driving the enable while no fault is latched, e.g. for
+    /// [`HBridge::with_enable`](super::hbridge::HBridge::with_enable).
+    pub fn output(&self) -> LatchedEnable<'_, M, P> {
+        LatchedEnable { latch: self }
+    }
+
+    /// The latched fault, if any.
+    pub fn fault(&self) -> Option<Overcurrent> {
+        self.state.lock(|state| state.borrow().fault)
+    }
+
+    /// Pull the enable low and latch a fault. Only the first fault is kept until cleared.
+    pub fn trip(&self, fault: Overcurrent) -> Result<(), Error> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.fault.get_or_insert(fault);
+            state.pin.set_low().map_err(|_| Error::Pin)
+        })
+    }
+
+    /// Clear a latched fault, restoring the enable to the last requested level.
+    pub fn clear(&self) -> Result<(), Error> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.fault = None;
+            let result = if state.requested {
+                state.pin.set_high()
+            } else {
+                state.pin.set_low()
+            };
+            result.map_err(|_| Error::Pin)
+        })
+    }
+
+    /// Drive the enable to a requested level, unless a fault is latched.
+    fn request(&self, high: bool) -> Result<(), Error> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.requested = high;
+            let result = if high && state.fault.is_none() {
+                state.pin.set_high()
+            } else {
+                state.pin.set_low()
+            };
+            result.map_err(|_| Error::Pin)
+        })
+    }
+}
+
+/// Enable output of an [`EnableLatch`], ignoring requests to go high while a fault is
+/// latched.
+pub struct LatchedEnable<'a, M: RawMutex, P> {
+    latch: &'a EnableLatch<M, P>,
+}
+
+impl<M: RawMutex, P: OutputPin> ErrorType for LatchedEnable<'_, M, P> {
+    type Error = ErrorKind;
+}
+
+impl<M: RawMutex, P: OutputPin> OutputPin for LatchedEnable<'_, M, P> {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.latch.request(false).map_err(|_| ErrorKind::Other)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.latch.request(true).map_err(|_| ErrorKind::Other)
+    }
+}
@@ -0,0 +1,233 @@
+//! Differential drive from two H-bridges (e.g. both channels of a DBH12)
+//!
+//! A differential drive steers by driving its left and right wheels at different speeds.
+//! Commands are a linear velocity (mm/s, positive forward) and an angular velocity (deg/s,
+//! positive turning left, i.e. counter-clockwise seen from above), which [`DriveConfig::mix`]
+//! converts to a duty for each wheel.
+//!
+//! Wheel speeds are taken to be proportional to duty, up to [`DriveConfig::max_speed_mm_s`]
+//! at full duty. This is open-loop, so the speeds (and the timed [`DifferentialDrive::arc_by`]
+//! and [`DifferentialDrive::pivot_by`] helpers) are estimates, which depend on the load and
+//! supply voltage. Commands which would need either wheel beyond the duty limit are reduced
+//! according to the [`Saturation`] policy.
+//!
+//! The motors must be wired so that positive speeds drive both wheels forward (for mirrored
+//! motors, swap the leads of one of them).
+
+use embassy_time::{Duration, Timer};
+use embedded_hal::{digital::OutputPin, pwm::SetDutyCycle};
+
+use super::{
+    Error,
+    hbridge::{BridgeChip, HBridge, MAX_SPEED},
+};
+use crate::motion::{US_PER_SEC, round};
+
+/// Radians per degree.
+const RAD_PER_DEG: f32 = core::f32::consts::PI / 180.0;
+
+/// How commands beyond the duty limit are reduced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Saturation {
+    /// Scale both wheel speeds down by the same factor, keeping the radius of the turn but
+    /// slowing down along it.
+    Scale,
+    /// Keep the angular velocity, reducing the linear velocity as needed (down to a pivot
+    /// turn, beyond which the angular velocity is reduced too).
+    PreserveTurn,
+}
+
+/// Parameters of a [`DifferentialDrive`].
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct DriveConfig {
+    /// Distance between the centres of the wheels (mm).
+    pub track_width_mm: f32,
+    /// Wheel speed at full duty (mm/s).
+    pub max_speed_mm_s: f32,
+    /// Highest duty applied to either motor (%), e.g. the highest duty the motors are rated
+    /// for.
+    pub max_duty: i8,
+    /// How commands beyond `max_duty` are reduced.
+    pub saturation: Saturation,
+}
+
+/// Wheel duties for a command, and the velocities they give.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Mix {
+    /// Duty of the left wheel (%).
+    pub left: i8,
+    /// Duty of the right wheel (%).
+    pub right: i8,
+    /// Linear velocity after saturation (mm/s).
+    pub linear_mm_s: f32,
+    /// Angular velocity after saturation (deg/s).
+    pub angular_deg_s: f32,
+}
+
+impl DriveConfig {
+    /// Fastest wheel speed, at `max_duty` (mm/s).
+    pub fn speed_limit_mm_s(&self) -> f32 {
+        self.max_speed_mm_s * self.max_duty.clamp(0, MAX_SPEED) as f32 / MAX_SPEED as f32
+    }
+
+    /// Mix a linear (mm/s) and angular (deg/s) velocity into wheel duties, reducing commands
+    /// beyond the duty limit according to the saturation policy.
+    pub fn mix(&self, linear_mm_s: f32, angular_deg_s: f32) -> Mix {
+        let limit = self.speed_limit_mm_s();
+        // speed of each wheel relative to the centre of the axle
+        let turn = angular_deg_s * RAD_PER_DEG * self.track_width_mm / 2.0;
+        let (linear, turn) = match self.saturation {
+            Saturation::Scale => {
+                let peak = linear_mm_s.abs() + turn.abs();
+                if peak > limit {
+                    (linear_mm_s * limit / peak, turn * limit / peak)
+                } else {
+                    (linear_mm_s, turn)
+                }
+            }
+            Saturation::PreserveTurn => {
+                let turn = turn.clamp(-limit, limit);
+                let headroom = limit - turn.abs();
+                (linear_mm_s.clamp(-headroom, headroom), turn)
+            }
+        };
+        Mix {
+            left: self.duty(linear - turn),
+            right: self.duty(linear + turn),
+            linear_mm_s: linear,
+            angular_deg_s: turn * 2.0 / (self.track_width_mm * RAD_PER_DEG),
+        }
+    }
+
+    /// Duty (%) for a wheel speed (mm/s) within the speed limit, rounded to the nearest step.
+    fn duty(&self, speed_mm_s: f32) -> i8 {
+        let duty = speed_mm_s * MAX_SPEED as f32 / self.max_speed_mm_s;
+        let max_duty = i32::from(self.max_duty.clamp(0, MAX_SPEED));
+        round(duty).clamp(-max_duty, max_duty) as i8
+    }
+}
+
+/// Pair of H-bridges driving the left and right wheels of a differential drive.
+pub struct DifferentialDrive<C, P, E> {
+    left: HBridge<C, P, E>,
+    right: HBridge<C, P, E>,
+    config: DriveConfig,
+}
+
+impl<C: BridgeChip, P: SetDutyCycle, E: OutputPin> DifferentialDrive<C, P, E> {
+    /// Create a drive from the bridges of its left and right wheels.
+    pub fn new(left: HBridge<C, P, E>, right: HBridge<C, P, E>, config: DriveConfig) -> Self {
+        assert!(config.track_width_mm > 0.0, "track width must be positive");
+        assert!(config.max_speed_mm_s > 0.0, "max speed must be positive");
+        Self {
+            left,
+            right,
+            config,
+        }
+    }
+
+    /// Parameters of the drive.
+    pub fn config(&self) -> &DriveConfig {
+        &self.config
+    }
+
+    /// Bridge of the left wheel.
+    pub fn left_mut(&mut self) -> &mut HBridge<C, P, E> {
+        &mut self.left
+    }
+
+    /// Bridge of the right wheel.
+    pub fn right_mut(&mut self) -> &mut HBridge<C, P, E> {
+        &mut self.right
+    }
+
+    /// Enable or disable both bridges (see [`HBridge::set_enabled`]).
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.left.set_enabled(enabled)?;
+        self.right.set_enabled(enabled)
+    }
+
+    /// Drive at a linear (mm/s) and angular (deg/s) velocity, returning the wheel duties and
+    /// the velocities after saturation.
+    pub fn drive(&mut self, linear_mm_s: f32, angular_deg_s: f32) -> Result<Mix, Error> {
+        let mix = self.config.mix(linear_mm_s, angular_deg_s);
+        self.left.set_speed(mix.left)?;
+        self.right.set_speed(mix.right)?;
+        Ok(mix)
+    }
+
+    /// Drive along an arc of `radius_mm` at `speed_mm_s`, turning left for positive radii and
+    /// right for negative ones.
+    pub fn arc(&mut self, speed_mm_s: f32, radius_mm: f32) -> Result<Mix, Error> {
+        if radius_mm == 0.0 {
+            return Err(Error::InvalidMotion);
+        }
+        let angular_deg_s = speed_mm_s / radius_mm / RAD_PER_DEG;
+        self.drive(speed_mm_s, angular_deg_s)
+    }
+
+    /// Turn on the spot at `rate_deg_s`, counter-clockwise for positive rates.
+    pub fn pivot(&mut self, rate_deg_s: f32) -> Result<Mix, Error> {
+        self.drive(0.0, rate_deg_s)
+    }
+
+    /// Set both duties to zero (coasting the DRV8871 and braking the DBH12).
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.left.set_speed(0)?;
+        self.right.set_speed(0)
+    }
+
+    /// Brake both wheels.
+    pub fn brake(&mut self) -> Result<(), Error> {
+        self.left.brake()?;
+        self.right.brake()
+    }
+
+    /// Let both wheels spin down freely (see [`HBridge::coast`]).
+    pub fn coast(&mut self) -> Result<(), Error> {
+        self.left.coast()?;
+        self.right.coast()
+    }
+
+    /// Drive `distance_mm` in a straight line at `speed_mm_s`, backwards for negative
+    /// distances, then stop.
+    pub async fn straight_by(&mut self, distance_mm: f32, speed_mm_s: f32) -> Result<(), Error> {
+        let mix = self.drive(speed_mm_s.abs().copysign(distance_mm), 0.0)?;
+        self.hold(distance_mm, mix.linear_mm_s).await
+    }
+
+    /// Drive through `angle_deg` of an arc of `radius_mm` at `speed_mm_s`, then stop.
+    ///
+    /// The turn direction follows the sign of the radius (see [`DifferentialDrive::arc`]),
+    /// and the wheels drive backwards for negative speeds.
+    pub async fn arc_by(
+        &mut self,
+        angle_deg: f32,
+        radius_mm: f32,
+        speed_mm_s: f32,
+    ) -> Result<(), Error> {
+        let mix = self.arc(speed_mm_s, radius_mm)?;
+        let turned = angle_deg.abs() * mix.angular_deg_s.signum();
+        self.hold(turned, mix.angular_deg_s).await
+    }
+
+    /// Turn on the spot by `angle_deg` at `rate_deg_s`, counter-clockwise for positive angles,
+    /// then stop.
+    pub async fn pivot_by(&mut self, angle_deg: f32, rate_deg_s: f32) -> Result<(), Error> {
+        let mix = self.pivot(rate_deg_s.abs().copysign(angle_deg))?;
+        self.hold(angle_deg, mix.angular_deg_s).await
+    }
+
+    /// Hold the current duties for the time needed to cover `amount` at `rate`, then stop.
+    async fn hold(&mut self, amount: f32, rate: f32) -> Result<(), Error> {
+        if amount != 0.0 {
+            if rate == 0.0 {
+                self.stop()?;
+                return Err(Error::InvalidMotion);
+            }
+            let us = amount / rate * US_PER_SEC as f32;
+            Timer::after(Duration::from_micros(us as u64)).await;
+        }
+        self.stop()
+    }
+}
@@ -0,0 +1,274 @@
+//! Generic H-bridge driver with DRV8871 and DBH12 backends
+//!
+//! Each H-bridge is driven from two PWM inputs (IN1 and IN2). To drive the motor, one input
+//! carries the PWM duty and the other is held low, depending on the direction. How the bridge
+//! responds to both inputs at the same level differs between chips, and is described by the
+//! [`BridgeChip`] marker types: the DRV8871 coasts with both inputs low and brakes with both
+//! high, while the DBH12 brakes with both low and can only coast by pulling its enable low.
+//!
+//! [`HBridge::ramp`] fades the duty in hardware on channels implementing [`FadeDutyCycle`]
+//! (e.g. the LEDC channels, see [`super::ledc`]).
+
+use core::marker::PhantomData;
+
+use embedded_hal::{digital::OutputPin, pwm::SetDutyCycle};
+
+use super::Error;
+use crate::stepper::driver::NoPin;
+
+/// Fastest speed in either direction (%).
+pub const MAX_SPEED: i8 = 100;
+
+/// Static description of the input logic of an H-bridge chip.
+pub trait BridgeChip {
+    /// Level on both inputs which lets the motor coast, or `None` if the chip can only coast
+    /// by being disabled.
+    const COAST_LEVEL: Option<bool>;
+    /// Level on both inputs which brakes the motor.
+    const BRAKE_LEVEL: bool;
+}
+
+/// TI DRV8871 (coasts with both inputs low, brakes with both high).
+pub struct Drv8871;
+
+impl BridgeChip for Drv8871 {
+    const COAST_LEVEL: Option<bool> = Some(false);
+    const BRAKE_LEVEL: bool = true;
+}
+
+/// DBH12 dual H-bridge module (brakes with both inputs low, coasts when EN is low).
+pub struct Dbh12;
+
+impl BridgeChip for Dbh12 {
+    const COAST_LEVEL: Option<bool> = None;
+    const BRAKE_LEVEL: bool = false;
+}
+
+/// PWM channel which can fade its duty cycle in hardware.
+#[allow(async_fn_in_trait)]
+pub trait FadeDutyCycle: SetDutyCycle {
+    /// Fade the duty cycle linearly from `from_pct` to `to_pct` (%) over `duration_ms`,
+    /// returning once the fade is complete.
+    async fn fade_duty_cycle_percent(
+        &mut self,
+        from_pct: u8,
+        to_pct: u8,
+        duration_ms: u16,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Output state of an H-bridge.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum State {
+    /// Driven at a signed speed (%), positive driving IN1 and negative driving IN2.
+    Drive(i8),
+    /// Motor terminals left floating, so the motor spins down freely.
+    Coast,
+    /// Motor terminals shorted together, so the motor stops quickly.
+    Brake,
+}
+
+/// H-bridge of the chip `C`, with PWM inputs of type `P` and an optional enable output `E`.
+pub struct HBridge<C, P, E = NoPin> {
+    in1: P,
+    in2: P,
+    enable: Option<E>,
+    /// Whether the bridge was enabled with [`HBridge::set_enabled`].
+    enabled: bool,
+    state: State,
+    _chip: PhantomData<C>,
+}
+
+impl<C: BridgeChip, P: SetDutyCycle> HBridge<C, P> {
+    /// Create a driver from its IN1 and IN2 PWM channels, both assumed to be at zero duty.
+    pub fn new(in1: P, in2: P) -> Self {
+        Self {
+            in1,
+            in2,
+            enable: None,
+            enabled: true,
+            state: State::Drive(0),
+            _chip: PhantomData,
+        }
+    }
+}
+
+impl<C: BridgeChip, P: SetDutyCycle, E: OutputPin> HBridge<C, P, E> {
+    /// Add an active high enable output, assumed to be low (disabled) initially.
+    pub fn with_enable<F: OutputPin>(self, pin: F) -> HBridge<C, P, F> {
+        HBridge {
+            in1: self.in1,
+            in2: self.in2,
+            enable: Some(pin),
+            enabled: false,
+            state: self.state,
+            _chip: PhantomData,
+        }
+    }
+
+    /// Current output state.
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Current signed speed (%), zero while coasting or braking.
+    pub fn speed(&self) -> i8 {
+        match self.state {
+            State::Drive(speed) => speed,
+            State::Coast | State::Brake => 0,
+        }
+    }
+
+    /// Enable or disable the bridge outputs. Ignored without an enable pin.
+    ///
+    /// While coasting with the enable pulled low, the change only applies once the motor is
+    /// driven or braked again.
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.enabled = enabled;
+        match self.state {
+            State::Coast if C::COAST_LEVEL.is_none() => Ok(()),
+            _ => self.write_enable(enabled),
+        }
+    }
+
+    /// Drive the motor at a signed speed from -100 to 100 (%).
+    ///
+    /// Zero holds both inputs low, which coasts the DRV8871 but brakes the DBH12.
+    pub fn set_speed(&mut self, speed: i8) -> Result<(), Error> {
+        check_speed(speed)?;
+        self.leave_coast()?;
+        // release the idle input first, so the bridge never brakes on a direction change
+        let (active, idle) = self.inputs(speed >= 0);
+        idle.set_duty_cycle_fully_off().map_err(|_| Error::Pwm)?;
+        active
+            .set_duty_cycle_percent(speed.unsigned_abs())
+            .map_err(|_| Error::Pwm)?;
+        self.state = State::Drive(speed);
+        Ok(())
+    }
+
+    /// Let the motor spin down freely.
+    ///
+    /// Chips which can only coast by being disabled need an enable pin, and stay disabled
+    /// until the next call to [`HBridge::set_speed`] or [`HBridge::brake`].
+    pub fn coast(&mut self) -> Result<(), Error> {
+        match C::COAST_LEVEL {
+            Some(level) => self.set_inputs(level)?,
+            None if self.enable.is_some() => {
+                self.set_inputs(false)?;
+                self.write_enable(false)?;
+            }
+            None => return Err(Error::MissingPin),
+        }
+        self.state = State::Coast;
+        Ok(())
+    }
+
+    /// Short the motor terminals, stopping the motor quickly.
+    pub fn brake(&mut self) -> Result<(), Error> {
+        self.leave_coast()?;
+        self.set_inputs(C::BRAKE_LEVEL)?;
+        self.state = State::Brake;
+        Ok(())
+    }
+
+    /// Ramp linearly from the current speed to `speed` (%) over `duration_ms`, fading the
+    /// duty in hardware.
+    ///
+    /// Ramps through zero are split into a ramp down and a ramp up on the other input, with
+    /// the duration shared in proportion to the change in speed of each. Cancelling a ramp
+    /// leaves the bridge at the end speed of the fade in progress, which the hardware
+    /// completes regardless. Fades rounding to zero length set the speed directly.
+    pub async fn ramp(&mut self, speed: i8, duration_ms: u16) -> Result<(), Error>
+    where
+        P: FadeDutyCycle,
+    {
+        check_speed(speed)?;
+        let from = self.speed();
+        if from.signum() * speed.signum() < 0 {
+            let total = from.unsigned_abs() as u32 + speed.unsigned_abs() as u32;
+            let down_ms = (duration_ms as u32 * from.unsigned_abs() as u32 / total) as u16;
+            self.fade(from, 0, down_ms).await?;
+            self.fade(0, speed, duration_ms - down_ms).await
+        } else {
+            self.fade(from, speed, duration_ms).await
+        }
+    }
+
+    /// Fade between two speeds of the same sign (or zero).
+    ///
+    /// The state is set to `to` before awaiting the fade: a hardware fade carries on to its
+    /// end even if the ramp is cancelled, so the state stays correct when the future is
+    /// dropped part way.
+    async fn fade(&mut self, from: i8, to: i8, duration_ms: u16) -> Result<(), Error>
+    where
+        P: FadeDutyCycle,
+    {
+        // hardware fades cannot run over zero time
+        if duration_ms == 0 {
+            return self.set_speed(to);
+        }
+        self.set_speed(from)?;
+        if from == to {
+            return Ok(());
+        }
+        self.state = State::Drive(to);
+        let (active, _) = self.inputs(from > 0 || to > 0);
+        let result = active
+            .fade_duty_cycle_percent(from.unsigned_abs(), to.unsigned_abs(), duration_ms)
+            .await;
+        if result.is_err() {
+            self.state = State::Drive(from);
+            return Err(Error::Pwm);
+        }
+        Ok(())
+    }
+
+    /// The (active, idle) inputs when driving forward or in reverse.
+    fn inputs(&mut self, forward: bool) -> (&mut P, &mut P) {
+        if forward {
+            (&mut self.in1, &mut self.in2)
+        } else {
+            (&mut self.in2, &mut self.in1)
+        }
+    }
+
+    /// Set both inputs to the same level.
+    fn set_inputs(&mut self, high: bool) -> Result<(), Error> {
+        for input in [&mut self.in1, &mut self.in2] {
+            let result = if high {
+                input.set_duty_cycle_fully_on()
+            } else {
+                input.set_duty_cycle_fully_off()
+            };
+            result.map_err(|_| Error::Pwm)?;
+        }
+        Ok(())
+    }
+
+    /// Restore the enable output after coasting with it pulled low.
+    fn leave_coast(&mut self) -> Result<(), Error> {
+        match self.state {
+            State::Coast if C::COAST_LEVEL.is_none() => self.write_enable(self.enabled),
+            _ => Ok(()),
+        }
+    }
+
+    /// Set the enable output, if present.
+    fn write_enable(&mut self, high: bool) -> Result<(), Error> {
+        let Some(pin) = &mut self.enable else {
+            return Ok(());
+        };
+        let result = if high { pin.set_high() } else { pin.set_low() };
+        result.map_err(|_| Error::Pin)
+    }
+}
+
+/// Check that a speed is within -100..=100 (%).
+fn check_speed(speed: i8) -> Result<(), Error> {
+    if (-MAX_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(())
+    } else {
+        Err(Error::InvalidSpeed(speed))
+    }
+}
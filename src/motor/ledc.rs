@@ -0,0 +1,193 @@
+//! Motor PWM from the ESP32C3 LEDC peripheral
+//!
+//! Helpers for the timer and channel setup shared by the motor demos, and hardware duty fades
+//! for [`HBridge::ramp`](super::hbridge::HBridge::ramp).
+//!
+//! The end of a fade is signalled by the LEDC fade end interrupt, bound once with
+//! [`bind_fade_interrupt`]. [`FadeChannel::start_fade`] returns a [`FadeEnd`] future which
+//! resolves on that interrupt, so fades on several channels can be awaited concurrently
+//! without polling. The interrupt of each channel is only enabled while a future is waiting
+//! on it, and the handler disables it again, leaving the raw fade end flag read by
+//! [`ChannelIFace::is_duty_fade_running`] untouched.
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use embassy_sync::waitqueue::AtomicWaker;
+use embedded_hal::pwm::{ErrorType, SetDutyCycle};
+use esp_hal::{
+    gpio::interconnect::PeripheralOutput,
+    handler, interrupt,
+    ledc::{
+        Ledc, LowSpeed,
+        channel::{self, ChannelIFace, FadeError},
+        timer::{self, TimerIFace},
+    },
+    peripherals::{Interrupt, LEDC},
+    time::Rate,
+};
+
+use super::{Error, hbridge::FadeDutyCycle};
+
+/// Number of LEDC channels on the ESP32C3.
+const CHANNELS: usize = 6;
+
+/// Task waiting for the end of a fade on each channel.
+static FADE_WAKERS: [AtomicWaker; CHANNELS] = [const { AtomicWaker::new() }; CHANNELS];
+
+/// Configure a low speed timer, clocked from the APB clock, for PWM at `frequency` with
+/// `duty` resolution.
+pub fn configure_timer(
+    timer: &mut timer::Timer<'_, LowSpeed>,
+    duty: timer::config::Duty,
+    frequency: Rate,
+) -> Result<(), Error> {
+    let config = timer::config::Config {
+        duty,
+        clock_source: timer::LSClockSource::APBClk,
+        frequency,
+    };
+    timer.configure(config).map_err(|_| Error::Pwm)
+}
+
+/// Configure a channel for push-pull PWM output from `timer`, starting at zero duty.
+pub fn configure_channel<'a>(
+    channel: &mut channel::Channel<'a, LowSpeed>,
+    timer: &'a timer::Timer<'a, LowSpeed>,
+) -> Result<(), Error> {
+    let config = channel::config::Config {
+        timer,
+        duty_pct: 0,
+        pin_config: channel::config::PinConfig::PushPull,
+    };
+    channel.configure(config).map_err(|_| Error::Pwm)
+}
+
+/// Bind and enable the fade end interrupt handler, which [`FadeEnd`] relies on.
+pub fn bind_fade_interrupt(_ledc: &mut Ledc<'_>) {
+    // SAFETY: nothing else in this crate binds the LEDC interrupt
+    unsafe { interrupt::bind_interrupt(Interrupt::LEDC, fade_end_interrupt.handler()) };
+    interrupt::enable(Interrupt::LEDC, fade_end_interrupt.priority()).unwrap();
+}
+
+/// Disable the interrupt of each channel whose fade has ended, and wake its task.
+#[handler]
+fn fade_end_interrupt() {
+    let ledc = LEDC::regs();
+    let status = ledc.int_st().read();
+    let ended = |number: usize| status.duty_chng_end_ch(number as u8).bit_is_set();
+    critical_section::with(|_| {
+        ledc.int_ena().modify(|_, w| {
+            for number in (0..CHANNELS).filter(|&number| ended(number)) {
+                w.duty_chng_end_ch(number as u8).clear_bit();
+            }
+            w
+        })
+    });
+    for (number, waker) in FADE_WAKERS.iter().enumerate() {
+        if ended(number) {
+            waker.wake();
+        }
+    }
+}
+
+/// Future resolving when the fade on a channel ends.
+#[must_use = "futures do nothing unless awaited"]
+pub struct FadeEnd {
+    number: usize,
+}
+
+impl Future for FadeEnd {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        FADE_WAKERS[self.number].register(cx.waker());
+        let ledc = LEDC::regs();
+        let number = self.number as u8;
+        if ledc.int_raw().read().duty_chng_end_ch(number).bit_is_set() {
+            return Poll::Ready(());
+        }
+        // a fade ending before this is enabled still raises the interrupt once it is
+        critical_section::with(|_| {
+            ledc.int_ena()
+                .modify(|_, w| w.duty_chng_end_ch(number).set_bit())
+        });
+        Poll::Pending
+    }
+}
+
+/// Low speed channel whose duty fades are awaited on the fade end interrupt.
+pub struct FadeChannel<'a> {
+    channel: channel::Channel<'a, LowSpeed>,
+    number: channel::Number,
+}
+
+impl<'a> FadeChannel<'a> {
+    /// Create channel `number` of the LEDC peripheral, output on `pin`.
+    pub fn new<'d: 'a>(
+        ledc: &Ledc<'d>,
+        number: channel::Number,
+        pin: impl PeripheralOutput<'d>,
+    ) -> Self {
+        Self {
+            channel: ledc.channel(number, pin),
+            number,
+        }
+    }
+
+    /// Configure the channel for push-pull PWM output from `timer`, starting at zero duty.
+    pub fn configure(&mut self, timer: &'a timer::Timer<'a, LowSpeed>) -> Result<(), Error> {
+        configure_channel(&mut self.channel, timer)
+    }
+
+    /// Start fading the duty linearly from `from_pct` to `to_pct` (%) over `duration_ms`,
+    /// returning a future which resolves once the fade ends.
+    ///
+    /// The future does not borrow the channel, so fades on several channels can be started
+    /// and then awaited together.
+    pub fn start_fade(
+        &mut self,
+        from_pct: u8,
+        to_pct: u8,
+        duration_ms: u16,
+    ) -> Result<FadeEnd, channel::Error> {
+        // the hardware needs at least one duty step to signal the end of a fade
+        if from_pct == to_pct {
+            return Err(channel::Error::Fade(FadeError::DutyRange));
+        }
+        self.channel
+            .start_duty_fade(from_pct, to_pct, duration_ms)?;
+        Ok(FadeEnd {
+            number: self.number as usize,
+        })
+    }
+}
+
+impl ErrorType for FadeChannel<'_> {
+    type Error = channel::Error;
+}
+
+impl SetDutyCycle for FadeChannel<'_> {
+    fn max_duty_cycle(&self) -> u16 {
+        self.channel.max_duty_cycle()
+    }
+
+    fn set_duty_cycle(&mut self, duty: u16) -> Result<(), Self::Error> {
+        self.channel.set_duty_cycle(duty)
+    }
+}
+
+impl FadeDutyCycle for FadeChannel<'_> {
+    async fn fade_duty_cycle_percent(
+        &mut self,
+        from_pct: u8,
+        to_pct: u8,
+        duration_ms: u16,
+    ) -> Result<(), Self::Error> {
+        self.start_fade(from_pct, to_pct, duration_ms)?.await;
+        Ok(())
+    }
+}
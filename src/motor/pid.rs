@@ -0,0 +1,90 @@
+//! PID controller with feed-forward, output clamping and anti-windup
+//!
+//! The output is the sum of a feed-forward term proportional to the setpoint, which provides
+//! most of the output in steady state, and PID terms correcting the remaining error. The
+//! derivative acts on the measurement rather than the error, so that setpoint changes do not
+//! cause output spikes.
+//!
+//! While the output is clamped at a limit, the integral only changes in the direction which
+//! brings the output back within limits (conditional integration). The integral therefore
+//! does not wind up while the output saturates (e.g. for an unreachable setpoint), and the
+//! controller responds immediately once the error reverses.
+
+/// Gains of a [`Pid`] controller, in output units per unit of the controlled variable.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct PidGains {
+    /// Proportional gain.
+    pub kp: f32,
+    /// Integral gain (per second).
+    pub ki: f32,
+    /// Derivative gain (seconds).
+    pub kd: f32,
+    /// Feed-forward gain, applied to the setpoint.
+    pub kff: f32,
+}
+
+/// PID controller, updated at a fixed or measured interval.
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct Pid {
+    gains: PidGains,
+    min: f32,
+    max: f32,
+    integral: f32,
+    /// Measurement of the previous update, for the derivative term.
+    previous: Option<f32>,
+}
+
+impl Pid {
+    /// Create a controller with its output clamped to `min..=max`.
+    pub const fn new(gains: PidGains, min: f32, max: f32) -> Self {
+        assert!(min < max, "output range must not be empty");
+        Self {
+            gains,
+            min,
+            max,
+            integral: 0.0,
+            previous: None,
+        }
+    }
+
+    /// Gains of the controller.
+    pub fn gains(&self) -> PidGains {
+        self.gains
+    }
+
+    /// Current integral term, in output units.
+    pub fn integral(&self) -> f32 {
+        self.integral
+    }
+
+    /// Clear the integral and derivative history, e.g. after the loop was stopped.
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.previous = None;
+    }
+
+    /// Update the controller with a measurement taken `dt_s` seconds after the previous one,
+    /// returning the new output.
+    ///
+    /// The derivative term is zero on the first update after creation or [`Pid::reset`].
+    pub fn update(&mut self, setpoint: f32, measurement: f32, dt_s: f32) -> f32 {
+        let PidGains { kp, ki, kd, kff } = self.gains;
+        let error = setpoint - measurement;
+        let derivative = match self.previous {
+            Some(previous) if dt_s > 0.0 => (previous - measurement) / dt_s,
+            _ => 0.0,
+        };
+        self.previous = Some(measurement);
+
+        let base = kff * setpoint + kp * error + kd * derivative;
+        let integral = self.integral + ki * error * dt_s;
+        let output = base + integral;
+        // only integrate while the output is within limits, or being driven back into them
+        let winding_up = output > self.max && integral > self.integral
+            || output < self.min && integral < self.integral;
+        if !winding_up {
+            self.integral = integral;
+        }
+        (base + self.integral).clamp(self.min, self.max)
+    }
+}
@@ -0,0 +1,222 @@
+//! Closed-loop motor speed control
+//!
+//! A [`SpeedController`] runs a [`Pid`] loop at a fixed rate. Each period it reads the speed
+//! from a [`SpeedSensor`] (e.g. a [`Tachometer`] on an encoder or hall sensor count) and sets
+//! the H-bridge speed (duty) from the controller output. The target speed is set, and the
+//! measured speed read back, through a [`SharedSpeed`], so the loop can run in its own task.
+//!
+//! A target of zero stops the loop driving the motor: the duty is held at zero (coasting the
+//! DRV8871 and braking the DBH12, see [`HBridge::set_speed`]) and the controller is reset.
+
+use core::sync::atomic::{AtomicI8, AtomicU32, Ordering};
+
+use embassy_time::{Duration, Instant, Ticker};
+use embedded_hal::{digital::OutputPin, pwm::SetDutyCycle};
+
+use super::{
+    Error,
+    hbridge::{BridgeChip, HBridge, MAX_SPEED},
+    pid::Pid,
+};
+use crate::{
+    encoder::EncoderCount,
+    motion::{US_PER_MIN, US_PER_SEC, round},
+};
+
+/// Source of speed measurements for a [`SpeedController`].
+pub trait SpeedSensor {
+    /// Whether the measured speed is signed. Speeds from sensors which cannot tell the
+    /// direction of rotation are taken to be in the direction the motor is driven.
+    fn is_directional(&self) -> bool;
+
+    /// Discard any motion since the previous read, e.g. before the first read of a loop.
+    fn reset(&mut self);
+
+    /// Average speed (rpm) since the previous read, `elapsed` ago.
+    fn read_rpm(&mut self, elapsed: Duration) -> Result<f32, Error>;
+}
+
+/// Speed measured from the change in an [`EncoderCount`] between reads.
+///
+/// The resolution is one count per read, so slower loops or more counts per revolution give
+/// smoother measurements (e.g. at 100 Hz, 1320 counts per revolution resolve ~4.5 rpm).
+pub struct Tachometer<'a> {
+    count: &'a EncoderCount,
+    counts_per_rev: u32,
+    directional: bool,
+    previous: i32,
+}
+
+impl<'a> Tachometer<'a> {
+    /// Measure a count from a [`QuadratureEncoder`](crate::encoder::QuadratureEncoder), with
+    /// `counts_per_rev` counts (four per encoder line) per revolution of the shaft.
+    pub fn quadrature(count: &'a EncoderCount, counts_per_rev: u32) -> Self {
+        Self::new(count, counts_per_rev, true)
+    }
+
+    /// Measure a count from a [`PulseCounter`](crate::encoder::PulseCounter) (e.g. a hall
+    /// sensor), with `pulses_per_rev` pulses per revolution of the shaft.
+    pub fn pulses(count: &'a EncoderCount, pulses_per_rev: u32) -> Self {
+        Self::new(count, pulses_per_rev, false)
+    }
+
+    fn new(count: &'a EncoderCount, counts_per_rev: u32, directional: bool) -> Self {
+        assert!(counts_per_rev > 0, "counts per revolution must be non-zero");
+        Self {
+            count,
+            counts_per_rev,
+            directional,
+            previous: count.get(),
+        }
+    }
+}
+
+impl SpeedSensor for Tachometer<'_> {
+    fn is_directional(&self) -> bool {
+        self.directional
+    }
+
+    fn reset(&mut self) {
+        self.previous = self.count.get();
+    }
+
+    fn read_rpm(&mut self, elapsed: Duration) -> Result<f32, Error> {
+        let count = self.count.get();
+        let delta = count.wrapping_sub(self.previous);
+        self.previous = count;
+        match elapsed.as_micros() {
+            0 => Ok(0.0),
+            us => Ok(delta as f32 * US_PER_MIN as f32 / (self.counts_per_rev as f32 * us as f32)),
+        }
+    }
+}
+
+/// Target and measured speed shared between a [`SpeedController`] loop and its users.
+///
+/// Each value has a single writer, so plain atomic loads and stores suffice (the ESP32C3 has
+/// no atomic read-modify-write instructions).
+pub struct SharedSpeed {
+    target: AtomicU32,
+    measured: AtomicU32,
+    duty: AtomicI8,
+}
+
+impl SharedSpeed {
+    /// Create with a target and measured speed of zero.
+    pub const fn new() -> Self {
+        Self {
+            target: AtomicU32::new(0),
+            measured: AtomicU32::new(0),
+            duty: AtomicI8::new(0),
+        }
+    }
+
+    /// Target speed (rpm).
+    pub fn target(&self) -> f32 {
+        f32::from_bits(self.target.load(Ordering::Relaxed))
+    }
+
+    /// Set the target speed (rpm), applied from the next period of the loop.
+    pub fn set_target(&self, rpm: f32) {
+        self.target.store(rpm.to_bits(), Ordering::Relaxed);
+    }
+
+    /// Speed measured in the latest period of the loop (rpm).
+    pub fn measured(&self) -> f32 {
+        f32::from_bits(self.measured.load(Ordering::Relaxed))
+    }
+
+    /// Duty set in the latest period of the loop (%).
+    pub fn duty(&self) -> i8 {
+        self.duty.load(Ordering::Relaxed)
+    }
+
+    /// Publish the results of a period. Must only be called by the loop.
+    fn publish(&self, rpm: f32, duty: i8) {
+        self.measured.store(rpm.to_bits(), Ordering::Relaxed);
+        self.duty.store(duty, Ordering::Relaxed);
+    }
+}
+
+impl Default for SharedSpeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed rate speed control loop, with the [`Pid`] output as the H-bridge speed (%).
+pub struct SpeedController<S> {
+    pid: Pid,
+    sensor: S,
+    period: Duration,
+    duty: i8,
+}
+
+impl<S: SpeedSensor> SpeedController<S> {
+    /// Create a loop updating `pid` from `sensor` every `period`.
+    ///
+    /// The output of `pid` is the duty (%), so its limits should be within -100..=100 (e.g.
+    /// the highest duty the motor is rated for). Outputs beyond that range are clamped.
+    pub const fn new(pid: Pid, sensor: S, period: Duration) -> Self {
+        Self {
+            pid,
+            sensor,
+            period,
+            duty: 0,
+        }
+    }
+
+    /// PID controller of the loop.
+    pub fn pid(&self) -> &Pid {
+        &self.pid
+    }
+
+    /// Run one period of the loop `elapsed` after the previous one, returning the measured
+    /// speed (rpm) and the new duty (%).
+    pub fn update(&mut self, target_rpm: f32, elapsed: Duration) -> Result<(f32, i8), Error> {
+        let mut rpm = self.sensor.read_rpm(elapsed)?;
+        if !self.sensor.is_directional() && self.duty < 0 {
+            rpm = -rpm;
+        }
+        if target_rpm == 0.0 {
+            self.pid.reset();
+            self.duty = 0;
+            return Ok((rpm, 0));
+        }
+
+        let dt_s = elapsed.as_micros() as f32 / US_PER_SEC as f32;
+        let output = self.pid.update(target_rpm, rpm, dt_s);
+        let limit = MAX_SPEED as f32;
+        // round to the nearest duty step (truncation alone would bias towards zero)
+        self.duty = round(output.clamp(-limit, limit)) as i8;
+        Ok((rpm, self.duty))
+    }
+
+    /// Control the speed of `motor` indefinitely, following the target in `shared` and
+    /// publishing the measured speed and duty to it every period.
+    ///
+    /// Intended to run in its own task. Returns only if the sensor or motor fails.
+    pub async fn run<C: BridgeChip, P: SetDutyCycle, E: OutputPin>(
+        &mut self,
+        motor: &mut HBridge<C, P, E>,
+        shared: &SharedSpeed,
+    ) -> Error {
+        self.sensor.reset();
+        let mut ticker = Ticker::every(self.period);
+        let mut previous = Instant::now();
+        loop {
+            ticker.next().await;
+            let now = Instant::now();
+            let result = self
+                .update(shared.target(), now - previous)
+                .and_then(|(rpm, duty)| {
+                    shared.publish(rpm, duty);
+                    motor.set_speed(duty)
+                });
+            previous = now;
+            if let Err(e) = result {
+                return e;
+            }
+        }
+    }
+}
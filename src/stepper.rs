@@ -0,0 +1,57 @@
+//! Stepper motor drivers and control
+
+pub mod axis;
+pub mod bipolar;
+pub mod controller;
+pub mod cycle;
+pub mod driver;
+pub mod generator;
+pub mod homing;
+pub mod jog;
+pub mod multi_axis;
+pub mod queue;
+#[cfg(target_arch = "riscv32")]
+pub mod rmt;
+pub mod supervisor;
+pub mod tmc2209;
+pub mod uln2003;
+pub mod units;
+
+/// Rotation direction, as signalled on the driver DIR pin.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Direction {
+    /// DIR pin driven high.
+    Forward,
+    /// DIR pin driven low.
+    Reverse,
+}
+
+impl Direction {
+    /// The opposite direction.
+    pub const fn reversed(self) -> Self {
+        match self {
+            Self::Forward => Self::Reverse,
+            Self::Reverse => Self::Forward,
+        }
+    }
+}
+
+/// Errors raised while driving a stepper motor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Error {
+    /// Reading or writing a GPIO pin failed.
+    Pin,
+    /// The driver chip has no microstep mode with this divisor.
+    UnsupportedMicrosteps(u16),
+    /// The operation needs a pin which was not provided to the driver.
+    MissingPin,
+    /// A peripheral used to generate step pulses or coil currents reported an error.
+    Peripheral,
+    /// The driver reported a fault (e.g. overcurrent or overtemperature) during a move.
+    Fault,
+    /// A move took the position, or the distance to its target, beyond the range of the
+    /// position type.
+    PositionOverflow,
+    /// A move was requested at zero velocity, which would never complete.
+    ZeroVelocity,
+}
@@ -0,0 +1,246 @@
+//! Stepper axes positioned in mechanical units
+//!
+//! An [`AxisConfig`] describes the drive train from the motor to the load: steps per motor
+//! revolution (including microsteps), an optional gearbox or pulley reduction, and a lead
+//! screw or belt pulley for linear axes. Linear axes are positioned in millimetres, and rotary
+//! axes in degrees of the output shaft.
+//!
+//! Moves are rounded to whole steps, and an [`Axis`] carries the fraction of a step lost to
+//! rounding over to the next move. Rounding errors therefore never accumulate over a sequence
+//! of relative moves: the axis is always within half a step of where it was told to be,
+//! however many moves it took to get there.
+
+use super::{
+    Error, controller::Stepper, driver::StepperDriver, generator::StepGenerator, units::Steps,
+};
+use crate::motion::{round, split, trapezoidal::TrapezoidalProfile};
+
+/// Degrees per revolution of a rotary axis.
+const DEG_PER_REV: f32 = 360.0;
+
+/// Number of micrometres per millimetre.
+const UM_PER_MM: f32 = 1_000.0;
+
+/// Reduction between the motor and the output shaft.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct GearRatio {
+    /// Motor revolutions per `output` revolutions of the output shaft.
+    pub motor: u32,
+    /// Output shaft revolutions per `motor` revolutions of the motor.
+    pub output: u32,
+}
+
+impl GearRatio {
+    /// Motor coupled directly to the output shaft.
+    pub const DIRECT: Self = Self::new(1, 1);
+
+    /// Create a ratio of `motor` motor revolutions per `output` output revolutions, e.g.
+    /// `GearRatio::new(60, 20)` for a 20 tooth pulley on the motor driving a 60 tooth pulley.
+    pub const fn new(motor: u32, output: u32) -> Self {
+        assert!(motor > 0 && output > 0, "gear ratio must be non-zero");
+        Self { motor, output }
+    }
+}
+
+/// Conversion from output shaft rotation to the position of the load.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum Transmission {
+    /// The output shaft is the load, positioned in degrees.
+    Rotary,
+    /// The load travels linearly, positioned in millimetres.
+    Linear {
+        /// Travel per output shaft revolution (µm).
+        travel_per_rev_um: u32,
+    },
+}
+
+impl Transmission {
+    /// Lead screw advancing `lead_um` per revolution (lead, not pitch, for multi-start
+    /// screws).
+    pub const fn lead_screw(lead_um: u32) -> Self {
+        assert!(lead_um > 0, "lead must be non-zero");
+        Self::Linear {
+            travel_per_rev_um: lead_um,
+        }
+    }
+
+    /// Toothed belt pulley with `teeth` teeth on a belt of `pitch_um` tooth pitch.
+    pub const fn pulley(teeth: u32, pitch_um: u32) -> Self {
+        assert!(
+            teeth > 0 && pitch_um > 0,
+            "pulley must have non-zero teeth and pitch"
+        );
+        Self::Linear {
+            travel_per_rev_um: teeth * pitch_um,
+        }
+    }
+
+    /// Units (mm or degrees) travelled per output shaft revolution.
+    pub const fn units_per_rev(self) -> f32 {
+        match self {
+            Self::Rotary => DEG_PER_REV,
+            Self::Linear { travel_per_rev_um } => travel_per_rev_um as f32 / UM_PER_MM,
+        }
+    }
+}
+
+/// Drive train of one axis, converting between steps and mechanical units (mm or degrees).
+#[derive(Clone, Copy, Debug, PartialEq, defmt::Format)]
+pub struct AxisConfig {
+    /// Steps (including microsteps) per motor revolution.
+    pub steps_per_rev: Steps,
+    /// Reduction between the motor and the output shaft.
+    pub gear_ratio: GearRatio,
+    /// Conversion from output shaft rotation to the position of the load.
+    pub transmission: Transmission,
+}
+
+impl AxisConfig {
+    /// Create a configuration.
+    pub const fn new(
+        steps_per_rev: Steps,
+        gear_ratio: GearRatio,
+        transmission: Transmission,
+    ) -> Self {
+        assert!(steps_per_rev.get() > 0, "steps per rev must be non-zero");
+        Self {
+            steps_per_rev,
+            gear_ratio,
+            transmission,
+        }
+    }
+
+    /// Steps per unit (mm or degree) of travel.
+    pub const fn steps_per_unit(&self) -> f32 {
+        let steps_per_output_rev = self.steps_per_rev.get() as f32 * self.gear_ratio.motor as f32
+            / self.gear_ratio.output as f32;
+        steps_per_output_rev / self.transmission.units_per_rev()
+    }
+
+    /// Travel per step (mm or degrees), i.e. the resolution of the axis.
+    pub const fn units_per_step(&self) -> f32 {
+        1.0 / self.steps_per_unit()
+    }
+
+    /// Step position nearest to a position in units, saturating at the limits of `i32`.
+    pub const fn to_steps(&self, units: f32) -> i32 {
+        round(units * self.steps_per_unit())
+    }
+
+    /// Position in units of a step position.
+    pub const fn to_units(&self, steps: i32) -> f32 {
+        steps as f32 / self.steps_per_unit()
+    }
+
+    /// Step rate (steps/s) for a velocity in units/s, rounded to the nearest step/s (at least
+    /// one).
+    pub const fn velocity(&self, units_per_sec: f32) -> u32 {
+        let steps_per_sec = round(units_per_sec * self.steps_per_unit());
+        let steps_per_sec = steps_per_sec.unsigned_abs();
+        if steps_per_sec > 1 { steps_per_sec } else { 1 }
+    }
+
+    /// Step acceleration (steps/s²) for an acceleration in units/s², rounded to the nearest
+    /// step/s² (at least one).
+    pub const fn acceleration(&self, units_per_sec2: f32) -> u32 {
+        self.velocity(units_per_sec2)
+    }
+}
+
+/// Stepper axis moved in mechanical units.
+///
+/// The commanded position is the stepper's target plus a fraction of a step. After moving or
+/// redefining the position of the stepper directly (e.g. when homing), call [`Axis::sync`]
+/// or [`Axis::set_position`] to discard the fraction.
+pub struct Axis<D, G> {
+    stepper: Stepper<D, G>,
+    config: AxisConfig,
+    /// Commanded position minus the stepper target, in the range ±0.5 (steps).
+    residual: f32,
+}
+
+impl<D: StepperDriver, G: StepGenerator> Axis<D, G> {
+    /// Create an axis, with the commanded position matching the stepper's target.
+    pub fn new(stepper: Stepper<D, G>, config: AxisConfig) -> Self {
+        Self {
+            stepper,
+            config,
+            residual: 0.0,
+        }
+    }
+
+    /// Drive train of the axis.
+    pub fn config(&self) -> &AxisConfig {
+        &self.config
+    }
+
+    /// Shared access to the underlying stepper.
+    pub fn stepper(&self) -> &Stepper<D, G> {
+        &self.stepper
+    }
+
+    /// Exclusive access to the underlying stepper.
+    pub fn stepper_mut(&mut self) -> &mut Stepper<D, G> {
+        &mut self.stepper
+    }
+
+    /// Release the underlying stepper.
+    pub fn into_inner(self) -> Stepper<D, G> {
+        self.stepper
+    }
+
+    /// Current position (mm or degrees), to the nearest step.
+    pub fn position(&self) -> f32 {
+        self.config.to_units(self.stepper.position())
+    }
+
+    /// Commanded position (mm or degrees), which the step position follows to within half a
+    /// step.
+    pub fn target(&self) -> f32 {
+        (self.stepper.target() as f32 + self.residual) / self.config.steps_per_unit()
+    }
+
+    /// Redefine the current position without moving, cancelling any planned move.
+    pub fn set_position(&mut self, units: f32) {
+        let (steps, residual) = split(units * self.config.steps_per_unit());
+        self.stepper.set_position(steps);
+        self.residual = residual;
+    }
+
+    /// Take the commanded position from the stepper's target, after it was moved directly.
+    pub fn sync(&mut self) {
+        self.residual = 0.0;
+    }
+
+    /// Plan a move to an absolute position (mm or degrees).
+    pub fn move_to(&mut self, units: f32) -> Result<(), Error> {
+        let (steps, residual) = split(units * self.config.steps_per_unit());
+        self.stepper.move_to(steps)?;
+        self.residual = residual;
+        Ok(())
+    }
+
+    /// Plan a move relative to the commanded position (mm or degrees).
+    pub fn move_by(&mut self, units: f32) -> Result<(), Error> {
+        // only the change is converted, so large positions don't cost float precision
+        let (delta, residual) = split(self.residual + units * self.config.steps_per_unit());
+        self.stepper
+            .move_to(self.stepper.target().saturating_add(delta))?;
+        self.residual = residual;
+        Ok(())
+    }
+
+    /// Execute the planned move with a trapezoidal profile, returning the number of steps
+    /// taken.
+    ///
+    /// `velocity` (units/s) and `acceleration` (units/s²) are converted to steps with
+    /// [`AxisConfig::velocity`] and [`AxisConfig::acceleration`].
+    pub async fn run(&mut self, velocity: f32, acceleration: f32) -> Result<u32, Error> {
+        let profile = TrapezoidalProfile::new(
+            self.config.velocity(velocity),
+            self.config.acceleration(acceleration),
+            self.stepper.remaining_steps(),
+        );
+        self.stepper.run(profile).await
+    }
+}
@@ -0,0 +1,249 @@
+//! Bipolar stepper microstepped in software from two H-bridges (e.g. DRV8871)
+//!
+//! Each coil is driven by its own H-bridge, with PWM on one input and the other held low
+//! depending on the sign of the coil current. The duty of coil A follows the cosine and coil
+//! B the sine of the electrical angle, so the rotor is held between full step positions in
+//! proportion to the two currents.
+//!
+//! A single [`SINE`] table of [`PHASES`] entries covers one electrical cycle (four full steps)
+//! at the finest resolution of 1/32 steps. Coarser resolutions walk the same table with a
+//! larger stride, and the cosine is read a quarter of a cycle ahead.
+//!
+//! The table is generated by the `const fn` [`sine_table`], so it costs nothing at run time
+//! and is checked on the host by the `bipolar` scenario of the simulator (see `sim/`).
+//!
+//! The H-bridges are driven open loop in voltage mode, so the coil current depends on the
+//! supply voltage and coil resistance. Use [`DualHBridge::with_amplitude`] to limit the
+//! current when the supply exceeds the motor's rated voltage.
+
+use embedded_hal::pwm::SetDutyCycle;
+
+use super::{
+    Direction, Error,
+    driver::{StepperDriver, Timing},
+};
+
+/// Finest supported microstep divisor.
+pub const MAX_MICROSTEPS: u16 = 32;
+
+/// Coarsest supported microstep divisor.
+pub const MIN_MICROSTEPS: u16 = 4;
+
+/// Entries in the phase table, covering one electrical cycle (four full steps) in 1/32 steps.
+pub const PHASES: usize = 4 * MAX_MICROSTEPS as usize;
+
+/// Table entry for a full coil current (Q15).
+pub const FULL_SCALE: i16 = i16::MAX;
+
+/// Sine of the electrical angle at each entry of the phase table (Q15).
+pub const SINE: [i16; PHASES] = sine_table();
+
+/// Sine over one cycle sampled at `N` evenly spaced angles, scaled to [`FULL_SCALE`] and
+/// rounded to the nearest integer.
+///
+/// Only the first quarter is evaluated, and mirrored into the others, so the table is exactly
+/// symmetric. `N` must be a non-zero multiple of four.
+pub const fn sine_table<const N: usize>() -> [i16; N] {
+    assert!(
+        N > 0 && N.is_multiple_of(4),
+        "sine table length must be a non-zero multiple of four"
+    );
+    let quarter = N / 4;
+    let mut table = [0; N];
+    let mut i = 0;
+    while i <= quarter {
+        let angle = core::f64::consts::FRAC_PI_2 * i as f64 / quarter as f64;
+        // non-negative over the first quarter, so rounding is adding a half and truncating
+        let value = (quarter_sine(angle) * FULL_SCALE as f64 + 0.5) as i16;
+        table[i] = value;
+        table[2 * quarter - i] = value;
+        table[(2 * quarter + i) % N] = -value;
+        table[(4 * quarter - i) % N] = -value;
+        i += 1;
+    }
+    table
+}
+
+/// Sine of `angle` (radians) from its Taylor series, for 0 ≤ `angle` ≤ π/2.
+const fn quarter_sine(angle: f64) -> f64 {
+    // terms up to angle^17 keep the error below 1e-13 over the first quarter
+    let square = angle * angle;
+    let mut term = angle;
+    let mut sum = angle;
+    let mut n = 1;
+    while n < 9 {
+        term = -term * square / ((2 * n) * (2 * n + 1)) as f64;
+        sum += term;
+        n += 1;
+    }
+    sum
+}
+
+/// Currents of coils A (cosine) and B (sine) at a position of the phase table (Q15).
+pub const fn coil_currents(phase: usize) -> [i16; 2] {
+    [SINE[(phase + PHASES / 4) % PHASES], SINE[phase % PHASES]]
+}
+
+/// Timing of the H-bridges, which switch the coils directly from their inputs.
+const TIMING: Timing = Timing {
+    min_pulse_width_ns: 0,
+    dir_setup_ns: 0,
+    wake_up_us: 0,
+};
+
+/// Bipolar stepper with each coil driven by an H-bridge from two PWM channels (IN1 and IN2).
+pub struct DualHBridge<P> {
+    /// IN1 and IN2 of the H-bridges driving coils A and B.
+    coils: [[P; 2]; 2],
+    microsteps: u16,
+    /// Position in the phase table.
+    phase: usize,
+    direction: Direction,
+    /// Peak coil duty (%).
+    amplitude_pct: u8,
+    energised: bool,
+}
+
+impl<P: SetDutyCycle> DualHBridge<P> {
+    /// Create a driver from the PWM channels connected to IN1 and IN2 of the H-bridge of each
+    /// coil, with the coils off and the finest microstep resolution selected.
+    pub fn new(coil_a: [P; 2], coil_b: [P; 2]) -> Self {
+        Self {
+            coils: [coil_a, coil_b],
+            microsteps: MAX_MICROSTEPS,
+            phase: 0,
+            direction: Direction::Forward,
+            amplitude_pct: 100,
+            energised: false,
+        }
+    }
+
+    /// Limit the peak coil duty to `amplitude_pct` (%).
+    pub fn with_amplitude(mut self, amplitude_pct: u8) -> Self {
+        self.amplitude_pct = amplitude_pct.min(100);
+        self
+    }
+
+    /// Position in the phase table, i.e. the electrical angle in 1/32 steps.
+    pub fn phase(&self) -> usize {
+        self.phase
+    }
+
+    /// Entries of the phase table per step at the selected resolution.
+    fn stride(&self) -> usize {
+        (MAX_MICROSTEPS / self.microsteps) as usize
+    }
+
+    /// Drive the coils with the currents for the current phase.
+    fn write_phase(&mut self) -> Result<(), Error> {
+        let amplitude_pct = self.amplitude_pct as u32;
+        for (inputs, current) in self.coils.iter_mut().zip(coil_currents(self.phase)) {
+            let full = inputs[0].max_duty_cycle() as u32 * amplitude_pct / 100;
+            let duty = (current.unsigned_abs() as u32 * full / FULL_SCALE as u32) as u16;
+            let [forward, reverse] = inputs;
+            // release the idle input first, so the bridge never brakes on a sign change
+            let (active, idle) = if current >= 0 {
+                (forward, reverse)
+            } else {
+                (reverse, forward)
+            };
+            idle.set_duty_cycle_fully_off()
+                .map_err(|_| Error::Peripheral)?;
+            active.set_duty_cycle(duty).map_err(|_| Error::Peripheral)?;
+        }
+        self.energised = true;
+        Ok(())
+    }
+
+    /// Let both coils coast.
+    fn release(&mut self) -> Result<(), Error> {
+        for input in self.coils.iter_mut().flatten() {
+            input
+                .set_duty_cycle_fully_off()
+                .map_err(|_| Error::Peripheral)?;
+        }
+        self.energised = false;
+        Ok(())
+    }
+}
+
+impl<P: SetDutyCycle> StepperDriver for DualHBridge<P> {
+    fn timing(&self) -> Timing {
+        TIMING
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        self.direction = direction;
+        Ok(())
+    }
+
+    /// Advance one microstep in the current direction on the rising edge.
+    fn set_step(&mut self, high: bool) -> Result<(), Error> {
+        if !high {
+            return Ok(());
+        }
+        let offset = match self.direction {
+            Direction::Forward => self.stride(),
+            Direction::Reverse => PHASES - self.stride(),
+        };
+        self.phase = (self.phase + offset) % PHASES;
+        self.write_phase()
+    }
+
+    fn microsteps(&self) -> u16 {
+        self.microsteps
+    }
+
+    /// Select a resolution from 1/4 to 1/32 steps.
+    ///
+    /// Switching to a coarser resolution moves the rotor back to the previous position of
+    /// that resolution.
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error> {
+        let range = MIN_MICROSTEPS..=MAX_MICROSTEPS;
+        if !microsteps.is_power_of_two() || !range.contains(&microsteps) {
+            return Err(Error::UnsupportedMicrosteps(microsteps));
+        }
+        self.microsteps = microsteps;
+        self.phase -= self.phase % self.stride();
+        if self.energised {
+            self.write_phase()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Drive the coils at the current phase, or let them coast.
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        if !enabled {
+            self.release()
+        } else if !self.energised {
+            self.write_phase()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Equivalent to disabling, since the H-bridges are only put to sleep by idling their
+    /// inputs.
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error> {
+        self.set_enabled(!asleep)
+    }
+
+    /// Let the coils coast and return to the start of the phase table.
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error> {
+        if reset {
+            self.release()?;
+            self.phase = 0;
+        }
+        Ok(())
+    }
+
+    /// Always false: fault conditions only disable the H-bridge outputs, with no fault pin.
+    fn is_faulted(&mut self) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
@@ -0,0 +1,390 @@
+//! Stepper controller with absolute position tracking
+//!
+//! Moves are planned with [`Stepper::move_to`] or [`Stepper::move_by`], which set the DIR pin
+//! immediately, and then executed with [`Stepper::run`] (for a whole motion profile) or
+//! [`Stepper::step`] (one step at a time, e.g. while watching a sensor). Forward steps
+//! increment the position and reverse steps decrement it.
+//!
+//! The driver is enabled and woken (waiting for its wake-up time) before the first step of a
+//! move, and may be disabled again once it has been idle for a timeout (see
+//! [`Stepper::with_idle_timeout`] and [`Stepper::pause`]), so the coils don't carry holding
+//! current between moves. A fault reported by the driver aborts the move with
+//! [`Error::Fault`], leaving the position at the last step taken, as does any other error
+//! from the step generator.
+//!
+//! Gear trains with backlash lose motion whenever DIR reverses. With
+//! [`Stepper::with_backlash`], the controller takes up the slack with extra steps at the start
+//! of each move after a reversal. These steps are not counted in the position, which stays
+//! the position of the load.
+
+use embassy_time::{Duration, Instant, Timer};
+
+use super::{
+    Direction, Error,
+    driver::{StepperDriver, Timing},
+    generator::{Interrupted, StepGenerator},
+};
+
+/// Controller for one stepper axis, tracking its signed position in (micro)steps.
+pub struct Stepper<D, G> {
+    driver: D,
+    generator: G,
+    position: i32,
+    target: i32,
+    /// DIR has changed since the last step, so the driver setup time must elapse first.
+    dir_setup_pending: bool,
+    /// Steps of lost motion when DIR reverses.
+    backlash: u32,
+    /// Steps still needed to take up the backlash in the current direction.
+    slack: u32,
+    /// The driver is enabled and awake.
+    powered: bool,
+    /// Time since which the driver has been idle.
+    idle_since: Instant,
+    /// Idle time after which the driver is disabled by [`Stepper::pause`].
+    idle_timeout: Option<Duration>,
+}
+
+impl<D: StepperDriver, G: StepGenerator> Stepper<D, G> {
+    /// Create a controller at position zero, with DIR matching the driver's current state.
+    ///
+    /// The driver is assumed to be disabled or asleep until the first step.
+    pub fn new(driver: D, generator: G) -> Self {
+        Self {
+            driver,
+            generator,
+            position: 0,
+            target: 0,
+            dir_setup_pending: false,
+            backlash: 0,
+            slack: 0,
+            powered: false,
+            idle_since: Instant::now(),
+            idle_timeout: None,
+        }
+    }
+
+    /// Compensate for `steps` steps of backlash, assuming it is initially taken up in the
+    /// current direction.
+    ///
+    /// The position then tracks the load rather than the motor, so after a reversal it differs
+    /// from the position measured by an encoder on the motor shaft.
+    pub fn with_backlash(mut self, steps: u32) -> Self {
+        self.backlash = steps;
+        self.slack = 0;
+        self
+    }
+
+    /// Disable and put the driver to sleep once it has been idle for `timeout`.
+    pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Current position (steps).
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Redefine the current position without moving, cancelling any planned move.
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+        self.target = position;
+    }
+
+    /// Target position of the planned move (steps).
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+
+    /// Number of steps remaining to reach the target.
+    pub fn remaining_steps(&self) -> u32 {
+        self.target.abs_diff(self.position)
+    }
+
+    /// Whether there are steps remaining to reach the target.
+    pub fn is_moving(&self) -> bool {
+        self.target != self.position
+    }
+
+    /// Direction of the planned move, or of the last move when stationary.
+    pub fn direction(&self) -> Direction {
+        self.driver.direction()
+    }
+
+    /// Shared access to the underlying driver.
+    pub fn driver(&self) -> &D {
+        &self.driver
+    }
+
+    /// Exclusive access to the underlying driver.
+    ///
+    /// Changing DIR through this reference bypasses the controller's setup time handling.
+    pub fn driver_mut(&mut self) -> &mut D {
+        &mut self.driver
+    }
+
+    /// Plan a move to an absolute position, setting DIR if required.
+    pub fn move_to(&mut self, target: i32) -> Result<(), Error> {
+        let direction = match target.cmp(&self.position) {
+            core::cmp::Ordering::Greater => Direction::Forward,
+            core::cmp::Ordering::Less => Direction::Reverse,
+            core::cmp::Ordering::Equal => self.driver.direction(),
+        };
+        if direction != self.driver.direction() {
+            self.driver.set_direction(direction)?;
+            self.dir_setup_pending = true;
+            // slack already taken up since the last reversal must be taken up again
+            self.slack = self.backlash - self.slack;
+        }
+        self.target = target;
+        Ok(())
+    }
+
+    /// Plan a move relative to the current position.
+    pub fn move_by(&mut self, delta: i32) -> Result<(), Error> {
+        self.move_to(self.position.saturating_add(delta))
+    }
+
+    /// Abandon the remainder of the planned move.
+    pub fn stop(&mut self) {
+        self.target = self.position;
+    }
+
+    /// Backlash compensated on each reversal (steps).
+    pub fn backlash(&self) -> u32 {
+        self.backlash
+    }
+
+    /// Compensation steps still to be taken before the next move moves the load.
+    pub fn slack(&self) -> u32 {
+        self.slack
+    }
+
+    /// Whether the driver is currently enabled and awake.
+    pub fn is_powered(&self) -> bool {
+        self.powered
+    }
+
+    /// Release reset, wake and enable the driver, then wait for its wake-up time.
+    ///
+    /// Does nothing if the driver is already powered. Called automatically before stepping.
+    pub async fn power_up(&mut self) -> Result<(), Error> {
+        if !self.powered {
+            self.driver.set_reset(false)?;
+            self.driver.set_sleep(false)?;
+            self.driver.set_enabled(true)?;
+            let wake_up_us = self.driver.timing().wake_up_us;
+            Timer::after(Duration::from_micros(wake_up_us.into())).await;
+            self.powered = true;
+            self.idle_since = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Disable the driver outputs and put it to sleep, releasing holding torque.
+    pub fn power_down(&mut self) -> Result<(), Error> {
+        self.driver.set_enabled(false)?;
+        self.driver.set_sleep(true)?;
+        self.powered = false;
+        Ok(())
+    }
+
+    /// Pulse the driver reset (for the minimum pulse width) to clear a latched fault, leaving
+    /// the driver powered down.
+    ///
+    /// The driver's microstep position returns to its home state, but the controller position
+    /// is unchanged.
+    pub async fn reset_driver(&mut self) -> Result<(), Error> {
+        self.power_down()?;
+        self.driver.set_reset(true)?;
+        let pulse_us = self.driver.timing().min_pulse_width_ns.div_ceil(1_000);
+        Timer::after(Duration::from_micros(pulse_us.into())).await;
+        self.driver.set_reset(false)
+    }
+
+    /// Wait without stepping for `duration`, powering down the driver if it reaches the idle
+    /// timeout in the meantime.
+    pub async fn pause(&mut self, duration: Duration) -> Result<(), Error> {
+        self.pause_until(Instant::now() + duration).await
+    }
+
+    /// Wait without stepping until `deadline`, powering down the driver if it reaches the
+    /// idle timeout in the meantime.
+    pub async fn pause_until(&mut self, deadline: Instant) -> Result<(), Error> {
+        if let (true, Some(timeout)) = (self.powered, self.idle_timeout) {
+            let power_down_at = self.idle_since + timeout;
+            if power_down_at < deadline {
+                Timer::at(power_down_at).await;
+                self.power_down()?;
+            }
+        }
+        Timer::at(deadline).await;
+        Ok(())
+    }
+
+    /// Take one step towards the target with the given period (µs), preceded by any backlash
+    /// compensation steps at the same period.
+    ///
+    /// Returns `false` without stepping if the target has already been reached.
+    pub async fn step(&mut self, period_us: u32) -> Result<bool, Error> {
+        if !self.is_moving() {
+            return Ok(false);
+        }
+        self.prepare().await?;
+        while self.slack > 0 {
+            self.pulse(period_us).await?;
+            self.slack -= 1;
+        }
+        self.pulse(period_us).await?;
+        self.advance(1)?;
+        Ok(true)
+    }
+
+    /// Step towards the target using the given periods (µs), e.g. from a motion profile.
+    ///
+    /// Any backlash compensation steps are taken first, at the first period of the profile.
+    /// Stops when either the target is reached or the periods are exhausted, returning the
+    /// number of steps taken (excluding compensation).
+    pub async fn run(&mut self, periods: impl IntoIterator<Item = u32>) -> Result<u32, Error> {
+        if !self.is_moving() {
+            return Ok(0);
+        }
+        self.prepare().await?;
+        let mut periods = periods
+            .into_iter()
+            .take(self.remaining_steps() as usize)
+            .peekable();
+        let Some(&first_period_us) = periods.peek() else {
+            return Ok(0);
+        };
+        let compensation = core::iter::repeat_n(first_period_us, self.slack as usize);
+        let mut monitor = FaultMonitor {
+            driver: &mut self.driver,
+        };
+        let result = self
+            .generator
+            .steps(&mut monitor, compensation.chain(periods))
+            .await;
+        self.idle_since = Instant::now();
+        match result {
+            Ok(steps) => {
+                let steps = self.take_up_slack(steps);
+                self.advance(steps)?;
+                // generators stepping in hardware are only checked once they finish
+                self.check_fault()?;
+                Ok(steps)
+            }
+            Err(Interrupted { steps, error }) => {
+                // keep the steps taken before the error
+                let steps = self.take_up_slack(steps);
+                self.advance(steps)?;
+                self.stop();
+                Err(error)
+            }
+        }
+    }
+
+    /// Emit one STEP pulse, without updating the position.
+    async fn pulse(&mut self, period_us: u32) -> Result<(), Error> {
+        let result = self.generator.step(&mut self.driver, period_us).await;
+        self.idle_since = Instant::now();
+        result
+    }
+
+    /// Attribute steps taken to the remaining slack first, returning the steps which moved
+    /// the load.
+    fn take_up_slack(&mut self, steps: u32) -> u32 {
+        let slack = steps.min(self.slack);
+        self.slack -= slack;
+        steps - slack
+    }
+
+    /// Power up the driver, check for faults and wait for the DIR setup time if DIR has
+    /// changed since the last step.
+    async fn prepare(&mut self) -> Result<(), Error> {
+        self.power_up().await?;
+        self.check_fault()?;
+        if self.dir_setup_pending {
+            let setup_us = self.driver.timing().dir_setup_ns.div_ceil(1_000);
+            Timer::after(Duration::from_micros(setup_us.into())).await;
+            self.dir_setup_pending = false;
+        }
+        Ok(())
+    }
+
+    /// Abandon the planned move if the driver is reporting a fault.
+    fn check_fault(&mut self) -> Result<(), Error> {
+        if self.driver.is_faulted()? {
+            self.stop();
+            return Err(Error::Fault);
+        }
+        Ok(())
+    }
+
+    /// Record steps taken in the current direction, abandoning the planned move if the
+    /// position would overflow.
+    fn advance(&mut self, steps: u32) -> Result<(), Error> {
+        let position = match self.driver.direction() {
+            Direction::Forward => self.position.checked_add_unsigned(steps),
+            Direction::Reverse => self.position.checked_sub_unsigned(steps),
+        };
+        let Some(position) = position else {
+            self.stop();
+            return Err(Error::PositionOverflow);
+        };
+        self.position = position;
+        Ok(())
+    }
+}
+
+/// Driver wrapper checking for faults before each step, so a fault part way through a move
+/// aborts it before the next step is taken.
+struct FaultMonitor<'a, D> {
+    driver: &'a mut D,
+}
+
+impl<D: StepperDriver> StepperDriver for FaultMonitor<'_, D> {
+    fn timing(&self) -> Timing {
+        self.driver.timing()
+    }
+
+    fn direction(&self) -> Direction {
+        self.driver.direction()
+    }
+
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        self.driver.set_direction(direction)
+    }
+
+    fn set_step(&mut self, high: bool) -> Result<(), Error> {
+        if high && self.driver.is_faulted()? {
+            return Err(Error::Fault);
+        }
+        self.driver.set_step(high)
+    }
+
+    fn microsteps(&self) -> u16 {
+        self.driver.microsteps()
+    }
+
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error> {
+        self.driver.set_microsteps(microsteps)
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.driver.set_enabled(enabled)
+    }
+
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error> {
+        self.driver.set_sleep(asleep)
+    }
+
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error> {
+        self.driver.set_reset(reset)
+    }
+
+    fn is_faulted(&mut self) -> Result<bool, Error> {
+        self.driver.is_faulted()
+    }
+}
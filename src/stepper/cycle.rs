@@ -0,0 +1,95 @@
+//! Periodic rotation cycle of the `stepper_async` demo
+//!
+//! The parameters and event loop are kept here, rather than in the demo itself, so that the
+//! host simulator runs exactly the same sequence as the ESP32C3. Each cycle steps one rotation
+//! through [`PROFILE`] with [`run_timed`], then waits out the rest of
+//! [`TOTAL_CYCLE_TIME_US`].
+
+use core::ops::ControlFlow;
+
+use embassy_time::{Duration, Instant, Ticker};
+
+use super::{
+    Error,
+    driver::{Drv8825, StepperDriver},
+    generator::run_timed,
+    units::{Microsteps, Rpm, StepInterval, Steps},
+};
+use crate::{motion::trapezoidal::TrapezoidalProfile, timing::JitterStats};
+
+// Inputs
+pub const MOTOR_STEPS_PER_REV: u32 = 200;
+pub const MICRO_STEP_MODE: Microsteps = Microsteps::new(2).checked_for::<Drv8825>();
+pub const RPM: Rpm = Rpm::new(320);
+pub const ACCELERATION: u32 = 4_000; // steps/s²
+pub const NUM_REVS: u32 = 16;
+pub const PAUSE_SEC: u64 = 2;
+
+// Timing statistics: deviations within ±HISTOGRAM_BINS / 2 * HISTOGRAM_BIN_WIDTH_US are binned
+pub const HISTOGRAM_BINS: usize = 8;
+pub const HISTOGRAM_BIN_WIDTH_US: u32 = 5;
+pub const OVERRUN_US: u32 = 50;
+
+// Calculated values (checked at compile time)
+pub const STEPS_PER_REV: Steps = MICRO_STEP_MODE.steps_per_rev(MOTOR_STEPS_PER_REV);
+pub const NUM_STEPS: Steps = STEPS_PER_REV.times(NUM_REVS);
+pub const MAX_VELOCITY: u32 = RPM.steps_per_sec(STEPS_PER_REV);
+pub const CRUISE_INTERVAL: StepInterval =
+    StepInterval::from_velocity(MAX_VELOCITY).checked_for::<Drv8825>();
+pub const PROFILE: TrapezoidalProfile =
+    TrapezoidalProfile::new(MAX_VELOCITY, ACCELERATION, NUM_STEPS.get());
+pub const RUN_TIME_US: u64 = PROFILE.duration_us();
+pub const TOTAL_CYCLE_TIME_US: u64 = match RUN_TIME_US.checked_add(PAUSE_SEC * 1_000_000) {
+    Some(total) => total,
+    None => panic!("cycle time overflows u64"),
+};
+
+/// Timing statistics of one rotation, as deviations of each step period from the profile.
+pub type RotationStats = JitterStats<HISTOGRAM_BINS>;
+
+/// A completed rotation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Rotation {
+    /// Number of the rotation, counting from zero (wrapping).
+    pub index: u32,
+    /// Steps taken.
+    pub steps: u32,
+    /// Time of the first step.
+    pub start: Instant,
+    /// Time the last step ended.
+    pub end: Instant,
+}
+
+/// Rotate once per cycle, passing each completed rotation and its timing statistics to
+/// `report`.
+///
+/// Runs until `report` breaks or stepping fails. Cycles are timed from the call, so a slow
+/// `report` shortens the following pause rather than delaying the next rotation.
+pub async fn run<D: StepperDriver>(
+    driver: &mut D,
+    mut report: impl FnMut(&Rotation, &RotationStats) -> ControlFlow<()>,
+) -> Result<(), Error> {
+    let mut stats = RotationStats::new(HISTOGRAM_BIN_WIDTH_US, OVERRUN_US);
+    let mut cycle_ticker = Ticker::every(Duration::from_micros(TOTAL_CYCLE_TIME_US));
+    let mut index: u32 = 0;
+    loop {
+        stats.reset();
+
+        // perform 1 rotation
+        let start = Instant::now();
+        let steps = run_timed(driver, PROFILE.delays(), &mut stats).await?;
+        let rotation = Rotation {
+            index,
+            steps,
+            start,
+            end: Instant::now(),
+        };
+        if report(&rotation, &stats).is_break() {
+            return Ok(());
+        }
+        index = index.wrapping_add(1);
+
+        // wait until "pause" period ends
+        cycle_ticker.next().await;
+    }
+}
@@ -0,0 +1,333 @@
+//! Generic step/dir driver with DRV8825, A4988 and TMC2209 backends
+//!
+//! Chip specific behaviour (timing requirements and microstep mode pin tables) is described by
+//! the [`DriverChip`] marker types, while [`StepDir`] owns the pins and implements
+//! [`StepperDriver`] for any of them.
+
+use core::{convert::Infallible, marker::PhantomData};
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+
+use super::{Direction, Error};
+
+/// Timing requirements of a driver chip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Timing {
+    /// Minimum duration of both the high and low phases of a STEP pulse (ns).
+    pub min_pulse_width_ns: u32,
+    /// Minimum time between a DIR change and the next STEP rising edge (ns).
+    pub dir_setup_ns: u32,
+    /// Time required after leaving sleep before STEP pulses are accepted (µs).
+    pub wake_up_us: u32,
+}
+
+/// Static description of a step/dir driver chip.
+pub trait DriverChip {
+    /// Timing requirements of the chip.
+    const TIMING: Timing;
+    /// Supported microstep divisors, with the levels required on mode pins M0, M1 and M2.
+    const MICROSTEPS: &'static [(u16, [bool; 3])];
+    /// Microstep divisor selected when all mode pins are low.
+    const DEFAULT_MICROSTEPS: u16;
+    /// Whether the fault input reads high while a fault is present.
+    const FAULT_ACTIVE_HIGH: bool = false;
+}
+
+/// TI DRV8825 (M0-M2 mode pins, active low nFAULT).
+pub struct Drv8825;
+
+impl DriverChip for Drv8825 {
+    const TIMING: Timing = Timing {
+        min_pulse_width_ns: 1_900,
+        dir_setup_ns: 650,
+        wake_up_us: 1_700,
+    };
+    const MICROSTEPS: &'static [(u16, [bool; 3])] = &[
+        (1, [false, false, false]),
+        (2, [true, false, false]),
+        (4, [false, true, false]),
+        (8, [true, true, false]),
+        (16, [false, false, true]),
+        (32, [true, false, true]),
+    ];
+    const DEFAULT_MICROSTEPS: u16 = 1;
+}
+
+/// Allegro A4988 (MS1-MS3 mode pins, no fault output).
+pub struct A4988;
+
+impl DriverChip for A4988 {
+    const TIMING: Timing = Timing {
+        min_pulse_width_ns: 1_000,
+        dir_setup_ns: 200,
+        wake_up_us: 1_000,
+    };
+    const MICROSTEPS: &'static [(u16, [bool; 3])] = &[
+        (1, [false, false, false]),
+        (2, [true, false, false]),
+        (4, [false, true, false]),
+        (8, [true, true, false]),
+        (16, [true, true, true]),
+    ];
+    const DEFAULT_MICROSTEPS: u16 = 1;
+}
+
+/// Trinamic TMC2209 in standalone step/dir mode (MS1-MS2 mode pins, active high DIAG).
+pub struct Tmc2209;
+
+impl DriverChip for Tmc2209 {
+    const TIMING: Timing = Timing {
+        min_pulse_width_ns: 100,
+        dir_setup_ns: 20,
+        wake_up_us: 0,
+    };
+    const MICROSTEPS: &'static [(u16, [bool; 3])] = &[
+        (8, [false, false, false]),
+        (16, [true, true, false]),
+        (32, [true, false, false]),
+        (64, [false, true, false]),
+    ];
+    const DEFAULT_MICROSTEPS: u16 = 8;
+    const FAULT_ACTIVE_HIGH: bool = true;
+}
+
+/// Interface to a step/dir stepper driver.
+///
+/// Optional pins which were not connected are ignored by the enable, sleep and reset
+/// methods, and never report a fault.
+pub trait StepperDriver {
+    /// Timing requirements of the driver.
+    fn timing(&self) -> Timing;
+
+    /// Current state of the DIR pin.
+    fn direction(&self) -> Direction;
+
+    /// Set the DIR pin.
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error>;
+
+    /// Set the STEP pin. Steps are taken on the rising edge.
+    fn set_step(&mut self, high: bool) -> Result<(), Error>;
+
+    /// Currently selected microstep divisor.
+    fn microsteps(&self) -> u16;
+
+    /// Select a microstep divisor via the mode pins.
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error>;
+
+    /// Enable or disable the driver outputs.
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error>;
+
+    /// Put the driver to sleep or wake it up.
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error>;
+
+    /// Assert or release the driver reset.
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error>;
+
+    /// Whether the driver is currently reporting a fault.
+    fn is_faulted(&mut self) -> Result<bool, Error>;
+}
+
+/// Placeholder for optional pins which are not connected.
+pub struct NoPin;
+
+impl ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for NoPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl InputPin for NoPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+/// Step/dir driver for the chip `C`.
+///
+/// All control outputs share the pin type `O`, except STEP (`S`) which may be replaced by
+/// [`NoPin`] when step pulses are produced by a peripheral. The optional fault input has
+/// type `I`.
+pub struct StepDir<C, O, S = O, I = NoPin> {
+    dir: O,
+    step: S,
+    enable: Option<O>,
+    sleep: Option<O>,
+    reset: Option<O>,
+    mode: [Option<O>; 3],
+    fault: Option<I>,
+    direction: Direction,
+    microsteps: u16,
+    _chip: PhantomData<C>,
+}
+
+impl<C: DriverChip, O: OutputPin, S: OutputPin> StepDir<C, O, S> {
+    /// Create a driver from its DIR and STEP pins.
+    ///
+    /// DIR is assumed to be high (forward) initially, and the microstep divisor is assumed to
+    /// be the chip default until mode pins are provided.
+    pub fn new(dir: O, step: S) -> Self {
+        Self {
+            dir,
+            step,
+            enable: None,
+            sleep: None,
+            reset: None,
+            mode: [None, None, None],
+            fault: None,
+            direction: Direction::Forward,
+            microsteps: C::DEFAULT_MICROSTEPS,
+            _chip: PhantomData,
+        }
+    }
+}
+
+impl<C: DriverChip, O: OutputPin, S: OutputPin, I: InputPin> StepDir<C, O, S, I> {
+    /// Add an active low enable output.
+    pub fn with_enable(mut self, pin: O) -> Self {
+        self.enable = Some(pin);
+        self
+    }
+
+    /// Add an active low sleep output.
+    pub fn with_sleep(mut self, pin: O) -> Self {
+        self.sleep = Some(pin);
+        self
+    }
+
+    /// Add an active low reset output.
+    pub fn with_reset(mut self, pin: O) -> Self {
+        self.reset = Some(pin);
+        self
+    }
+
+    /// Add microstep mode outputs M0, M1 and M2. Missing pins are assumed to be tied low.
+    pub fn with_mode_pins(mut self, pins: [Option<O>; 3]) -> Self {
+        self.mode = pins;
+        self
+    }
+
+    /// Declare the microstep divisor selected by hardwired mode pins (e.g. jumpers).
+    ///
+    /// Fails with [`Error::UnsupportedMicrosteps`] if the chip has no mode with this divisor.
+    pub fn with_fixed_microsteps(mut self, microsteps: u16) -> Result<Self, Error> {
+        if !C::MICROSTEPS
+            .iter()
+            .any(|(divisor, _)| *divisor == microsteps)
+        {
+            return Err(Error::UnsupportedMicrosteps(microsteps));
+        }
+        self.microsteps = microsteps;
+        Ok(self)
+    }
+
+    /// Add a fault input, with polarity determined by the chip.
+    pub fn with_fault<F: InputPin>(self, pin: F) -> StepDir<C, O, S, F> {
+        StepDir {
+            dir: self.dir,
+            step: self.step,
+            enable: self.enable,
+            sleep: self.sleep,
+            reset: self.reset,
+            mode: self.mode,
+            fault: Some(pin),
+            direction: self.direction,
+            microsteps: self.microsteps,
+            _chip: PhantomData,
+        }
+    }
+}
+
+impl<C: DriverChip, O: OutputPin, S: OutputPin, I: InputPin> StepperDriver for StepDir<C, O, S, I> {
+    fn timing(&self) -> Timing {
+        C::TIMING
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        set_level(&mut self.dir, direction == Direction::Forward)?;
+        self.direction = direction;
+        Ok(())
+    }
+
+    fn set_step(&mut self, high: bool) -> Result<(), Error> {
+        set_level(&mut self.step, high)
+    }
+
+    fn microsteps(&self) -> u16 {
+        self.microsteps
+    }
+
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error> {
+        let (_, levels) = C::MICROSTEPS
+            .iter()
+            .find(|(divisor, _)| *divisor == microsteps)
+            .ok_or(Error::UnsupportedMicrosteps(microsteps))?;
+
+        // check before writing anything, so a failure leaves the mode pins untouched
+        let unreachable = self
+            .mode
+            .iter()
+            .zip(levels)
+            .any(|(pin, high)| pin.is_none() && *high);
+        if unreachable {
+            return Err(Error::MissingPin);
+        }
+
+        for (pin, high) in self.mode.iter_mut().zip(levels) {
+            if let Some(pin) = pin {
+                set_level(pin, *high)?;
+            }
+        }
+        self.microsteps = microsteps;
+        Ok(())
+    }
+
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        match &mut self.enable {
+            Some(pin) => set_level(pin, !enabled),
+            None => Ok(()),
+        }
+    }
+
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error> {
+        match &mut self.sleep {
+            Some(pin) => set_level(pin, !asleep),
+            None => Ok(()),
+        }
+    }
+
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error> {
+        match &mut self.reset {
+            Some(pin) => set_level(pin, !reset),
+            None => Ok(()),
+        }
+    }
+
+    fn is_faulted(&mut self) -> Result<bool, Error> {
+        match &mut self.fault {
+            Some(pin) => Ok(pin.is_high().map_err(|_| Error::Pin)? == C::FAULT_ACTIVE_HIGH),
+            None => Ok(false),
+        }
+    }
+}
+
+/// Drive an output pin to the given level.
+pub(crate) fn set_level<P: OutputPin>(pin: &mut P, high: bool) -> Result<(), Error> {
+    let result = if high { pin.set_high() } else { pin.set_low() };
+    result.map_err(|_| Error::Pin)
+}
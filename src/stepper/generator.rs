@@ -0,0 +1,235 @@
+//! Step pulse generation
+//!
+//! A [`StepGenerator`] turns step periods (e.g. from a motion profile) into STEP pulses.
+//! [`SoftwareStepGenerator`] toggles the STEP pin from an embassy timer, while the RMT backed
+//! generator in [`super::rmt`] produces pulses in hardware from the codes built by
+//! [`RmtEncoder`]. Each pulse is high for the first half of its period, except that the RMT
+//! encoder caps the high phase at [`MAX_CODE_TICKS`] so that it always fits in one code.
+//!
+//! [`run_timed`] steps a driver directly through a profile while recording step timing, as
+//! used by the `stepper_async` demo and its host simulator in `sim/`.
+
+use embassy_time::{Duration, Instant, Timer};
+
+use super::{Error, driver::StepperDriver};
+use crate::timing::JitterStats;
+
+/// Source of STEP pulses for a [`StepperDriver`].
+#[allow(async_fn_in_trait)]
+pub trait StepGenerator {
+    /// Emit one step with the given period (µs), returning once the period has elapsed.
+    async fn step<D: StepperDriver>(&mut self, driver: &mut D, period_us: u32)
+    -> Result<(), Error>;
+
+    /// Emit one step per period (µs), returning the number of steps taken.
+    ///
+    /// On error, the steps taken before it are returned along with the error.
+    async fn steps<D: StepperDriver>(
+        &mut self,
+        driver: &mut D,
+        periods: impl IntoIterator<Item = u32>,
+    ) -> Result<u32, Interrupted> {
+        let mut count = 0;
+        for period_us in periods {
+            self.step(driver, period_us)
+                .await
+                .map_err(|error| Interrupted {
+                    steps: count,
+                    error,
+                })?;
+            count += 1;
+        }
+        Ok(count)
+    }
+}
+
+/// Error interrupting a sequence of steps, with the number of steps taken before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Interrupted {
+    /// Steps taken before the error.
+    pub steps: u32,
+    /// Cause of the interruption.
+    pub error: Error,
+}
+
+impl From<Interrupted> for Error {
+    fn from(interrupted: Interrupted) -> Self {
+        interrupted.error
+    }
+}
+
+/// Generates step pulses by toggling the driver STEP pin from the embassy timer.
+///
+/// Consecutive steps are scheduled from the end of the previous step rather than from the
+/// time of the call, so late wakeups do not accumulate into drift.
+#[derive(Default)]
+pub struct SoftwareStepGenerator {
+    next_start: Option<Instant>,
+}
+
+impl SoftwareStepGenerator {
+    /// Create a generator with no steps scheduled.
+    pub const fn new() -> Self {
+        Self { next_start: None }
+    }
+}
+
+impl StepGenerator for SoftwareStepGenerator {
+    async fn step<D: StepperDriver>(
+        &mut self,
+        driver: &mut D,
+        period_us: u32,
+    ) -> Result<(), Error> {
+        let period = Duration::from_micros(period_us.into());
+        let high_time = Duration::from_micros((period_us / 2).into());
+
+        // continue the previous schedule unless the caller has fallen a full period behind
+        let now = Instant::now();
+        let start = match self.next_start {
+            Some(start) if start + period > now => start,
+            _ => now,
+        };
+
+        driver.set_step(true)?;
+        Timer::at(start + high_time).await;
+        driver.set_step(false)?;
+        Timer::at(start + period).await;
+
+        self.next_start = Some(start + period);
+        Ok(())
+    }
+}
+
+/// Step through `periods` (µs) on a fixed schedule, recording the time between consecutive
+/// rising edges against the expected period in `stats`.
+///
+/// Step deadlines are accumulated from the time of the call rather than measured from "now",
+/// so late wakeups don't stretch the profile (equivalent to a Ticker with a varying period).
+/// Returns the number of steps taken.
+pub async fn run_timed<D: StepperDriver, const BINS: usize>(
+    driver: &mut D,
+    periods: impl IntoIterator<Item = u32>,
+    stats: &mut JitterStats<BINS>,
+) -> Result<u32, Error> {
+    let mut deadline = Instant::now();
+    let mut previous: Option<(Instant, u32)> = None;
+    let mut count = 0;
+    for period_us in periods {
+        let high_time_us = period_us / 2;
+
+        driver.set_step(true)?;
+        let high_start_time = Instant::now();
+        if let Some((previous_start_time, previous_period_us)) = previous {
+            let actual_us = high_start_time
+                .duration_since(previous_start_time)
+                .as_micros();
+            stats.record(previous_period_us.into(), actual_us);
+        }
+        previous = Some((high_start_time, period_us));
+        deadline += Duration::from_micros(high_time_us.into());
+        Timer::at(deadline).await;
+
+        driver.set_step(false)?;
+        deadline += Duration::from_micros((period_us - high_time_us).into());
+        Timer::at(deadline).await;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Maximum length of one half of an RMT pulse code (ticks).
+pub const MAX_CODE_TICKS: u32 = 0x7fff;
+
+/// Build a pulse code in the RMT RAM format.
+///
+/// The first half occupies the low 16 bits (length in bits 0-14, level in bit 15) and the
+/// second half the high 16 bits. A zero length acts as an end marker.
+pub const fn pulse_code(level1: bool, ticks1: u16, level2: bool, ticks2: u16) -> u32 {
+    let low = (ticks1 as u32 & MAX_CODE_TICKS) | ((level1 as u32) << 15);
+    let high = (ticks2 as u32 & MAX_CODE_TICKS) | ((level2 as u32) << 15);
+    low | (high << 16)
+}
+
+/// Converts step periods (µs) into RMT pulse codes.
+///
+/// Long low phases are split across several codes, while high phases are capped at a single
+/// code segment. Codes can therefore be transmitted in batches split at any code, as the gap
+/// between batches only ever extends a low phase. The sequence is terminated by a dedicated
+/// end marker code (zero). The encoder runs on any target, so generated codes can be inspected
+/// on a host.
+pub struct RmtEncoder<I> {
+    periods: I,
+    ticks_per_us: u32,
+    /// Remaining (level, ticks) of the phase being encoded.
+    phase: Option<(bool, u64)>,
+    /// Ticks of the low phase following the current high phase.
+    pending_low: u64,
+    steps: u32,
+    finished: bool,
+}
+
+impl<I: Iterator<Item = u32>> RmtEncoder<I> {
+    /// Create an encoder for an RMT channel clocked at `ticks_per_us` ticks per microsecond.
+    pub fn new(periods: impl IntoIterator<IntoIter = I>, ticks_per_us: u32) -> Self {
+        Self {
+            periods: periods.into_iter(),
+            ticks_per_us,
+            phase: None,
+            pending_low: 0,
+            steps: 0,
+            finished: false,
+        }
+    }
+
+    /// Number of steps encoded so far.
+    pub fn steps(&self) -> u32 {
+        self.steps
+    }
+
+    /// Take the next (level, ticks) segment of at most [`MAX_CODE_TICKS`].
+    fn next_segment(&mut self) -> Option<(bool, u16)> {
+        if self.phase.is_none() {
+            if self.pending_low > 0 {
+                self.phase = Some((false, self.pending_low));
+                self.pending_low = 0;
+            } else {
+                let ticks = self.periods.next()? as u64 * self.ticks_per_us as u64;
+                let high = (ticks / 2).clamp(1, MAX_CODE_TICKS as u64);
+                self.phase = Some((true, high));
+                self.pending_low = ticks.saturating_sub(high).max(1);
+                self.steps += 1;
+            }
+        }
+
+        let (level, remaining) = self.phase?;
+        let ticks = remaining.min(MAX_CODE_TICKS as u64);
+        self.phase = (remaining > ticks).then_some((level, remaining - ticks));
+        Some((level, ticks as u16))
+    }
+}
+
+impl<I: Iterator<Item = u32>> Iterator for RmtEncoder<I> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let code = match (self.next_segment(), self.next_segment()) {
+            (Some((level1, ticks1)), Some((level2, ticks2))) => {
+                pulse_code(level1, ticks1, level2, ticks2)
+            }
+            (Some((level, ticks)), None) => {
+                // split the final segment across both halves, so that only the dedicated end
+                // marker code contains a zero length
+                let first = (ticks / 2).max(1);
+                pulse_code(level, first, level, (ticks - first).max(1))
+            }
+            _ => {
+                self.finished = true;
+                pulse_code(false, 0, false, 0)
+            }
+        };
+        Some(code)
+    }
+}
@@ -0,0 +1,147 @@
+//! Homing against a limit switch (e.g. a hall effect sensor)
+//!
+//! The axis first approaches the switch quickly, backs off until the switch releases, then
+//! re-approaches slowly so the switch is reached at a repeatable speed. The position at which
+//! the switch triggers during the slow approach becomes position zero.
+
+use embassy_time::{Duration, with_timeout};
+use embedded_hal::digital::InputPin;
+
+use super::{
+    Direction, Error, controller::Stepper, driver::StepperDriver, generator::StepGenerator,
+};
+
+/// Parameters of a homing sequence.
+#[derive(Clone, Copy, Debug, defmt::Format)]
+pub struct HomingConfig {
+    /// Direction of travel towards the switch.
+    pub direction: Direction,
+    /// Step period during the fast approach (µs).
+    pub fast_period_us: u32,
+    /// Step period during the back off and slow approach (µs).
+    pub slow_period_us: u32,
+    /// Maximum travel while searching for the switch (steps).
+    pub max_travel: u32,
+    /// Distance moved away from the switch after the fast approach (steps).
+    pub backoff_steps: u32,
+    /// Whether the switch input reads low while triggered (e.g. open drain hall sensors).
+    pub active_low: bool,
+    /// Maximum duration of the whole sequence.
+    pub timeout: Duration,
+}
+
+/// Errors raised while homing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum HomingError {
+    /// Driving the stepper failed.
+    Stepper(Error),
+    /// Reading the switch input failed.
+    Sensor,
+    /// The switch did not trigger within the maximum travel.
+    SensorNeverTriggered,
+    /// The switch was still triggered after backing off.
+    SensorStuck,
+    /// The sequence did not complete within the configured timeout.
+    Timeout,
+    /// The configuration cannot home the axis (zero back off distance).
+    InvalidConfig,
+}
+
+impl From<Error> for HomingError {
+    fn from(error: Error) -> Self {
+        Self::Stepper(error)
+    }
+}
+
+/// Home the axis against the switch, setting the stepper position to zero on success.
+///
+/// On timeout, the sequence is abandoned wherever it is: the planned move is stopped and STEP
+/// driven low, in case the timeout fell within a pulse.
+pub async fn home<D, G, S>(
+    stepper: &mut Stepper<D, G>,
+    switch: &mut S,
+    config: &HomingConfig,
+) -> Result<(), HomingError>
+where
+    D: StepperDriver,
+    G: StepGenerator,
+    S: InputPin,
+{
+    if config.backoff_steps == 0 {
+        return Err(HomingError::InvalidConfig);
+    }
+    match with_timeout(config.timeout, home_inner(stepper, switch, config)).await {
+        Ok(result) => result,
+        Err(_) => {
+            stepper.stop();
+            stepper.driver_mut().set_step(false)?;
+            Err(HomingError::Timeout)
+        }
+    }
+}
+
+async fn home_inner<D, G, S>(
+    stepper: &mut Stepper<D, G>,
+    switch: &mut S,
+    config: &HomingConfig,
+) -> Result<(), HomingError>
+where
+    D: StepperDriver,
+    G: StepGenerator,
+    S: InputPin,
+{
+    let toward = match config.direction {
+        Direction::Forward => 1,
+        Direction::Reverse => -1,
+    };
+    let max_travel = config.max_travel.min(i32::MAX as u32) as i32;
+    let backoff = config.backoff_steps.min(i32::MAX as u32) as i32;
+
+    // fast approach, unless already sitting on the switch
+    if !is_triggered(switch, config)? {
+        stepper.move_by(toward * max_travel)?;
+        approach(stepper, switch, config, config.fast_period_us).await?;
+    }
+
+    // back off until clear of the switch
+    stepper.move_by(-toward * backoff)?;
+    stepper
+        .run(core::iter::repeat(config.slow_period_us))
+        .await?;
+    if is_triggered(switch, config)? {
+        return Err(HomingError::SensorStuck);
+    }
+
+    // slow re-approach, allowing some margin beyond the back off distance
+    stepper.move_by(toward * backoff.saturating_mul(2))?;
+    approach(stepper, switch, config, config.slow_period_us).await?;
+    stepper.set_position(0);
+    Ok(())
+}
+
+/// Step through the planned move until the switch triggers.
+async fn approach<D, G, S>(
+    stepper: &mut Stepper<D, G>,
+    switch: &mut S,
+    config: &HomingConfig,
+    period_us: u32,
+) -> Result<(), HomingError>
+where
+    D: StepperDriver,
+    G: StepGenerator,
+    S: InputPin,
+{
+    while stepper.step(period_us).await? {
+        if is_triggered(switch, config)? {
+            stepper.stop();
+            return Ok(());
+        }
+    }
+    Err(HomingError::SensorNeverTriggered)
+}
+
+/// Whether the switch is currently triggered.
+fn is_triggered<S: InputPin>(switch: &mut S, config: &HomingConfig) -> Result<bool, HomingError> {
+    let high = switch.is_high().map_err(|_| HomingError::Sensor)?;
+    Ok(high != config.active_low)
+}
@@ -0,0 +1,132 @@
+//! Continuous velocity (jog) mode
+//!
+//! Other tasks set a signed target velocity on a shared [`Jog`] at any time, and the task
+//! owning the stepper follows it with [`Jog::run`]. Velocity changes are ramped at a constant
+//! acceleration, so reversing decelerates to rest before changing DIR and accelerating again.
+//!
+//! Each step updates the velocity by v² ± 2a (the change over one step at constant
+//! acceleration), so new targets take effect from the next step.
+
+use embassy_sync::{blocking_mutex::raw::RawMutex, signal::Signal};
+
+use super::{
+    Direction, Error, controller::Stepper, driver::StepperDriver, generator::StepGenerator,
+};
+use crate::motion::US_PER_SEC;
+
+/// Limits applied while jogging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct JogConfig {
+    /// Maximum velocity in either direction (steps/s). Targets are clamped to this.
+    pub max_velocity: u32,
+    /// Acceleration used for every velocity change (steps/s²).
+    pub acceleration: u32,
+}
+
+/// Target velocity shared between the tasks setting it and the task stepping the motor.
+pub struct Jog<M: RawMutex> {
+    target: Signal<M, i32>,
+}
+
+impl<M: RawMutex> Jog<M> {
+    /// Create a jog target at rest.
+    pub const fn new() -> Self {
+        Self {
+            target: Signal::new(),
+        }
+    }
+
+    /// Set the target velocity (steps/s), positive for forward.
+    pub fn set_velocity(&self, velocity: i32) {
+        self.target.signal(velocity);
+    }
+
+    /// Decelerate to rest.
+    pub fn stop(&self) {
+        self.set_velocity(0);
+    }
+
+    /// Wait for a non-zero target velocity, then follow the target until it returns to zero
+    /// and the stepper has decelerated to rest.
+    ///
+    /// Any move already planned on the stepper is abandoned. Jogging beyond the range of the
+    /// position type stops with [`Error::PositionOverflow`].
+    pub async fn run<D, G>(
+        &self,
+        stepper: &mut Stepper<D, G>,
+        config: &JogConfig,
+    ) -> Result<(), Error>
+    where
+        D: StepperDriver,
+        G: StepGenerator,
+    {
+        stepper.stop();
+        let clamp = |velocity: i32| {
+            let max = config.max_velocity.min(i32::MAX as u32) as i32;
+            velocity.clamp(-max, max)
+        };
+        let mut target = 0;
+        while target == 0 {
+            target = clamp(self.target.wait().await);
+        }
+
+        let ramp = 2 * u64::from(config.acceleration);
+        let start_speed = (ramp.isqrt() as u32).max(1);
+        let mut direction = stepper.direction();
+        let mut speed: u32 = 0;
+        loop {
+            if let Some(velocity) = self.target.try_take() {
+                target = clamp(velocity);
+            }
+            let target_direction = if target >= 0 {
+                Direction::Forward
+            } else {
+                Direction::Reverse
+            };
+            let target_speed = target.unsigned_abs();
+
+            // speed after one step of acceleration or deceleration, where speeds below that
+            // reached by the first step from rest are treated as rest
+            let speed_sq = u64::from(speed) * u64::from(speed);
+            let faster = (speed_sq + ramp).isqrt() as u32;
+            let slower = match speed_sq.saturating_sub(ramp).isqrt() as u32 {
+                slower if slower < start_speed => 0,
+                slower => slower,
+            };
+
+            speed = if speed == 0 {
+                if target_speed == 0 {
+                    return Ok(());
+                }
+                direction = target_direction;
+                start_speed.min(target_speed)
+            } else if direction != target_direction {
+                // decelerate to rest before reversing
+                slower
+            } else if target_speed > speed {
+                faster.min(target_speed)
+            } else {
+                slower.max(target_speed)
+            };
+            if speed == 0 {
+                continue;
+            }
+
+            let delta = match direction {
+                Direction::Forward => 1,
+                Direction::Reverse => -1,
+            };
+            // a saturated move would take no step, and the loop would spin without yielding
+            let next = stepper.position().checked_add(delta);
+            stepper.move_to(next.ok_or(Error::PositionOverflow)?)?;
+            let period_us = US_PER_SEC.div_ceil(speed.into()) as u32;
+            stepper.step(period_us).await?;
+        }
+    }
+}
+
+impl<M: RawMutex> Default for Jog<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
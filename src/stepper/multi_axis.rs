@@ -0,0 +1,145 @@
+//! Coordinated straight-line moves across two to four step/dir axes
+//!
+//! All axes share one step schedule: on each tick of a [`LinearInterpolator`], the STEP pins
+//! of the axes which step on that tick are raised together, so per-axis pulses stay aligned
+//! with the major axis motion profile.
+
+use embassy_time::{Duration, Instant, Timer};
+
+use super::{Direction, Error, driver::StepperDriver};
+use crate::motion::interpolation::LinearInterpolator;
+
+/// Group of `N` stepper axes moved in lockstep, tracking the position of each.
+pub struct MultiAxis<D, const N: usize> {
+    drivers: [D; N],
+    positions: [i32; N],
+    targets: [i32; N],
+    /// Longest DIR setup time of the axes whose DIR changed since the last step (ns).
+    dir_setup_ns: u32,
+}
+
+impl<D: StepperDriver, const N: usize> MultiAxis<D, N> {
+    /// Create a group from its drivers, with every axis at position zero.
+    pub fn new(drivers: [D; N]) -> Self {
+        const {
+            assert!(
+                N >= 2 && N <= 4,
+                "multi-axis groups support two to four axes"
+            )
+        };
+        Self {
+            drivers,
+            positions: [0; N],
+            targets: [0; N],
+            dir_setup_ns: 0,
+        }
+    }
+
+    /// Current position of each axis (steps).
+    pub fn positions(&self) -> [i32; N] {
+        self.positions
+    }
+
+    /// Redefine the current position of each axis without moving.
+    pub fn set_positions(&mut self, positions: [i32; N]) {
+        self.positions = positions;
+        self.targets = positions;
+    }
+
+    /// Exclusive access to the driver of one axis.
+    pub fn driver_mut(&mut self, axis: usize) -> &mut D {
+        &mut self.drivers[axis]
+    }
+
+    /// Enable or disable every axis.
+    pub fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        self.drivers
+            .iter_mut()
+            .try_for_each(|driver| driver.set_enabled(enabled))
+    }
+
+    /// Plan a straight-line move to the given absolute positions, setting DIR pins.
+    ///
+    /// Returns the number of steps on the major axis, which the motion profile passed to
+    /// [`Self::run`] should cover, or [`Error::PositionOverflow`] (without changing DIR) if
+    /// the distance to a target does not fit in the position type.
+    pub fn move_to(&mut self, targets: [i32; N]) -> Result<u32, Error> {
+        let major_steps = interpolator(self.positions, targets)?.major_steps();
+        for ((driver, position), target) in self.drivers.iter_mut().zip(self.positions).zip(targets)
+        {
+            let direction = match target.cmp(&position) {
+                core::cmp::Ordering::Greater => Direction::Forward,
+                core::cmp::Ordering::Less => Direction::Reverse,
+                core::cmp::Ordering::Equal => continue,
+            };
+            if direction != driver.direction() {
+                driver.set_direction(direction)?;
+                self.dir_setup_ns = self.dir_setup_ns.max(driver.timing().dir_setup_ns);
+            }
+        }
+        self.targets = targets;
+        Ok(major_steps)
+    }
+
+    /// Execute the planned move, taking one major axis step per period (µs).
+    ///
+    /// Stops early if the periods are exhausted, returning the number of ticks executed.
+    pub async fn run(&mut self, periods: impl IntoIterator<Item = u32>) -> Result<u32, Error> {
+        if self.dir_setup_ns > 0 {
+            let setup_us = self.dir_setup_ns.div_ceil(1_000);
+            Timer::after(Duration::from_micros(setup_us.into())).await;
+            self.dir_setup_ns = 0;
+        }
+
+        let mut deadline = Instant::now();
+        let mut ticks = 0;
+        for (stepping, period_us) in interpolator(self.positions, self.targets)?.zip(periods) {
+            let high_time_us = period_us / 2;
+
+            self.set_steps(&stepping, true)?;
+            deadline += Duration::from_micros(high_time_us.into());
+            Timer::at(deadline).await;
+
+            self.set_steps(&stepping, false)?;
+            deadline += Duration::from_micros((period_us - high_time_us).into());
+            Timer::at(deadline).await;
+
+            for ((position, driver), stepped) in
+                self.positions.iter_mut().zip(&self.drivers).zip(stepping)
+            {
+                if stepped {
+                    match driver.direction() {
+                        Direction::Forward => *position += 1,
+                        Direction::Reverse => *position -= 1,
+                    }
+                }
+            }
+            ticks += 1;
+        }
+        Ok(ticks)
+    }
+
+    /// Set the STEP pin of each axis which steps on this tick.
+    fn set_steps(&mut self, stepping: &[bool; N], high: bool) -> Result<(), Error> {
+        for (driver, stepping) in self.drivers.iter_mut().zip(stepping) {
+            if *stepping {
+                driver.set_step(high)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Interpolator for a move between two sets of positions.
+fn interpolator<const N: usize>(
+    positions: [i32; N],
+    targets: [i32; N],
+) -> Result<LinearInterpolator<N>, Error> {
+    let mut deltas = [0; N];
+    for ((delta, position), target) in deltas.iter_mut().zip(positions).zip(targets) {
+        *delta = target
+            .checked_sub(position)
+            .ok_or(Error::PositionOverflow)?;
+    }
+    Ok(LinearInterpolator::new(deltas))
+}
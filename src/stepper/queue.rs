@@ -0,0 +1,281 @@
+//! Queue of stepper moves shared between tasks
+//!
+//! Tasks on any executor submit moves to a [`MoveQueue`] and receive a [`Ticket`], which can
+//! be awaited for the outcome of the move. The task owning the stepper (typically on a high
+//! priority interrupt executor) executes queued moves in order with [`MoveQueue::run_next`].
+//!
+//! [`MoveQueue::cancel`] stops the active move and discards every queued move. The active
+//! move decelerates along its profile rather than stopping abruptly, so no steps are lost.
+
+use core::{cell::RefCell, future::poll_fn, task::Poll};
+
+use embassy_sync::{
+    blocking_mutex::{Mutex, raw::RawMutex},
+    channel::Channel,
+    waitqueue::MultiWakerRegistration,
+};
+
+use super::{
+    Direction, Error, controller::Stepper, driver::StepperDriver, generator::StepGenerator,
+};
+use crate::motion::trapezoidal::TrapezoidalProfile;
+
+/// Number of tasks which can await tickets at once without being woken spuriously.
+const MAX_WAITERS: usize = 4;
+
+/// A relative move requested from the stepper task.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct MoveRequest {
+    /// Distance to move (steps).
+    pub steps: u32,
+    /// Cruising velocity (steps/s).
+    pub velocity: u32,
+    /// Direction of travel.
+    pub direction: Direction,
+}
+
+/// How a queued move finished.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum MoveOutcome {
+    /// Every requested step was taken.
+    Completed,
+    /// The move was cancelled after taking the given number of steps.
+    Cancelled { steps: u32 },
+    /// Driving the stepper failed, after which the remainder of the move was abandoned.
+    Failed(Error),
+    /// The move finished, but its outcome was overwritten by later moves before it was
+    /// awaited.
+    Expired,
+}
+
+/// Handle to a submitted move, used to await its outcome.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Ticket(u32);
+
+/// A move waiting in the queue.
+#[derive(Clone, Copy)]
+struct QueuedMove {
+    id: u32,
+    request: MoveRequest,
+}
+
+struct State<const N: usize> {
+    /// Id of the next submitted move. Ids are allocated in queue order.
+    next_id: u32,
+    /// Moves with lower ids have finished, since moves finish in queue order.
+    finished_below: u32,
+    /// Moves with lower ids have been cancelled, unless they had already finished.
+    cancelled_below: u32,
+    /// Outcomes of the most recently finished moves, indexed by id modulo `N`.
+    outcomes: [Option<(u32, MoveOutcome)>; N],
+    waiters: MultiWakerRegistration<MAX_WAITERS>,
+}
+
+/// Bounded queue of up to `N` pending moves for one stepper, plus the move being executed.
+///
+/// `M` selects the mutex guarding the queue, e.g. `CriticalSectionRawMutex` when submitting
+/// tasks run on a different executor to the stepper task.
+pub struct MoveQueue<M: RawMutex, const N: usize> {
+    moves: Channel<M, QueuedMove, N>,
+    state: Mutex<M, RefCell<State<N>>>,
+}
+
+impl<M: RawMutex, const N: usize> MoveQueue<M, N> {
+    /// Create an empty queue.
+    pub const fn new() -> Self {
+        Self {
+            moves: Channel::new(),
+            state: Mutex::new(RefCell::new(State {
+                next_id: 0,
+                finished_below: 0,
+                cancelled_below: 0,
+                outcomes: [None; N],
+                waiters: MultiWakerRegistration::new(),
+            })),
+        }
+    }
+
+    /// Submit a move, waiting for space in the queue if it is full.
+    pub async fn submit(&self, request: MoveRequest) -> Ticket {
+        poll_fn(|cx| match self.try_submit(request) {
+            Some(ticket) => Poll::Ready(ticket),
+            None => {
+                // a competing submitter may take the space first, in which case try again
+                let _ = self.moves.poll_ready_to_send(cx);
+                if let Some(ticket) = self.try_submit(request) {
+                    return Poll::Ready(ticket);
+                }
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Submit a move if there is space in the queue.
+    pub fn try_submit(&self, request: MoveRequest) -> Option<Ticket> {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            let id = state.next_id;
+            self.moves.try_send(QueuedMove { id, request }).ok()?;
+            state.next_id = id.wrapping_add(1);
+            Some(Ticket(id))
+        })
+    }
+
+    /// Submit a move and wait for it to finish.
+    pub async fn execute(&self, request: MoveRequest) -> MoveOutcome {
+        let ticket = self.submit(request).await;
+        self.wait(ticket).await
+    }
+
+    /// Wait for a submitted move to finish.
+    pub async fn wait(&self, ticket: Ticket) -> MoveOutcome {
+        poll_fn(|cx| {
+            self.state.lock(|state| {
+                let mut state = state.borrow_mut();
+                if let Some((id, outcome)) = state.outcomes[ticket.0 as usize % N]
+                    && id == ticket.0
+                {
+                    return Poll::Ready(outcome);
+                }
+                if is_before(ticket.0, state.finished_below) {
+                    return Poll::Ready(MoveOutcome::Expired);
+                }
+                state.waiters.register(cx.waker());
+                Poll::Pending
+            })
+        })
+        .await
+    }
+
+    /// Cancel the active move and every move currently in the queue.
+    ///
+    /// Moves submitted afterwards are unaffected.
+    pub fn cancel(&self) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.cancelled_below = state.next_id;
+        });
+    }
+
+    /// Number of moves waiting in the queue, excluding the active move.
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    /// Whether there are no moves waiting in the queue.
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+
+    /// Wait for the next move and execute it on the stepper with a trapezoidal profile.
+    ///
+    /// Any move already planned on the stepper is replaced. The outcome is returned as well
+    /// as being passed to the submitter.
+    pub async fn run_next<D, G>(
+        &self,
+        stepper: &mut Stepper<D, G>,
+        acceleration: u32,
+    ) -> MoveOutcome
+    where
+        D: StepperDriver,
+        G: StepGenerator,
+    {
+        let queued = self.moves.receive().await;
+        let outcome = if self.is_cancelled(queued.id) {
+            MoveOutcome::Cancelled { steps: 0 }
+        } else {
+            self.run(stepper, queued, acceleration).await
+        };
+        self.finish(queued.id, outcome);
+        outcome
+    }
+
+    /// Execute a move, checking for cancellation before every step.
+    ///
+    /// Moves at zero velocity, or taking the position beyond the range of the position type,
+    /// fail without stepping.
+    async fn run<D, G>(
+        &self,
+        stepper: &mut Stepper<D, G>,
+        queued: QueuedMove,
+        acceleration: u32,
+    ) -> MoveOutcome
+    where
+        D: StepperDriver,
+        G: StepGenerator,
+    {
+        let request = queued.request;
+        if request.velocity == 0 {
+            return MoveOutcome::Failed(Error::ZeroVelocity);
+        }
+        let position = stepper.position();
+        let target = i32::try_from(request.steps)
+            .ok()
+            .and_then(|steps| match request.direction {
+                Direction::Forward => position.checked_add(steps),
+                Direction::Reverse => position.checked_sub(steps),
+            });
+        let Some(target) = target else {
+            return MoveOutcome::Failed(Error::PositionOverflow);
+        };
+        if let Err(e) = stepper.move_to(target) {
+            return MoveOutcome::Failed(e);
+        }
+
+        let steps = request.steps;
+        let profile = TrapezoidalProfile::new(request.velocity, acceleration, steps);
+        let mut index = 0;
+        let mut taken = 0;
+        let mut stopping = false;
+        while let Some(period_us) = profile.period_us(index) {
+            if !stopping && self.is_cancelled(queued.id) {
+                // the deceleration ramp from the current velocity is the tail of the profile
+                stopping = true;
+                let ramp_steps = index.min(profile.accel_steps());
+                index = index.max(steps - ramp_steps);
+                continue;
+            }
+            if let Err(e) = stepper.step(period_us).await {
+                stepper.stop();
+                return MoveOutcome::Failed(e);
+            }
+            index += 1;
+            taken += 1;
+        }
+
+        stepper.stop();
+        if stopping {
+            MoveOutcome::Cancelled { steps: taken }
+        } else {
+            MoveOutcome::Completed
+        }
+    }
+
+    /// Whether the move with the given id has been cancelled.
+    fn is_cancelled(&self, id: u32) -> bool {
+        self.state
+            .lock(|state| is_before(id, state.borrow().cancelled_below))
+    }
+
+    /// Record the outcome of a move and wake any tasks waiting for it.
+    fn finish(&self, id: u32, outcome: MoveOutcome) {
+        self.state.lock(|state| {
+            let mut state = state.borrow_mut();
+            state.outcomes[id as usize % N] = Some((id, outcome));
+            state.finished_below = id.wrapping_add(1);
+            state.waiters.wake();
+        });
+    }
+}
+
+impl<M: RawMutex, const N: usize> Default for MoveQueue<M, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether id `a` was allocated before id `b`, allowing for wrap around.
+fn is_before(a: u32, b: u32) -> bool {
+    (b.wrapping_sub(a) as i32) > 0
+}
@@ -0,0 +1,95 @@
+//! Hardware step pulse generation with the ESP32C3 RMT peripheral
+//!
+//! Pulse codes are built by [`RmtEncoder`] and streamed in batches which fit within a single
+//! channel's RAM, so step periods of any length are split across as many codes as they need.
+//! The encoder keeps each high phase within one code, so the short gap while the next batch
+//! is loaded only extends the low phase of a step.
+
+use core::iter;
+
+use esp_hal::rmt::TxChannelAsync;
+
+use super::{
+    Error,
+    driver::StepperDriver,
+    generator::{Interrupted, RmtEncoder, StepGenerator},
+};
+
+/// Number of pulse codes held by one RMT channel on the ESP32C3.
+const CHANNEL_RAM_SIZE: usize = 48;
+
+/// Generates step pulses from an RMT transmit channel connected to the STEP pin.
+///
+/// The driver's own STEP pin is never used, so it can be constructed with
+/// [`super::driver::NoPin`] in its place.
+pub struct RmtStepGenerator<C> {
+    channel: C,
+    ticks_per_us: u32,
+}
+
+impl<C: TxChannelAsync> RmtStepGenerator<C> {
+    /// Create a generator for a channel clocked at `ticks_per_us` ticks per microsecond, with
+    /// its idle output configured low.
+    pub fn new(channel: C, ticks_per_us: u32) -> Self {
+        Self {
+            channel,
+            ticks_per_us,
+        }
+    }
+
+    /// Transmit `codes`, which must end with an end marker.
+    async fn transmit(&mut self, codes: &[u32]) -> Result<(), Error> {
+        self.channel
+            .transmit(codes)
+            .await
+            .map_err(|_| Error::Peripheral)
+    }
+}
+
+impl<C: TxChannelAsync> StepGenerator for RmtStepGenerator<C> {
+    async fn step<D: StepperDriver>(
+        &mut self,
+        driver: &mut D,
+        period_us: u32,
+    ) -> Result<(), Error> {
+        self.steps(driver, iter::once(period_us))
+            .await
+            .map(|_| ())
+            .map_err(Error::from)
+    }
+
+    async fn steps<D: StepperDriver>(
+        &mut self,
+        _driver: &mut D,
+        periods: impl IntoIterator<Item = u32>,
+    ) -> Result<u32, Interrupted> {
+        let mut encoder = RmtEncoder::new(periods, self.ticks_per_us);
+        let mut batch = [0; CHANNEL_RAM_SIZE];
+        let mut batch_len = 0;
+        // steps whose rising edge has been transmitted (each high phase is within one code)
+        let mut sent = 0;
+
+        for code in encoder.by_ref() {
+            batch[batch_len] = code;
+            batch_len += 1;
+            if code == 0 {
+                // the encoder's end marker terminates the last batch
+                if batch_len > 1 {
+                    self.transmit(&batch[..batch_len])
+                        .await
+                        .map_err(|error| Interrupted { steps: sent, error })?;
+                }
+                break;
+            }
+            if batch_len == CHANNEL_RAM_SIZE - 1 {
+                batch[batch_len] = 0;
+                self.transmit(&batch)
+                    .await
+                    .map_err(|error| Interrupted { steps: sent, error })?;
+                sent = encoder.steps();
+                batch_len = 0;
+            }
+        }
+        Ok(encoder.steps())
+    }
+}
@@ -0,0 +1,158 @@
+//! Closed-loop supervision of a stepper axis with encoder feedback
+//!
+//! The commanded position (steps counted by [`Stepper`]) is compared with the position
+//! measured by an encoder on the motor shaft. The difference is the following error:
+//! positive when the motor lags behind the commanded position in the forward direction.
+//!
+//! Small errors are expected, since the rotor lags the field under load and the encoder
+//! resolution may be coarser than a microstep. Errors beyond the correction threshold at the
+//! end of a move are missed steps, which are made up by stepping the difference. Errors beyond
+//! the fault threshold at any time indicate a stall and abort the move.
+
+use super::{Error, controller::Stepper, driver::StepperDriver, generator::StepGenerator};
+use crate::encoder::EncoderCount;
+
+/// Parameters of a [`Supervisor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct SupervisorConfig {
+    /// Steps (including microsteps) per motor revolution.
+    pub steps_per_rev: u32,
+    /// Encoder counts per motor revolution (four per encoder line).
+    pub counts_per_rev: u32,
+    /// Following error beyond which missed steps are corrected at the end of a move (steps).
+    pub correction_threshold: u32,
+    /// Following error beyond which a move is aborted (steps).
+    pub fault_threshold: u32,
+}
+
+/// Errors raised while running a supervised move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum SupervisorError {
+    /// Driving the stepper failed.
+    Stepper(Error),
+    /// The following error exceeded the fault threshold (steps).
+    FollowingError(i32),
+}
+
+impl From<Error> for SupervisorError {
+    fn from(error: Error) -> Self {
+        Self::Stepper(error)
+    }
+}
+
+/// Compares commanded and measured positions of one stepper axis.
+#[derive(Clone, Copy, Debug, defmt::Format)]
+pub struct Supervisor {
+    config: SupervisorConfig,
+    step_origin: i32,
+    count_origin: i32,
+}
+
+impl Supervisor {
+    /// Create a supervisor, with step position zero aligned to encoder count zero.
+    pub const fn new(config: SupervisorConfig) -> Self {
+        assert!(config.steps_per_rev > 0, "steps per rev must be non-zero");
+        assert!(config.counts_per_rev > 0, "counts per rev must be non-zero");
+        Self {
+            config,
+            step_origin: 0,
+            count_origin: 0,
+        }
+    }
+
+    /// Parameters of the supervisor.
+    pub const fn config(&self) -> &SupervisorConfig {
+        &self.config
+    }
+
+    /// Treat the given step position and encoder count as coinciding, e.g. after homing.
+    pub fn align(&mut self, step_position: i32, count: i32) {
+        self.step_origin = step_position;
+        self.count_origin = count;
+    }
+
+    /// Position measured by the encoder, converted to steps (rounded to the nearest step).
+    pub fn measured_steps(&self, count: i32) -> i32 {
+        let counts = i64::from(count.wrapping_sub(self.count_origin));
+        let scaled = counts * i64::from(self.config.steps_per_rev);
+        let counts_per_rev = i64::from(self.config.counts_per_rev);
+        let rounded = (scaled + scaled.signum() * counts_per_rev / 2) / counts_per_rev;
+        self.step_origin.wrapping_add(rounded as i32)
+    }
+
+    /// Commanded position minus measured position (steps).
+    pub fn following_error(&self, step_position: i32, count: i32) -> i32 {
+        step_position.wrapping_sub(self.measured_steps(count))
+    }
+
+    /// Following error, or an error if it exceeds the fault threshold.
+    pub fn check(&self, step_position: i32, count: i32) -> Result<i32, SupervisorError> {
+        let error = self.following_error(step_position, count);
+        if error.unsigned_abs() > self.config.fault_threshold {
+            Err(SupervisorError::FollowingError(error))
+        } else {
+            Ok(error)
+        }
+    }
+
+    /// Step towards the stepper's target, checking the following error after every step,
+    /// then correct any missed steps at `correction_period_us`.
+    ///
+    /// Returns the number of steps taken to correct missed steps.
+    pub async fn run<D, G>(
+        &self,
+        stepper: &mut Stepper<D, G>,
+        count: &EncoderCount,
+        periods: impl IntoIterator<Item = u32>,
+        correction_period_us: u32,
+    ) -> Result<u32, SupervisorError>
+    where
+        D: StepperDriver,
+        G: StepGenerator,
+    {
+        for period_us in periods {
+            if !stepper.step(period_us).await? {
+                break;
+            }
+            if let Err(e) = self.check(stepper.position(), count.get()) {
+                stepper.stop();
+                return Err(e);
+            }
+        }
+        self.correct(stepper, count, correction_period_us).await
+    }
+
+    /// Make up missed steps once the stepper has stopped, by redefining its position as the
+    /// measured position and stepping back to the commanded position.
+    ///
+    /// Errors within the correction threshold are left alone. Returns the number of steps
+    /// taken.
+    pub async fn correct<D, G>(
+        &self,
+        stepper: &mut Stepper<D, G>,
+        count: &EncoderCount,
+        period_us: u32,
+    ) -> Result<u32, SupervisorError>
+    where
+        D: StepperDriver,
+        G: StepGenerator,
+    {
+        let commanded = stepper.position();
+        let error = self.check(commanded, count.get())?;
+        if error.unsigned_abs() <= self.config.correction_threshold {
+            return Ok(0);
+        }
+
+        stepper.set_position(commanded.wrapping_sub(error));
+        stepper.move_to(commanded)?;
+        let mut taken = 0;
+        while stepper.step(period_us).await? {
+            taken += 1;
+            if let Err(e) = self.check(stepper.position(), count.get()) {
+                stepper.stop();
+                return Err(e);
+            }
+        }
+        Ok(taken)
+    }
+}
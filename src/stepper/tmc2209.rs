@@ -0,0 +1,501 @@
+//! Configuration of a TMC2209 over its single-wire UART interface
+//!
+//! The TMC2209 still receives STEP and DIR pulses from [`super::driver::StepDir`], while the
+//! UART (on the PDN_UART pin) sets motor current, microstep resolution and chopper mode, and
+//! reports driver status and StallGuard readings.
+//!
+//! TX and RX share the PDN_UART line (TX via a 1 kΩ resistor), so every byte sent is also
+//! received. [`Tmc2209Uart`] reads back and checks this echo before each reply.
+
+use embassy_time::{Duration, with_timeout};
+use embedded_io_async::{Read, Write};
+
+/// Sync byte starting every frame (including the reserved bits).
+const SYNC: u8 = 0x05;
+
+/// Address used by the driver in its replies to the master.
+const MASTER_ADDRESS: u8 = 0xff;
+
+/// Register address flag marking a write access.
+const WRITE_FLAG: u8 = 0x80;
+
+/// Maximum time for the driver to reply to a request.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(20);
+
+/// Length of a read request frame.
+pub const READ_REQUEST_LEN: usize = 4;
+
+/// Length of a write frame, and of the driver's reply to a read request.
+pub const DATA_FRAME_LEN: usize = 8;
+
+/// Registers accessible over UART.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+#[repr(u8)]
+pub enum Register {
+    /// Global configuration flags (read/write).
+    Gconf = 0x00,
+    /// Global status flags, cleared by writing 1 (read/write).
+    Gstat = 0x01,
+    /// Count of successful UART writes (read only).
+    Ifcnt = 0x02,
+    /// Reply delay (write only).
+    Slaveconf = 0x03,
+    /// Input pin states and chip version (read only).
+    Ioin = 0x06,
+    /// Run and hold current (write only).
+    IholdIrun = 0x10,
+    /// Delay before reducing to hold current (write only).
+    Tpowerdown = 0x11,
+    /// Measured time between microsteps (read only).
+    Tstep = 0x12,
+    /// Upper TSTEP threshold for StealthChop (write only).
+    Tpwmthrs = 0x13,
+    /// Lower TSTEP threshold for CoolStep and the StallGuard DIAG output (write only).
+    Tcoolthrs = 0x14,
+    /// Velocity for motion driven by the internal pulse generator (write only).
+    Vactual = 0x22,
+    /// StallGuard detection threshold (write only).
+    Sgthrs = 0x40,
+    /// StallGuard load measurement (read only).
+    SgResult = 0x41,
+    /// CoolStep configuration (write only).
+    Coolconf = 0x42,
+    /// Microstep counter (read only).
+    Mscnt = 0x6a,
+    /// Chopper and microstep configuration (read/write).
+    Chopconf = 0x6c,
+    /// Driver status flags (read only).
+    DrvStatus = 0x6f,
+    /// StealthChop PWM configuration (read/write).
+    Pwmconf = 0x70,
+}
+
+/// Errors raised while communicating with a TMC2209.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum UartError {
+    /// Reading or writing the UART failed.
+    Io,
+    /// The driver did not reply within the timeout.
+    Timeout,
+    /// The echo of a sent frame did not match the frame.
+    Echo,
+    /// A reply had an invalid sync byte, address or register.
+    UnexpectedReply,
+    /// A reply failed its CRC check.
+    Crc,
+    /// The driver has no microstep mode with this divisor.
+    UnsupportedMicrosteps(u16),
+    /// The driver cannot run at this current (mA RMS).
+    UnsupportedCurrent(u32),
+}
+
+/// CRC8 of a frame (polynomial x⁸ + x² + x + 1), with each byte processed LSB first.
+pub const fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    let mut i = 0;
+    while i < bytes.len() {
+        let mut byte = bytes[i];
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if (crc >> 7) ^ (byte & 0x01) != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+            byte >>= 1;
+            bit += 1;
+        }
+        i += 1;
+    }
+    crc
+}
+
+/// Encode a frame writing `value` to a register of the driver at `address` (0 to 3).
+pub const fn write_frame(address: u8, register: Register, value: u32) -> [u8; DATA_FRAME_LEN] {
+    let data = value.to_be_bytes();
+    let mut frame = [
+        SYNC,
+        address,
+        register as u8 | WRITE_FLAG,
+        data[0],
+        data[1],
+        data[2],
+        data[3],
+        0,
+    ];
+    let (payload, _) = frame.split_at(DATA_FRAME_LEN - 1);
+    frame[DATA_FRAME_LEN - 1] = crc8(payload);
+    frame
+}
+
+/// Encode a frame requesting the value of a register from the driver at `address` (0 to 3).
+pub const fn read_request(address: u8, register: Register) -> [u8; READ_REQUEST_LEN] {
+    let mut frame = [SYNC, address, register as u8, 0];
+    let (payload, _) = frame.split_at(READ_REQUEST_LEN - 1);
+    frame[READ_REQUEST_LEN - 1] = crc8(payload);
+    frame
+}
+
+/// Decode the driver's reply to a read request for `register`, returning the register value.
+pub fn parse_reply(frame: &[u8; DATA_FRAME_LEN], register: Register) -> Result<u32, UartError> {
+    if frame[DATA_FRAME_LEN - 1] != crc8(&frame[..DATA_FRAME_LEN - 1]) {
+        return Err(UartError::Crc);
+    }
+    if frame[0] & 0x0f != SYNC || frame[1] != MASTER_ADDRESS || frame[2] != register as u8 {
+        return Err(UartError::UnexpectedReply);
+    }
+    Ok(u32::from_be_bytes([frame[3], frame[4], frame[5], frame[6]]))
+}
+
+/// Flags of the GCONF register.
+pub mod gconf {
+    /// Scale current from the VREF pin rather than internally.
+    pub const I_SCALE_ANALOG: u32 = 1 << 0;
+    /// Use the internal sense resistors.
+    pub const INTERNAL_RSENSE: u32 = 1 << 1;
+    /// Use SpreadCycle rather than StealthChop.
+    pub const EN_SPREAD_CYCLE: u32 = 1 << 2;
+    /// Invert the motor direction.
+    pub const SHAFT: u32 = 1 << 3;
+    /// Disable the PDN function of the PDN_UART pin, which is required for UART access.
+    pub const PDN_DISABLE: u32 = 1 << 6;
+    /// Select the microstep resolution with CHOPCONF.MRES rather than the MS1/MS2 pins.
+    pub const MSTEP_REG_SELECT: u32 = 1 << 7;
+    /// Filter the STEP pulse timing used by the driver's velocity measurements.
+    pub const MULTISTEP_FILT: u32 = 1 << 8;
+}
+
+/// Run and hold current settings (IHOLD_IRUN register).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct IholdIrun {
+    /// Standstill current scale, 0 to 31 (each step is 1/32 of full scale).
+    pub hold: u8,
+    /// Motor run current scale, 0 to 31.
+    pub run: u8,
+    /// Number of 2¹⁸ clock periods per step of the reduction to hold current, 0 to 15.
+    pub hold_delay: u8,
+}
+
+impl IholdIrun {
+    /// Power on defaults for UART controlled drivers.
+    pub const DEFAULT: Self = Self {
+        hold: 16,
+        run: 31,
+        hold_delay: 1,
+    };
+
+    /// Encode the register value.
+    pub const fn bits(&self) -> u32 {
+        (self.hold as u32 & 0x1f)
+            | (self.run as u32 & 0x1f) << 8
+            | (self.hold_delay as u32 & 0x0f) << 16
+    }
+}
+
+/// Chopper configuration (CHOPCONF register).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct Chopconf(pub u32);
+
+impl Chopconf {
+    /// Power on default: TOFF = 3, HSTRT = 5, TBL = 0, full scale sense voltage, 1/256
+    /// microsteps with interpolation.
+    pub const DEFAULT: Self = Self(0x1000_0053);
+
+    const VSENSE: u32 = 1 << 17;
+    const MRES_SHIFT: u32 = 24;
+    const MRES_MASK: u32 = 0x0f << Self::MRES_SHIFT;
+
+    /// Microstep divisor, from 1 (full steps) to 256.
+    pub const fn microsteps(&self) -> u16 {
+        let mres = (self.0 & Self::MRES_MASK) >> Self::MRES_SHIFT;
+        match mres {
+            0..=8 => 256 >> mres,
+            _ => 1,
+        }
+    }
+
+    /// The configuration with a different microstep divisor, or `None` if unsupported.
+    pub const fn with_microsteps(self, microsteps: u16) -> Option<Self> {
+        if !microsteps.is_power_of_two() || microsteps > 256 {
+            return None;
+        }
+        let mres = 8 - microsteps.trailing_zeros();
+        Some(Self(
+            (self.0 & !Self::MRES_MASK) | (mres << Self::MRES_SHIFT),
+        ))
+    }
+
+    /// Whether the reduced (high sensitivity) sense voltage range is selected.
+    pub const fn vsense(&self) -> bool {
+        self.0 & Self::VSENSE != 0
+    }
+
+    /// The configuration with the given sense voltage range.
+    pub const fn with_vsense(self, vsense: bool) -> Self {
+        if vsense {
+            Self(self.0 | Self::VSENSE)
+        } else {
+            Self(self.0 & !Self::VSENSE)
+        }
+    }
+}
+
+/// Driver status flags (DRV_STATUS register).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub struct DrvStatus {
+    /// Overtemperature pre-warning (120 °C).
+    pub overtemperature_warning: bool,
+    /// Overtemperature shutdown (150 °C).
+    pub overtemperature: bool,
+    /// Short to ground on phase A or B.
+    pub short_to_ground: [bool; 2],
+    /// Short to supply (low side short) on phase A or B.
+    pub short_to_supply: [bool; 2],
+    /// Open load on phase A or B.
+    pub open_load: [bool; 2],
+    /// Current scale in use, 0 to 31.
+    pub current_scale: u8,
+    /// Whether the driver is in StealthChop mode.
+    pub stealth: bool,
+    /// Whether the motor is at standstill.
+    pub standstill: bool,
+}
+
+impl DrvStatus {
+    /// Decode the register value.
+    pub fn from_bits(bits: u32) -> Self {
+        let bit = |n: u32| bits & (1 << n) != 0;
+        Self {
+            overtemperature_warning: bit(0),
+            overtemperature: bit(1),
+            short_to_ground: [bit(2), bit(3)],
+            short_to_supply: [bit(4), bit(5)],
+            open_load: [bit(6), bit(7)],
+            current_scale: ((bits >> 16) & 0x1f) as u8,
+            stealth: bit(30),
+            standstill: bit(31),
+        }
+    }
+
+    /// Whether any condition which disables the driver outputs is present.
+    pub const fn is_faulted(&self) -> bool {
+        self.overtemperature
+            || self.short_to_ground[0]
+            || self.short_to_ground[1]
+            || self.short_to_supply[0]
+            || self.short_to_supply[1]
+    }
+}
+
+/// Chopper mode, selecting between quiet and high torque operation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum ChopperMode {
+    /// Voltage chopper, quiet at low to moderate speeds. Required for StallGuard.
+    StealthChop,
+    /// Cycle-by-cycle current chopper, with higher torque at speed.
+    SpreadCycle,
+}
+
+/// Convert an RMS current to a current scale and sense voltage range.
+///
+/// I_rms = (CS + 1) / 32 × V_fs / (R_sense + 20 mΩ) / √2, where V_fs is 325 mV, or 180 mV
+/// with VSENSE set. VSENSE is used when it gives finer resolution at the requested current.
+pub const fn current_scale(current_ma: u32, sense_resistor_mohm: u32) -> (u8, bool) {
+    const fn scale(current_ma: u32, sense_resistor_mohm: u32, full_scale_mv: u64) -> u64 {
+        // CS + 1 = 32 × √2 × I × (R + 20 mΩ) / V_fs, with √2 ≈ 1414 / 1000
+        let numerator = 32 * 1414 * current_ma as u64 * (sense_resistor_mohm as u64 + 20);
+        let denominator = 1_000_000 * full_scale_mv;
+        (numerator + denominator / 2) / denominator
+    }
+
+    let high_sensitivity = scale(current_ma, sense_resistor_mohm, 180);
+    let (steps, vsense) = if high_sensitivity <= 32 {
+        (high_sensitivity, true)
+    } else {
+        (scale(current_ma, sense_resistor_mohm, 325), false)
+    };
+    let steps = if steps > 32 { 32 } else { steps };
+    (steps.saturating_sub(1) as u8, vsense)
+}
+
+/// TMC2209 configured over UART, keeping copies of its write only registers.
+pub struct Tmc2209Uart<U> {
+    uart: U,
+    address: u8,
+    sense_resistor_mohm: u32,
+    gconf: u32,
+    chopconf: Chopconf,
+    ihold_irun: IholdIrun,
+}
+
+impl<U: Read + Write> Tmc2209Uart<U> {
+    /// Create a driver for the chip at `address` (0 to 3, set by the MS1/MS2 pins).
+    ///
+    /// Nothing is sent until [`Tmc2209Uart::init`] is called.
+    pub fn new(uart: U, address: u8) -> Self {
+        assert!(address <= 3, "TMC2209 address must be 0 to 3");
+        Self {
+            uart,
+            address,
+            sense_resistor_mohm: 110,
+            gconf: gconf::PDN_DISABLE | gconf::MSTEP_REG_SELECT | gconf::MULTISTEP_FILT,
+            chopconf: Chopconf::DEFAULT,
+            ihold_irun: IholdIrun::DEFAULT,
+        }
+    }
+
+    /// Set the value of the external sense resistors (defaults to 110 mΩ, as fitted to most
+    /// breakout boards).
+    pub fn with_sense_resistor(mut self, sense_resistor_mohm: u32) -> Self {
+        self.sense_resistor_mohm = sense_resistor_mohm;
+        self
+    }
+
+    /// Take control of the driver over UART, writing the current configuration and clearing
+    /// any latched status flags.
+    ///
+    /// Microsteps are then set by [`Tmc2209Uart::set_microsteps`] rather than the MS1/MS2 pins.
+    pub async fn init(&mut self) -> Result<(), UartError> {
+        self.write_register(Register::Gstat, 0b111).await?;
+        self.write_register(Register::Gconf, self.gconf).await?;
+        self.write_register(Register::Chopconf, self.chopconf.0)
+            .await?;
+        self.write_register(Register::IholdIrun, self.ihold_irun.bits())
+            .await
+    }
+
+    /// Chip version (0x21 for the TMC2209), useful for checking the connection.
+    pub async fn version(&mut self) -> Result<u8, UartError> {
+        let ioin = self.read_register(Register::Ioin).await?;
+        Ok((ioin >> 24) as u8)
+    }
+
+    /// Set the run and hold currents (mA RMS).
+    ///
+    /// Both currents share one sense voltage range, chosen to suit the run current, and the
+    /// hold current is at least 1/32 of full scale. The lowest current scale is still 1/32
+    /// of full scale, so a zero run current is rejected (disable the driver instead).
+    pub async fn set_current(&mut self, run_ma: u32, hold_ma: u32) -> Result<(), UartError> {
+        if run_ma == 0 {
+            return Err(UartError::UnsupportedCurrent(run_ma));
+        }
+        let (run, vsense) = current_scale(run_ma, self.sense_resistor_mohm);
+        let full_scale_ma = (run_ma * 32).div_ceil(u32::from(run) + 1);
+        let hold = ((hold_ma * 32).div_ceil(full_scale_ma)).clamp(1, 32) - 1;
+
+        let chopconf = self.chopconf.with_vsense(vsense);
+        if chopconf != self.chopconf {
+            self.write_register(Register::Chopconf, chopconf.0).await?;
+            self.chopconf = chopconf;
+        }
+        let ihold_irun = IholdIrun {
+            hold: hold as u8,
+            run,
+            ..self.ihold_irun
+        };
+        self.write_register(Register::IholdIrun, ihold_irun.bits())
+            .await?;
+        self.ihold_irun = ihold_irun;
+        Ok(())
+    }
+
+    /// Current microstep divisor.
+    pub fn microsteps(&self) -> u16 {
+        self.chopconf.microsteps()
+    }
+
+    /// Set the microstep divisor (a power of two from 1 to 256).
+    pub async fn set_microsteps(&mut self, microsteps: u16) -> Result<(), UartError> {
+        let chopconf = self
+            .chopconf
+            .with_microsteps(microsteps)
+            .ok_or(UartError::UnsupportedMicrosteps(microsteps))?;
+        self.write_register(Register::Chopconf, chopconf.0).await?;
+        self.chopconf = chopconf;
+        Ok(())
+    }
+
+    /// Select StealthChop or SpreadCycle.
+    pub async fn set_chopper_mode(&mut self, mode: ChopperMode) -> Result<(), UartError> {
+        let gconf = match mode {
+            ChopperMode::StealthChop => self.gconf & !gconf::EN_SPREAD_CYCLE,
+            ChopperMode::SpreadCycle => self.gconf | gconf::EN_SPREAD_CYCLE,
+        };
+        self.write_register(Register::Gconf, gconf).await?;
+        self.gconf = gconf;
+        Ok(())
+    }
+
+    /// Read the driver status flags.
+    pub async fn drv_status(&mut self) -> Result<DrvStatus, UartError> {
+        let bits = self.read_register(Register::DrvStatus).await?;
+        Ok(DrvStatus::from_bits(bits))
+    }
+
+    /// Set the StallGuard threshold. A stall is signalled on DIAG when the StallGuard result
+    /// falls below twice this value.
+    pub async fn set_stallguard_threshold(&mut self, threshold: u8) -> Result<(), UartError> {
+        self.write_register(Register::Sgthrs, threshold.into())
+            .await
+    }
+
+    /// Set the TSTEP value above which (i.e. below the corresponding velocity) StallGuard
+    /// and CoolStep are disabled. StallGuard is inactive while this is zero.
+    pub async fn set_coolstep_threshold(&mut self, tstep: u32) -> Result<(), UartError> {
+        self.write_register(Register::Tcoolthrs, tstep & 0xf_ffff)
+            .await
+    }
+
+    /// Read the StallGuard load measurement (0 to 510, lower values mean higher load).
+    ///
+    /// Only valid in StealthChop mode while the motor is moving.
+    pub async fn stallguard_result(&mut self) -> Result<u16, UartError> {
+        let result = self.read_register(Register::SgResult).await?;
+        Ok((result & 0x3ff) as u16)
+    }
+
+    /// Read a register.
+    pub async fn read_register(&mut self, register: Register) -> Result<u32, UartError> {
+        let request = read_request(self.address, register);
+        let mut response = [0; READ_REQUEST_LEN + DATA_FRAME_LEN];
+        self.transfer(&request, &mut response).await?;
+
+        let (echo, reply) = response.split_at(READ_REQUEST_LEN);
+        if echo != request {
+            return Err(UartError::Echo);
+        }
+        let mut frame = [0; DATA_FRAME_LEN];
+        frame.copy_from_slice(reply);
+        parse_reply(&frame, register)
+    }
+
+    /// Write a register. The driver does not acknowledge writes, but a successful write
+    /// increments the IFCNT register.
+    pub async fn write_register(
+        &mut self,
+        register: Register,
+        value: u32,
+    ) -> Result<(), UartError> {
+        let frame = write_frame(self.address, register, value);
+        let mut echo = [0; DATA_FRAME_LEN];
+        self.transfer(&frame, &mut echo).await?;
+        if echo == frame {
+            Ok(())
+        } else {
+            Err(UartError::Echo)
+        }
+    }
+
+    /// Send a frame and read back the given number of bytes (echo and reply).
+    async fn transfer(&mut self, frame: &[u8], response: &mut [u8]) -> Result<(), UartError> {
+        self.uart
+            .write_all(frame)
+            .await
+            .map_err(|_| UartError::Io)?;
+        self.uart.flush().await.map_err(|_| UartError::Io)?;
+        with_timeout(REPLY_TIMEOUT, self.uart.read_exact(response))
+            .await
+            .map_err(|_| UartError::Timeout)?
+            .map_err(|_| UartError::Io)
+    }
+}
@@ -0,0 +1,203 @@
+//! Four-phase unipolar stepper (e.g. 28BYJ-48) driven through a ULN2003 darlington array
+//!
+//! There is no step/dir interface: each coil is switched directly from a GPIO (IN1-IN4 on the
+//! ULN2003 board). [`Uln2003`] implements [`StepperDriver`] by advancing through the coil
+//! sequence on each rising edge of the (virtual) STEP signal, so it can be used with
+//! [`Stepper`](super::controller::Stepper), any software step generator and the motion
+//! profiles like a step/dir driver.
+//!
+//! All step modes walk the same eight entry half-step sequence: wave drive uses the single
+//! coil entries, full step the two coil entries, and half step every entry.
+
+use embedded_hal::digital::OutputPin;
+
+use super::{
+    Direction, Error,
+    axis::GearRatio,
+    driver::{StepperDriver, Timing, set_level},
+};
+
+/// Full steps per revolution of the 28BYJ-48 motor, before its gearbox.
+pub const MOTOR_STEPS_PER_REV_28BYJ48: u32 = 32;
+
+/// Gearbox of the 28BYJ-48 (32/9 × 22/11 × 26/9 × 31/10, about 63.68:1).
+pub const GEAR_RATIO_28BYJ48: GearRatio = GearRatio::new(25_792, 405);
+
+/// Coils energised (IN1-IN4) at each position of the half-step sequence.
+const HALF_STEP_SEQUENCE: [[bool; 4]; 8] = [
+    [true, false, false, false],
+    [true, true, false, false],
+    [false, true, false, false],
+    [false, true, true, false],
+    [false, false, true, false],
+    [false, false, true, true],
+    [false, false, false, true],
+    [true, false, false, true],
+];
+
+/// Coil energising sequence.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, defmt::Format)]
+pub enum StepMode {
+    /// One coil at a time (lowest current and torque).
+    Wave,
+    /// Two coils at a time (full torque).
+    Full,
+    /// Alternating one and two coils, doubling the steps per revolution.
+    Half,
+}
+
+impl StepMode {
+    /// Steps per full step, reported as the microstep divisor.
+    pub const fn divisor(self) -> u16 {
+        match self {
+            Self::Wave | Self::Full => 1,
+            Self::Half => 2,
+        }
+    }
+
+    /// Whether `index` of the half-step sequence belongs to this mode.
+    const fn contains(self, index: usize) -> bool {
+        match self {
+            Self::Wave => index.is_multiple_of(2),
+            Self::Full => !index.is_multiple_of(2),
+            Self::Half => true,
+        }
+    }
+}
+
+/// Timing of the ULN2003, which switches the coils directly from its inputs.
+const TIMING: Timing = Timing {
+    min_pulse_width_ns: 0,
+    dir_setup_ns: 0,
+    wake_up_us: 0,
+};
+
+/// Unipolar stepper driven through a ULN2003, with coil outputs IN1-IN4.
+pub struct Uln2003<P> {
+    pins: [P; 4],
+    mode: StepMode,
+    /// Position in the half-step sequence.
+    index: usize,
+    direction: Direction,
+    energised: bool,
+}
+
+impl<P: OutputPin> Uln2003<P> {
+    /// Create a driver from the pins connected to IN1-IN4, with the coils off.
+    pub fn new(pins: [P; 4], mode: StepMode) -> Self {
+        Self {
+            pins,
+            mode,
+            index: if mode.contains(0) { 0 } else { 1 },
+            direction: Direction::Forward,
+            energised: false,
+        }
+    }
+
+    /// Current coil energising sequence.
+    pub fn mode(&self) -> StepMode {
+        self.mode
+    }
+
+    /// Change the coil energising sequence.
+    ///
+    /// Switching to wave or full step from a position of the other moves the rotor by half a
+    /// step on the next step.
+    pub fn set_mode(&mut self, mode: StepMode) {
+        self.mode = mode;
+    }
+
+    /// Energise the coils for the current position in the sequence.
+    fn write_phase(&mut self) -> Result<(), Error> {
+        for (pin, high) in self.pins.iter_mut().zip(HALF_STEP_SEQUENCE[self.index]) {
+            set_level(pin, high)?;
+        }
+        self.energised = true;
+        Ok(())
+    }
+
+    /// Switch all coils off.
+    fn release(&mut self) -> Result<(), Error> {
+        for pin in &mut self.pins {
+            set_level(pin, false)?;
+        }
+        self.energised = false;
+        Ok(())
+    }
+}
+
+impl<P: OutputPin> StepperDriver for Uln2003<P> {
+    fn timing(&self) -> Timing {
+        TIMING
+    }
+
+    fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    fn set_direction(&mut self, direction: Direction) -> Result<(), Error> {
+        self.direction = direction;
+        Ok(())
+    }
+
+    /// Advance one step in the current direction on the rising edge.
+    fn set_step(&mut self, high: bool) -> Result<(), Error> {
+        if !high {
+            return Ok(());
+        }
+        let len = HALF_STEP_SEQUENCE.len();
+        let offset = match self.direction {
+            Direction::Forward => 1,
+            Direction::Reverse => len - 1,
+        };
+        // one entry for half step, or on to the next entry of the mode (skipping at most one)
+        self.index = (self.index + offset) % len;
+        if !self.mode.contains(self.index) {
+            self.index = (self.index + offset) % len;
+        }
+        self.write_phase()
+    }
+
+    fn microsteps(&self) -> u16 {
+        self.mode.divisor()
+    }
+
+    /// Select full (1) or half (2) step mode. Use [`Uln2003::set_mode`] for wave drive.
+    fn set_microsteps(&mut self, microsteps: u16) -> Result<(), Error> {
+        self.mode = match microsteps {
+            1 => StepMode::Full,
+            2 => StepMode::Half,
+            _ => return Err(Error::UnsupportedMicrosteps(microsteps)),
+        };
+        Ok(())
+    }
+
+    /// Energise the coils at the current position, or switch them all off.
+    fn set_enabled(&mut self, enabled: bool) -> Result<(), Error> {
+        if !enabled {
+            self.release()
+        } else if !self.energised {
+            self.write_phase()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Equivalent to disabling, since the ULN2003 has no sleep mode.
+    fn set_sleep(&mut self, asleep: bool) -> Result<(), Error> {
+        self.set_enabled(!asleep)
+    }
+
+    /// Switch the coils off and return to the start of the sequence.
+    fn set_reset(&mut self, reset: bool) -> Result<(), Error> {
+        if reset {
+            self.release()?;
+            self.index = if self.mode.contains(0) { 0 } else { 1 };
+        }
+        Ok(())
+    }
+
+    fn is_faulted(&mut self) -> Result<bool, Error> {
+        Ok(false)
+    }
+}
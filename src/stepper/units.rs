@@ -0,0 +1,194 @@
+//! Typed quantities for step/dir motion parameters
+//!
+//! Conversions between these types are `const fn`s which panic rather than truncating or
+//! overflowing. Evaluating them in `const` items turns invalid parameter combinations (zero
+//! speeds, step intervals too short for the driver, overflowing step counts) into build
+//! errors:
+//!
+//! ```ignore
+//! const MICROSTEPS: Microsteps = Microsteps::new(8).checked_for::<Drv8825>();
+//! const STEPS_PER_REV: Steps = MICROSTEPS.steps_per_rev(200);
+//! const INTERVAL: StepInterval =
+//!     StepInterval::from_rpm(Rpm::new(320), STEPS_PER_REV).checked_for::<Drv8825>();
+//! ```
+
+use super::driver::DriverChip;
+use crate::motion::{US_PER_MIN, US_PER_SEC};
+
+/// Unwrap the result of a checked operation in a const context.
+macro_rules! checked {
+    ($value:expr, $message:literal) => {
+        match $value {
+            Some(value) => value,
+            None => panic!($message),
+        }
+    };
+}
+
+/// Rotational speed (revolutions per minute).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub struct Rpm(u32);
+
+impl Rpm {
+    /// Create a non-zero speed.
+    pub const fn new(rpm: u32) -> Self {
+        assert!(rpm > 0, "rpm must be non-zero");
+        Self(rpm)
+    }
+
+    /// Speed in revolutions per minute.
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Step rate at this speed (steps/s), rounded down.
+    pub const fn steps_per_sec(self, steps_per_rev: Steps) -> u32 {
+        let steps_per_min = checked!(
+            self.0.checked_mul(steps_per_rev.0),
+            "steps per minute overflows u32"
+        );
+        let velocity = steps_per_min / 60;
+        assert!(velocity > 0, "step rate rounds down to zero steps/s");
+        velocity
+    }
+}
+
+/// A number of (micro)steps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub struct Steps(u32);
+
+impl Steps {
+    /// Create a step count.
+    pub const fn new(steps: u32) -> Self {
+        Self(steps)
+    }
+
+    /// Number of steps.
+    pub const fn get(self) -> u32 {
+        self.0
+    }
+
+    /// This count multiplied by `factor`, e.g. steps per revolution times revolutions.
+    pub const fn times(self, factor: u32) -> Self {
+        Self(checked!(
+            self.0.checked_mul(factor),
+            "step count overflows u32"
+        ))
+    }
+
+    /// This count as a signed relative move.
+    pub const fn as_i32(self) -> i32 {
+        assert!(self.0 <= i32::MAX as u32, "step count overflows i32");
+        self.0 as i32
+    }
+}
+
+/// Microstep divisor (e.g. 8 for 1/8th steps).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub struct Microsteps(u16);
+
+impl Microsteps {
+    /// Full steps.
+    pub const FULL: Self = Self(1);
+
+    /// Create a divisor, which must be a power of two from 1 to 256.
+    pub const fn new(divisor: u16) -> Self {
+        assert!(
+            divisor.is_power_of_two() && divisor <= 256,
+            "microstep divisor must be a power of two from 1 to 256"
+        );
+        Self(divisor)
+    }
+
+    /// Microstep divisor.
+    pub const fn get(self) -> u16 {
+        self.0
+    }
+
+    /// Check that the driver chip supports this divisor.
+    pub const fn checked_for<C: DriverChip>(self) -> Self {
+        let mut i = 0;
+        while i < C::MICROSTEPS.len() {
+            if C::MICROSTEPS[i].0 == self.0 {
+                return self;
+            }
+            i += 1;
+        }
+        panic!("microstep divisor not supported by the driver chip")
+    }
+
+    /// Microsteps per revolution of a motor with `full_steps` steps per revolution.
+    pub const fn steps_per_rev(self, full_steps: u32) -> Steps {
+        assert!(full_steps > 0, "full steps per rev must be non-zero");
+        Steps(checked!(
+            full_steps.checked_mul(self.0 as u32),
+            "steps per rev overflows u32"
+        ))
+    }
+}
+
+/// Time between consecutive step pulses at a constant speed (µs).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, defmt::Format)]
+pub struct StepInterval(u32);
+
+impl StepInterval {
+    /// Create a non-zero interval.
+    pub const fn from_micros(us: u32) -> Self {
+        assert!(us > 0, "step interval must be non-zero");
+        Self(us)
+    }
+
+    /// Interval at the given step rate (steps/s), rounded up so the rate is not exceeded.
+    pub const fn from_velocity(steps_per_sec: u32) -> Self {
+        assert!(steps_per_sec > 0, "step rate must be non-zero");
+        let us = US_PER_SEC.div_ceil(steps_per_sec as u64);
+        Self::from_micros(us as u32)
+    }
+
+    /// Interval when rotating at `rpm`, rounded down.
+    pub const fn from_rpm(rpm: Rpm, steps_per_rev: Steps) -> Self {
+        let steps_per_min = rpm.0 as u64 * steps_per_rev.0 as u64;
+        assert!(steps_per_min > 0, "steps per rev must be non-zero");
+        let us = US_PER_MIN / steps_per_min;
+        assert!(us > 0, "step interval rounds down to zero µs");
+        Self(us as u32)
+    }
+
+    /// Interval in microseconds.
+    pub const fn as_micros(self) -> u32 {
+        self.0
+    }
+
+    /// Duration of the high phase of a 50% duty STEP pulse (µs).
+    pub const fn high_us(self) -> u32 {
+        self.0 / 2
+    }
+
+    /// Duration of the low phase of a 50% duty STEP pulse, taking any odd microsecond (µs).
+    pub const fn low_us(self) -> u32 {
+        self.0 - self.high_us()
+    }
+
+    /// Step rate at this interval (steps/s), rounded down.
+    pub const fn velocity(self) -> u32 {
+        let velocity = US_PER_SEC / self.0 as u64;
+        assert!(velocity > 0, "step rate rounds down to zero steps/s");
+        velocity as u32
+    }
+
+    /// Check that both phases of a 50% duty STEP pulse meet the driver chip's minimum
+    /// pulse width.
+    pub const fn checked_for<C: DriverChip>(self) -> Self {
+        assert!(
+            self.high_us() as u64 * 1_000 >= C::TIMING.min_pulse_width_ns as u64,
+            "step interval is shorter than the driver's minimum pulse width"
+        );
+        self
+    }
+
+    /// Total time to take `steps` steps at this interval (µs).
+    pub const fn run_time_us(self, steps: Steps) -> u64 {
+        // cannot overflow: both factors fit in u32
+        self.0 as u64 * steps.0 as u64
+    }
+}
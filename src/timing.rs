@@ -0,0 +1,141 @@
+//! Statistics for step timing jitter
+//!
+//! Each sample is the deviation of a measured interval from the interval that was requested,
+//! so that profiles with varying step periods can be summarised together. Only running totals
+//! and a fixed size histogram are kept, allowing every step of a long move to be recorded
+//! without buffering the raw samples.
+
+/// Running statistics of timing deviations, with a histogram of `BINS` bins.
+///
+/// The histogram is centred on zero deviation: bin `BINS / 2` holds deviations in
+/// `0..bin_width_us`, the bin below it `-bin_width_us..0`, and so on. The outermost bins also
+/// collect any deviations beyond the histogram range.
+#[derive(Clone, Debug)]
+pub struct JitterStats<const BINS: usize> {
+    bin_width_us: u32,
+    overrun_us: u32,
+    count: u32,
+    min: i32,
+    max: i32,
+    sum: i64,
+    sum_sq: u64,
+    overruns: u32,
+    histogram: [u32; BINS],
+}
+
+impl<const BINS: usize> JitterStats<BINS> {
+    /// Create empty statistics.
+    ///
+    /// Samples exceeding the expected interval by more than `overrun_us` are counted as
+    /// overruns.
+    pub const fn new(bin_width_us: u32, overrun_us: u32) -> Self {
+        const { assert!(BINS > 0, "histogram needs at least one bin") };
+        assert!(bin_width_us > 0, "bin width must be non-zero");
+        Self {
+            bin_width_us,
+            overrun_us,
+            count: 0,
+            min: i32::MAX,
+            max: i32::MIN,
+            sum: 0,
+            sum_sq: 0,
+            overruns: 0,
+            histogram: [0; BINS],
+        }
+    }
+
+    /// Discard all samples, e.g. at the start of a new move.
+    pub fn reset(&mut self) {
+        *self = Self::new(self.bin_width_us, self.overrun_us);
+    }
+
+    /// Record a measured interval against the expected interval (µs).
+    pub fn record(&mut self, expected_us: u64, actual_us: u64) {
+        let deviation = actual_us as i64 - expected_us as i64;
+        self.record_deviation(deviation.clamp(i32::MIN.into(), i32::MAX.into()) as i32);
+    }
+
+    /// Record the deviation of an interval from its expected value (µs).
+    pub fn record_deviation(&mut self, deviation_us: i32) {
+        self.count = self.count.saturating_add(1);
+        self.min = self.min.min(deviation_us);
+        self.max = self.max.max(deviation_us);
+        self.sum = self.sum.saturating_add(deviation_us.into());
+        let magnitude = u64::from(deviation_us.unsigned_abs());
+        self.sum_sq = self.sum_sq.saturating_add(magnitude * magnitude);
+        if deviation_us > 0 && deviation_us.unsigned_abs() > self.overrun_us {
+            self.overruns = self.overruns.saturating_add(1);
+        }
+
+        let offset = i64::from(deviation_us).div_euclid(self.bin_width_us.into());
+        let bin = (offset + (BINS / 2) as i64).clamp(0, BINS as i64 - 1);
+        self.histogram[bin as usize] = self.histogram[bin as usize].saturating_add(1);
+    }
+
+    /// Number of samples recorded.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Smallest deviation (µs), or `None` if no samples have been recorded.
+    pub fn min(&self) -> Option<i32> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    /// Largest deviation (µs), or `None` if no samples have been recorded.
+    pub fn max(&self) -> Option<i32> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    /// Mean deviation (µs), rounded towards zero.
+    pub fn mean(&self) -> i32 {
+        match self.count {
+            0 => 0,
+            count => (self.sum / i64::from(count)) as i32,
+        }
+    }
+
+    /// Population standard deviation of the deviations (µs), rounded down.
+    pub fn std_dev(&self) -> u32 {
+        if self.count == 0 {
+            return 0;
+        }
+        // variance = E[x²] - E[x]², scaled by n² to stay in integers
+        let count = u128::from(self.count);
+        let sum = self.sum.unsigned_abs() as u128;
+        let scaled_variance = (u128::from(self.sum_sq) * count).saturating_sub(sum * sum);
+        ((scaled_variance / (count * count)) as u64).isqrt() as u32
+    }
+
+    /// Number of samples which exceeded the expected interval by more than the overrun limit.
+    pub fn overruns(&self) -> u32 {
+        self.overruns
+    }
+
+    /// Sample count of each histogram bin, from most negative to most positive deviation.
+    pub fn histogram(&self) -> &[u32; BINS] {
+        &self.histogram
+    }
+
+    /// Width of each histogram bin (µs).
+    pub fn bin_width_us(&self) -> u32 {
+        self.bin_width_us
+    }
+}
+
+/// One line summary, e.g. `n=6400 min=-4 max=11 mean=0 sd=2 overruns=0 hist=[0, 3, ...]`.
+impl<const BINS: usize> defmt::Format for JitterStats<BINS> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "n={} min={} max={} mean={} sd={} overruns={} hist={}",
+            self.count,
+            self.min().unwrap_or(0),
+            self.max().unwrap_or(0),
+            self.mean(),
+            self.std_dev(),
+            self.overruns,
+            self.histogram.as_slice(),
+        );
+    }
+}